@@ -0,0 +1,354 @@
+//! Analysis queries on trained graphs.
+//!
+//! These read what a [`MonteCarloGraph`] has learned about positions of a game: the
+//! most visited line from a position, the best explored moves with confidence
+//! intervals, and positions where the graph disagrees with a reference such as
+//! [`ConnectFourSolver`](crate::agents::connect_four_solver::ConnectFourSolver) or a
+//! [`ScoreFunction`](crate::agents::ScoreFunction)-driven agent.
+//!
+//! Values are expected scores for the player to move, counting a draw as half a win.
+
+use crate::{
+    Game, GameStatus,
+    agents::monte_carlo_graph::{EdgeWeight, MonteCarloGraph, NodeMapping},
+};
+
+use derive_aliases::derive;
+
+/// An estimated proportion with its 95% Wilson score interval.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::analysis::Rate;
+///
+/// let rate = Rate::wilson(5, 10);
+/// assert_eq!(rate.estimate, 0.5);
+/// assert!((rate.lower - 0.2366).abs() < 1e-4);
+/// assert!((rate.upper - 0.7634).abs() < 1e-4);
+/// ```
+#[derive(..Copy, Debug, PartialEq)]
+pub struct Rate {
+    /// Observed proportion
+    pub estimate: f64,
+    /// Lower bound of the interval
+    pub lower: f64,
+    /// Upper bound of the interval
+    pub upper: f64,
+}
+
+impl Rate {
+    /// Z-score of a two-sided 95% interval.
+    const Z: f64 = 1.959_963_984_540_054;
+
+    /// Estimates a proportion from `successes` out of `trials`.
+    ///
+    /// Without trials the estimate is 0 and the interval is `[0, 1]`.
+    pub fn wilson(successes: usize, trials: usize) -> Rate {
+        if trials == 0 {
+            return Rate {
+                estimate: 0.0,
+                lower: 0.0,
+                upper: 1.0,
+            };
+        }
+
+        let n = trials as f64;
+        let p = successes as f64 / n;
+        let z2 = Self::Z * Self::Z;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = Self::Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        Rate {
+            estimate: p,
+            lower: (center - margin).max(0.0),
+            upper: (center + margin).min(1.0),
+        }
+    }
+}
+
+/// Statistics of an explored move.
+#[derive(..Copy, Debug)]
+pub struct MoveStats<G: Game> {
+    /// The move
+    pub mv: G::MoveType,
+    /// Results of the games in which the move was played, from the mover's perspective
+    pub stats: EdgeWeight,
+    /// Fraction of those games won
+    pub win: Rate,
+    /// Fraction of those games drawn
+    pub draw: Rate,
+    /// Fraction of those games lost
+    pub loss: Rate,
+}
+
+impl<G: Game> MoveStats<G> {
+    /// Returns the expected score of the move, counting a draw as half a win.
+    pub fn score(&self) -> f64 {
+        expected_score(&self.stats)
+    }
+}
+
+/// A position whose value in the graph differs from a reference value.
+#[derive(..Copy, Debug)]
+pub struct Disagreement<G: Game> {
+    /// The position
+    pub position: G,
+    /// Number of games that passed through it
+    pub visits: usize,
+    /// Value learned by the graph
+    pub graph_value: f64,
+    /// Value given by the reference
+    pub reference_value: f64,
+}
+
+impl<G: Game> Disagreement<G> {
+    /// Returns how far apart the two values are.
+    pub fn gap(&self) -> f64 {
+        (self.graph_value - self.reference_value).abs()
+    }
+}
+
+/// A position where the graph's choice of move differs from a reference's.
+#[derive(..Copy, Debug)]
+pub struct MoveDisagreement<G: Game> {
+    /// The position
+    pub position: G,
+    /// Number of games that passed through it
+    pub visits: usize,
+    /// Most visited move in the graph
+    pub graph_move: G::MoveType,
+    /// Move chosen by the reference
+    pub reference_move: G::MoveType,
+    /// Expected score of the graph's move minus that of the reference's move, both
+    /// according to the graph
+    pub regret: f64,
+}
+
+/// Returns `(wins + draws / 2) / simulations`, or 0 without simulations.
+fn expected_score(stats: &EdgeWeight) -> f64 {
+    if stats.simulations() == 0 {
+        0.0
+    } else {
+        (stats.wins() as f64 + 0.5 * stats.draws() as f64) / stats.simulations() as f64
+    }
+}
+
+impl<G: Game, M: NodeMapping<G>> MonteCarloGraph<G, M> {
+    /// Returns the explored moves of `state` with their statistics, in move order.
+    fn explored_moves(&self, state: &G) -> Vec<(G::MoveType, EdgeWeight)> {
+        if state.get_status() != GameStatus::InProgress {
+            return Vec::new();
+        }
+
+        let node = M::node(state);
+        let player = state.get_current_player();
+        let mut next = *state;
+        state
+            .get_available_moves()
+            .into_iter()
+            .filter_map(|mv| {
+                let record = next.make_move(mv, player).unwrap();
+                let stats = self.edge_weight(node, M::node(&next)).copied();
+                next.undo(record);
+                stats
+                    .filter(|stats| stats.simulations() > 0)
+                    .map(|stats| (mv, stats))
+            })
+            .collect()
+    }
+
+    /// Returns the graph's value of `state` for the player to move, if it was visited.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::connect_four::ConnectFour;
+    /// use games_rs::{Game, GameStatus};
+    ///
+    /// let root = ConnectFour::default();
+    /// let (mut left, mut right) = (root, root);
+    /// left.play(0, root.get_current_player()).unwrap();
+    /// right.play(6, root.get_current_player()).unwrap();
+    ///
+    /// let mut graph = MonteCarloGraph::<ConnectFour>::new();
+    /// graph.back_propogate(vec![root, left], GameStatus::Win(0));
+    /// graph.back_propogate(vec![root, right], GameStatus::Draw);
+    ///
+    /// assert_eq!(graph.position_value(&root), Some(0.75));
+    /// assert_eq!(graph.position_value(&left), None);
+    /// ```
+    pub fn position_value(&self, state: &G) -> Option<f64> {
+        if state.get_status() != GameStatus::InProgress {
+            return None;
+        }
+        let aggregate = self.get_aggregate_outcomes(&M::node(state));
+        (aggregate.simulations() > 0).then(|| expected_score(&aggregate))
+    }
+
+    /// Returns the line of most visited moves from `state`, at most `max_len` long.
+    ///
+    /// The line ends at the first position without explored moves.
+    pub fn principal_variation(&self, state: &G, max_len: usize) -> Vec<G::MoveType> {
+        let mut line = Vec::new();
+        let mut state = *state;
+        while line.len() < max_len {
+            let Some((mv, _)) = self
+                .explored_moves(&state)
+                .into_iter()
+                .rev()
+                .max_by_key(|(_, stats)| stats.simulations())
+            else {
+                break;
+            };
+            state.play(mv, state.get_current_player()).unwrap();
+            line.push(mv);
+        }
+        line
+    }
+
+    /// Returns up to `k` explored moves of `state`, most visited first.
+    ///
+    /// Moves with equal visits are ordered by expected score.
+    pub fn top_moves(&self, state: &G, k: usize) -> Vec<MoveStats<G>> {
+        let mut moves = self
+            .explored_moves(state)
+            .into_iter()
+            .map(|(mv, stats)| MoveStats {
+                mv,
+                stats,
+                win: Rate::wilson(stats.wins(), stats.simulations()),
+                draw: Rate::wilson(stats.draws(), stats.simulations()),
+                loss: Rate::wilson(stats.losses(), stats.simulations()),
+            })
+            .collect::<Vec<_>>();
+        moves.sort_by(|a, b| {
+            b.stats
+                .simulations()
+                .cmp(&a.stats.simulations())
+                .then(b.score().total_cmp(&a.score()))
+        });
+        moves.truncate(k);
+        moves
+    }
+
+    /// Finds positions visited at least `min_visits` times whose value differs from
+    /// `reference` by more than `threshold`, largest gap first.
+    ///
+    /// `reference` returns the expected score for the player to move, or `None` to skip
+    /// the position.
+    pub fn disagreements(
+        &self,
+        min_visits: usize,
+        threshold: f64,
+        reference: impl Fn(&G) -> Option<f64>,
+    ) -> Vec<Disagreement<G>> {
+        let mut found = self
+            .nodes()
+            .into_iter()
+            .filter(|n| self.visits(n) >= min_visits)
+            .filter_map(|n| {
+                let graph_value = self.position_value(&n)?;
+                let reference_value = reference(&n)?;
+                Some(Disagreement {
+                    position: n,
+                    visits: self.visits(&n),
+                    graph_value,
+                    reference_value,
+                })
+            })
+            .filter(|disagreement| disagreement.gap() > threshold)
+            .collect::<Vec<_>>();
+        found.sort_by(|a, b| b.gap().total_cmp(&a.gap()));
+        found
+    }
+
+    /// Finds positions visited at least `min_visits` times where the graph's most
+    /// visited move and the move chosen by `reference` differ in expected score by more
+    /// than `threshold`, largest difference first.
+    ///
+    /// Both moves are valued with the graph's statistics, so positions where the
+    /// reference picks an unexplored move are skipped, as are positions for which
+    /// `reference` returns `None`.
+    pub fn move_disagreements(
+        &self,
+        min_visits: usize,
+        threshold: f64,
+        reference: impl Fn(&G) -> Option<G::MoveType>,
+    ) -> Vec<MoveDisagreement<G>> {
+        let mut found = self
+            .nodes()
+            .into_iter()
+            .filter(|n| self.visits(n) >= min_visits)
+            .filter_map(|n| {
+                let moves = self.explored_moves(&n);
+                let (graph_move, graph_stats) = moves
+                    .iter()
+                    .rev()
+                    .max_by_key(|(_, stats)| stats.simulations())?;
+                let reference_move = reference(&n)?;
+                let (_, reference_stats) = moves.iter().find(|(mv, _)| *mv == reference_move)?;
+                Some(MoveDisagreement {
+                    position: n,
+                    visits: self.visits(&n),
+                    graph_move: *graph_move,
+                    reference_move,
+                    regret: expected_score(graph_stats) - expected_score(reference_stats),
+                })
+            })
+            .filter(|disagreement| disagreement.regret.abs() > threshold)
+            .collect::<Vec<_>>();
+        found.sort_by(|a, b| b.regret.abs().total_cmp(&a.regret.abs()));
+        found
+    }
+}
+
+mod test {
+    #[test]
+    fn test_analysis_queries() {
+        use crate::agents::monte_carlo_graph::MonteCarloGraph;
+        use crate::connect_four::ConnectFour;
+        use crate::{Game, GameStatus};
+
+        let after = |moves: &[usize]| {
+            let mut game = ConnectFour::default();
+            let mut path = vec![game];
+            for mv in moves {
+                game.play(*mv, game.get_current_player()).unwrap();
+                path.push(game);
+            }
+            path
+        };
+
+        // Column 3 is played three times with one win, loss and draw; column 0 once
+        // and wins
+        let mut graph = MonteCarloGraph::<ConnectFour>::new();
+        graph.back_propogate(after(&[3, 3]), GameStatus::Draw);
+        graph.back_propogate(after(&[3, 2]), GameStatus::Win(1));
+        graph.back_propogate(after(&[3, 2, 2]), GameStatus::Win(0));
+        graph.back_propogate(after(&[0]), GameStatus::Win(0));
+        let root = ConnectFour::default();
+
+        assert_eq!(graph.principal_variation(&root, 10), vec![3, 2, 2]);
+        assert_eq!(graph.principal_variation(&root, 1), vec![3]);
+
+        let top = graph.top_moves(&root, 5);
+        assert_eq!(top.iter().map(|m| m.mv).collect::<Vec<_>>(), vec![3, 0]);
+        assert_eq!(top[0].stats, (1, 1, 1).into());
+        assert_eq!(top[0].score(), 0.5);
+        assert!(top[0].win.lower < 1.0 / 3.0 && top[0].win.upper > 1.0 / 3.0);
+        assert_eq!(graph.top_moves(&root, 1).len(), 1);
+        assert_eq!(graph.position_value(&root), Some(0.625));
+
+        // Only the position after 3 is valued far from a sure win; unvisited
+        // continuations have no value to compare
+        let found = graph.disagreements(1, 0.4, |_| Some(1.0));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].position, after(&[3])[1]);
+        assert_eq!(found[0].graph_value, 0.5);
+
+        // By the graph's own numbers column 0 is half a point better than column 3
+        let found = graph.move_disagreements(1, 0.1, |state| (*state == root).then_some(0));
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].graph_move, found[0].reference_move), (3, 0));
+        assert_eq!(found[0].regret, -0.5);
+    }
+}
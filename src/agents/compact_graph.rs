@@ -0,0 +1,391 @@
+//! Compact, read-only storage for trained graphs.
+//!
+//! A [`CompactGraph`] keeps a hash of each position instead of the position itself and
+//! stores edges in compressed sparse row form: the children of a node occupy a
+//! contiguous slice of one array, found through an offset table. This makes it a
+//! fraction of the size of the [`MonteCarloGraph`] it was built from, so it is the form
+//! trained graphs are shipped and played from. Training continues on the full graph.
+
+use std::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    Game,
+    agents::graph_file::{self, Compression, GraphFileError, GraphMapping, Layout},
+    agents::monte_carlo_graph::{EdgeWeight, Exact, MonteCarloGraph, NodeMapping, UpdateMode},
+};
+
+/// A hash identifying a position.
+pub trait PositionKey:
+    Copy + Ord + std::fmt::Debug + Serialize + DeserializeOwned + Send + Sync + 'static
+{
+    /// Returns the key of `state`.
+    fn key<G: Game>(state: &G) -> Self;
+}
+
+impl PositionKey for u64 {
+    /// The position's [`Game::hash_key`].
+    #[inline]
+    fn key<G: Game>(state: &G) -> Self {
+        state.hash_key()
+    }
+}
+
+impl PositionKey for u128 {
+    /// The position's [`Game::hash_key`] followed by an independent FNV-1a hash.
+    #[inline]
+    fn key<G: Game>(state: &G) -> Self {
+        ((state.hash_key() as u128) << 64) | fnv(state, FNV_OFFSET) as u128
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the standard hasher is stable across builds.
+struct Fnv(u64);
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+}
+
+/// Standard FNV-1a starting value, used for key hashes.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Starting value for check values, so they are independent of the keys.
+const CHECK_OFFSET: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Hashes a position with FNV-1a from the given starting value.
+fn fnv<G: Hash>(state: &G, offset: u64) -> u64 {
+    let mut hasher = Fnv(offset);
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Edge statistics stored in 32-bit counters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct CompactWeight([u32; 3]);
+
+impl TryFrom<EdgeWeight> for CompactWeight {
+    type Error = String;
+
+    fn try_from(value: EdgeWeight) -> Result<Self, Self::Error> {
+        let narrow = |count: usize| {
+            u32::try_from(count)
+                .map_err(|_| format!("Edge count {} does not fit in 32 bits", count))
+        };
+        Ok(CompactWeight([
+            narrow(value.wins())?,
+            narrow(value.losses())?,
+            narrow(value.draws())?,
+        ]))
+    }
+}
+
+impl From<CompactWeight> for EdgeWeight {
+    fn from(value: CompactWeight) -> Self {
+        let [wins, losses, draws] = value.0;
+        (wins as usize, losses as usize, draws as usize).into()
+    }
+}
+
+/// Read-only graph of position hashes with edges in compressed sparse row form.
+///
+/// Lookups take game states, which are mapped with `M` and hashed into `K`. A position
+/// that is not in the graph can share its hash with one that is; with verification
+/// enabled, every node also stores an independent 32-bit check value that a lookup
+/// must match, which makes such false hits about four billion times less likely.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::compact_graph::CompactGraph;
+/// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+/// use games_rs::agents::train::play_batch_parallel;
+/// use games_rs::agents::RandomAgent;
+/// use games_rs::connect_four::ConnectFour;
+/// use games_rs::Game;
+///
+/// let samples = play_batch_parallel::<ConnectFour, _, _>(
+///     || Box::new(RandomAgent::new()),
+///     || Box::new(RandomAgent::new()),
+///     100,
+///     None,
+/// );
+/// let mut graph = MonteCarloGraph::<ConnectFour>::new();
+/// graph.train_batch_parallel(&samples, None);
+///
+/// let compact: CompactGraph<ConnectFour> = CompactGraph::from_graph(&graph, true).unwrap();
+/// let root = ConnectFour::default();
+/// let mut child = root;
+/// child.play(3, root.get_current_player()).unwrap();
+///
+/// assert_eq!(compact.node_count(), graph.node_count());
+/// assert_eq!(compact.edge_weight(&root, &child), graph.edge_weight(root, child).copied());
+/// assert!(compact.memory_usage() * 5 < graph.memory_usage());
+/// ```
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(serialize = "K: Serialize", deserialize = "K: DeserializeOwned"))]
+pub struct CompactGraph<G, K = u64, M = Exact> {
+    /// Key of the root position
+    root: K,
+    /// Sorted position keys; a node is identified by its index in this array
+    keys: Vec<K>,
+    /// Check values parallel to `keys`, if verification is enabled
+    checks: Option<Vec<u32>>,
+    /// Children of node `i` are `targets[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<u32>,
+    /// Child node indices, sorted within each node
+    targets: Vec<u32>,
+    /// Statistics parallel to `targets`
+    weights: Vec<CompactWeight>,
+    /// Statistics of evicted children, by node index
+    absorbed: Vec<(u32, CompactWeight)>,
+    /// How results were credited to edges during training
+    mode: UpdateMode,
+    /// Number of games the graph was trained on
+    samples: u64,
+    #[serde(skip)]
+    _marker: PhantomData<(G, M)>,
+}
+
+impl<G: Game, K: PositionKey, M: NodeMapping<G>> CompactGraph<G, K, M> {
+    /// Builds a compact copy of a trained graph, with check values if `verify` is set.
+    ///
+    /// # Errors
+    /// Returns an error if two positions of the graph share a key, or if an edge has
+    /// more than `u32::MAX` results of one kind.
+    pub fn from_graph(graph: &MonteCarloGraph<G, M>, verify: bool) -> Result<Self, String> {
+        let mut nodes: Vec<(K, G)> = graph.nodes().into_iter().map(|n| (K::key(&n), n)).collect();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some(pair) = nodes.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(format!(
+                "Positions {:?} and {:?} share the key {:?}",
+                pair[0].1, pair[1].1, pair[0].0
+            ));
+        }
+
+        let keys: Vec<K> = nodes.iter().map(|(key, _)| *key).collect();
+        let index = |n: &G| keys.binary_search(&K::key(n)).unwrap() as u32;
+
+        let mut offsets = Vec::with_capacity(nodes.len() + 1);
+        let mut targets = Vec::with_capacity(graph.edge_count());
+        let mut weights = Vec::with_capacity(graph.edge_count());
+        let mut absorbed = Vec::new();
+        offsets.push(0);
+        for (i, (_, n)) in nodes.iter().enumerate() {
+            let mut children = graph
+                .edges_from(n)
+                .into_iter()
+                .map(|(child, weight)| (index(&child), weight))
+                .collect::<Vec<_>>();
+            children.sort_by_key(|(child, _)| *child);
+
+            let mut total = EdgeWeight::default();
+            for (child, weight) in children {
+                targets.push(child);
+                weights.push(CompactWeight::try_from(weight)?);
+                total += weight;
+            }
+            let aggregate = graph.get_aggregate_outcomes(n);
+            if aggregate != total {
+                absorbed.push((i as u32, CompactWeight::try_from(aggregate - total)?));
+            }
+            offsets.push(targets.len() as u32);
+        }
+
+        let checks = verify.then(|| {
+            nodes
+                .iter()
+                .map(|(_, n)| fnv(n, CHECK_OFFSET) as u32)
+                .collect()
+        });
+
+        Ok(CompactGraph {
+            root: K::key(&graph.root()),
+            keys,
+            checks,
+            offsets,
+            targets,
+            weights,
+            absorbed,
+            mode: graph.update_mode(),
+            samples: graph.samples(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the index of the node for `state`, if the graph has one.
+    fn index(&self, state: &G) -> Option<usize> {
+        let node = M::node(state);
+        let i = self.keys.binary_search(&K::key(&node)).ok()?;
+        match &self.checks {
+            Some(checks) if checks[i] != fnv(&node, CHECK_OFFSET) as u32 => None,
+            _ => Some(i),
+        }
+    }
+
+    /// Returns the child indices and statistics of node `i`.
+    fn children(&self, i: usize) -> (&[u32], &[CompactWeight]) {
+        let range = self.offsets[i] as usize..self.offsets[i + 1] as usize;
+        (&self.targets[range.clone()], &self.weights[range])
+    }
+
+    /// Checks if the graph has a node for `state`.
+    pub fn contains_node(&self, state: &G) -> bool {
+        self.index(state).is_some()
+    }
+
+    /// Returns the statistics of the move from `from` to `to`, if it was explored.
+    pub fn edge_weight(&self, from: &G, to: &G) -> Option<EdgeWeight> {
+        let (from, to) = (self.index(from)?, self.index(to)? as u32);
+        let (targets, weights) = self.children(from);
+        let j = targets.binary_search(&to).ok()?;
+        Some(weights[j].into())
+    }
+
+    /// Aggregates outcomes from all moves explored from `state`, including evicted ones.
+    pub fn get_aggregate_outcomes(&self, state: &G) -> EdgeWeight {
+        let Some(i) = self.index(state) else {
+            return EdgeWeight::default();
+        };
+        let absorbed = self
+            .absorbed
+            .binary_search_by_key(&(i as u32), |(n, _)| *n)
+            .map_or(EdgeWeight::default(), |j| self.absorbed[j].1.into());
+        let (_, weights) = self.children(i);
+        weights
+            .iter()
+            .fold(absorbed, |total, weight| total + (*weight).into())
+    }
+
+    /// Returns the number of nodes in the graph.
+    pub fn node_count(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the number of edges in the graph.
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Returns the heap memory used by the graph in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.keys.capacity() * std::mem::size_of::<K>()
+            + self
+                .checks
+                .as_ref()
+                .map_or(0, |checks| checks.capacity() * 4)
+            + self.offsets.capacity() * 4
+            + self.targets.capacity() * 4
+            + self.weights.capacity() * std::mem::size_of::<CompactWeight>()
+            + self.absorbed.capacity() * std::mem::size_of::<(u32, CompactWeight)>()
+    }
+
+    /// Returns how results were credited to edges during training.
+    pub fn update_mode(&self) -> UpdateMode {
+        self.mode
+    }
+
+    /// Returns the number of games the graph was trained on.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Writes the graph to a compressed file; see [`graph_file`] for the format.
+    pub fn to_file(&self, path: &str) -> Result<(), GraphFileError>
+    where
+        M: GraphMapping,
+    {
+        let payload =
+            bitcode::serialize(self).map_err(|err| GraphFileError::Encode(err.to_string()))?;
+        graph_file::write(
+            path,
+            G::name,
+            self.samples,
+            Layout::Compact,
+            M::MAPPING,
+            &payload,
+            Compression::default(),
+        )
+    }
+
+    /// Loads a graph written by [`CompactGraph::to_file`].
+    ///
+    /// # Errors
+    /// Returns a [`GraphFileError`] if the file cannot be read, is corrupted, comes from
+    /// an unsupported version, was trained on a different game or node mapping, or holds
+    /// a full graph.
+    pub fn from_file(path: &str) -> Result<Self, GraphFileError>
+    where
+        M: GraphMapping,
+    {
+        let (header, payload) = graph_file::read(path, G::name, Layout::Compact, M::MAPPING)?;
+        if header.version == 0 {
+            return Err(GraphFileError::WrongLayout {
+                expected: Layout::Compact,
+                found: Layout::Graph,
+            });
+        }
+        bitcode::deserialize(&payload).map_err(|err| GraphFileError::Decode(err.to_string()))
+    }
+}
+
+mod test {
+    #[test]
+    fn test_compact_graph_matches_full_graph() {
+        use super::CompactGraph;
+        use crate::agents::RandomAgent;
+        use crate::agents::graph_file::GraphFileError;
+        use crate::agents::monte_carlo_graph::{EvictionPolicy, MonteCarloGraph};
+        use crate::agents::train::play_batch_parallel;
+        use crate::ultimate_ttt::UltimateTTT;
+
+        let samples = play_batch_parallel::<UltimateTTT, _, _>(
+            || Box::new(RandomAgent::new()),
+            || Box::new(RandomAgent::new()),
+            200,
+            None,
+        );
+        let mut graph = MonteCarloGraph::<UltimateTTT>::new();
+        graph.train_batch_parallel(&samples, None);
+        graph.evict(graph.node_count() / 2, EvictionPolicy::Deepest);
+
+        let compact = CompactGraph::<UltimateTTT, u128>::from_graph(&graph, true).unwrap();
+        assert_eq!(compact.node_count(), graph.node_count());
+        assert_eq!(compact.edge_count(), graph.edge_count());
+        for n in graph.nodes() {
+            assert_eq!(
+                compact.get_aggregate_outcomes(&n),
+                graph.get_aggregate_outcomes(&n)
+            );
+            for (child, weight) in graph.edges_from(&n) {
+                assert_eq!(compact.edge_weight(&n, &child), Some(weight));
+            }
+        }
+        assert!(compact.memory_usage() * 5 < graph.memory_usage());
+
+        let path = std::env::temp_dir().join(format!("compact_test_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        compact.to_file(path).unwrap();
+        let loaded = CompactGraph::<UltimateTTT, u128>::from_file(path).unwrap();
+        assert_eq!(loaded.node_count(), compact.node_count());
+        assert_eq!(
+            loaded.get_aggregate_outcomes(&UltimateTTT::default()),
+            graph.get_aggregate_outcomes(&UltimateTTT::default())
+        );
+        assert!(matches!(
+            MonteCarloGraph::<UltimateTTT>::from_file(path),
+            Err(GraphFileError::WrongLayout { .. })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
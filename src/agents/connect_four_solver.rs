@@ -0,0 +1,591 @@
+//! Perfect-play solver for Connect Four.
+//!
+//! The solver computes the exact game-theoretic value of any position using negamax
+//! with alpha-beta pruning, a transposition table, move ordering and an iterative
+//! null-window search over the score range. It works directly on the bitboards of
+//! [`ConnectFour`].
+
+use std::cell::{Cell, RefCell};
+
+use crate::{
+    Game, GameStatus,
+    agents::Agent,
+    connect_four::{BOARD_MASK, BOTTOM_MASK, ConnectFour, MOVE_ORDER, Token},
+};
+
+use derive_aliases::derive;
+
+/// Number of cells on the board.
+const CELLS: i8 = 42;
+
+/// Smallest possible score: losing to the opponent's fourth token.
+const MIN_SCORE: i8 = -(CELLS / 2) + 3;
+
+/// Exact game-theoretic value of a position, from the perspective of the player to move.
+///
+/// Distances count plies (single moves by either player) until the winning token is
+/// placed, assuming both sides play perfectly: winners win as fast as possible and
+/// losers delay the loss as long as possible.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::connect_four_solver::Score;
+///
+/// let score = Score::Win(3);
+/// assert!(score.is_win());
+/// assert_eq!(score.plies(), Some(3));
+/// ```
+#[derive(..StdTraits, Debug)]
+pub enum Score {
+    /// The player to move wins, placing the winning token `n` plies from now
+    Win(u8),
+    /// Neither player can force a win
+    Draw,
+    /// The opponent wins, placing the winning token `n` plies from now
+    Loss(u8),
+}
+
+impl Score {
+    /// Converts a raw negamax value at a position with `moves` tokens played.
+    ///
+    /// Raw values are positive when the player to move wins and equal 22 minus the
+    /// number of tokens the winner has placed when the game ends.
+    fn from_value(value: i8, moves: u8) -> Score {
+        let moves = moves as i8;
+        if value > 0 {
+            let stones = CELLS / 2 + 1 - value;
+            Score::Win((2 * (stones - moves / 2) - 1) as u8)
+        } else if value < 0 {
+            let stones = CELLS / 2 + 1 + value;
+            Score::Loss((2 * (stones - (moves + 1) / 2)) as u8)
+        } else {
+            Score::Draw
+        }
+    }
+
+    /// Returns `true` if the player to move can force a win.
+    pub fn is_win(&self) -> bool {
+        matches!(self, Score::Win(_))
+    }
+
+    /// Returns `true` if the opponent can force a win.
+    pub fn is_loss(&self) -> bool {
+        matches!(self, Score::Loss(_))
+    }
+
+    /// Returns the result for the player to move: 1 for a win, 0.5 for a draw and 0
+    /// for a loss.
+    pub fn expected_score(&self) -> f64 {
+        match self {
+            Score::Win(_) => 1.0,
+            Score::Draw => 0.5,
+            Score::Loss(_) => 0.0,
+        }
+    }
+
+    /// Returns `true` if the position is a draw with perfect play.
+    pub fn is_draw(&self) -> bool {
+        *self == Score::Draw
+    }
+
+    /// Returns the number of plies until the winning token is placed, or `None` for draws.
+    pub fn plies(&self) -> Option<u8> {
+        match self {
+            Score::Win(plies) | Score::Loss(plies) => Some(*plies),
+            Score::Draw => None,
+        }
+    }
+}
+
+/// Bitboard position from the perspective of the player to move.
+#[derive(..Copy)]
+struct Position {
+    /// Tokens of the player to move.
+    current: u64,
+    /// All tokens on the board.
+    mask: u64,
+    /// Number of tokens played.
+    moves: u8,
+}
+
+impl Position {
+    fn from_game(game: &ConnectFour) -> Self {
+        Position {
+            current: game.get_bitboard(game.get_current_player()),
+            mask: game.get_bitboard(Token::Red) | game.get_bitboard(Token::Yellow),
+            moves: game.move_count() as u8,
+        }
+    }
+
+    /// Returns a key that uniquely identifies the position.
+    #[inline]
+    fn key(&self) -> u64 {
+        self.current + self.mask
+    }
+
+    /// Returns the cells where a token can be dropped.
+    #[inline]
+    fn possible(&self) -> u64 {
+        (self.mask + BOTTOM_MASK) & BOARD_MASK
+    }
+
+    /// Returns the empty cells that would complete a line for the given tokens.
+    #[inline]
+    fn winning_cells(tokens: u64, mask: u64) -> u64 {
+        // Vertical
+        let mut cells = (tokens << 1) & (tokens << 2) & (tokens << 3);
+
+        // Horizontal and both diagonals
+        for shift in [7, 6, 8] {
+            let pair = (tokens << shift) & (tokens << (2 * shift));
+            cells |= pair & (tokens << (3 * shift));
+            cells |= pair & (tokens >> shift);
+            let pair = (tokens >> shift) & (tokens >> (2 * shift));
+            cells |= pair & (tokens << shift);
+            cells |= pair & (tokens >> (3 * shift));
+        }
+
+        cells & (BOARD_MASK ^ mask)
+    }
+
+    /// Returns `true` if the player to move can win immediately.
+    #[inline]
+    fn can_win_next(&self) -> bool {
+        Self::winning_cells(self.current, self.mask) & self.possible() != 0
+    }
+
+    /// Returns the moves that do not hand the opponent an immediate win.
+    ///
+    /// Assumes the player to move cannot win immediately.
+    fn non_losing_moves(&self) -> u64 {
+        let mut possible = self.possible();
+        let opponent_wins = Self::winning_cells(self.current ^ self.mask, self.mask);
+        let forced = possible & opponent_wins;
+        if forced != 0 {
+            if forced & (forced - 1) != 0 {
+                // The opponent has two immediate threats
+                return 0;
+            }
+            possible = forced;
+        }
+        // Avoid playing directly below an opponent's winning cell
+        possible & !(opponent_wins >> 1)
+    }
+
+    /// Scores a move by the number of winning cells it creates.
+    #[inline]
+    fn move_score(&self, mv: u64) -> u32 {
+        Self::winning_cells(self.current | mv, self.mask).count_ones()
+    }
+
+    /// Plays the token given as a single-bit mask and switches sides.
+    #[inline]
+    fn play(&self, mv: u64) -> Position {
+        Position {
+            current: self.current ^ self.mask,
+            mask: self.mask | mv,
+            moves: self.moves + 1,
+        }
+    }
+}
+
+/// Transposition table storing upper bounds of negamax values.
+struct Table {
+    keys: Vec<u64>,
+    values: Vec<i8>,
+}
+
+impl Table {
+    fn new(size: usize) -> Self {
+        Table {
+            keys: vec![0; size],
+            values: vec![0; size],
+        }
+    }
+
+    /// Returns the stored bound for `key`.
+    ///
+    /// Stored bounds are always at least 1, so a value of 0 marks an empty slot. This
+    /// keeps the zero-filled table from matching the empty board, whose key is 0.
+    #[inline]
+    fn get(&self, key: u64) -> Option<i8> {
+        let index = (key % self.keys.len() as u64) as usize;
+        (self.keys[index] == key && self.values[index] != 0).then_some(self.values[index])
+    }
+
+    #[inline]
+    fn put(&mut self, key: u64, value: i8) {
+        let index = (key % self.keys.len() as u64) as usize;
+        self.keys[index] = key;
+        self.values[index] = value;
+    }
+
+    fn clear(&mut self) {
+        self.keys.fill(0);
+        self.values.fill(0);
+    }
+}
+
+/// A Connect Four agent that always plays a game-theoretically optimal move.
+///
+/// Solving early positions is expensive (minutes for the empty board in release builds);
+/// the transposition table is kept between calls so that successive positions from the
+/// same game are solved much faster.
+///
+/// # Examples
+/// ```
+/// use games_rs::Game;
+/// use games_rs::agents::connect_four_solver::{ConnectFourSolver, Score};
+/// use games_rs::connect_four::ConnectFour;
+///
+/// let mut game = ConnectFour::new();
+/// for mv in [3, 0, 3, 0, 3, 0] {
+///     game.play(mv, game.get_current_player()).unwrap();
+/// }
+///
+/// let solver = ConnectFourSolver::new();
+/// assert_eq!(solver.solve(&game), Score::Win(1));
+/// ```
+pub struct ConnectFourSolver {
+    table: RefCell<Table>,
+    nodes: Cell<u64>,
+}
+
+impl ConnectFourSolver {
+    /// Default number of transposition table entries (about 18 MB).
+    pub const DEFAULT_TABLE_SIZE: usize = (1 << 21) + 1;
+
+    /// Creates a solver with the default transposition table size.
+    pub fn new() -> Self {
+        Self::with_table_size(Self::DEFAULT_TABLE_SIZE)
+    }
+
+    /// Creates a solver with a transposition table of `entries` entries.
+    ///
+    /// An odd size spreads keys more evenly across the table.
+    pub fn with_table_size(entries: usize) -> Self {
+        ConnectFourSolver {
+            table: RefCell::new(Table::new(entries.max(1))),
+            nodes: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of positions explored since the solver was created.
+    pub fn explored_nodes(&self) -> u64 {
+        self.nodes.get()
+    }
+
+    /// Clears the transposition table.
+    pub fn reset(&self) {
+        self.table.borrow_mut().clear();
+    }
+
+    /// Computes the exact score of a position for the player to move.
+    ///
+    /// Finished games score [`Score::Draw`] or [`Score::Loss(0)`](Score::Loss), since the
+    /// player to move in a won position is the one who lost.
+    pub fn solve(&self, game: &ConnectFour) -> Score {
+        let position = Position::from_game(game);
+        Score::from_value(self.solve_value(game, position), position.moves)
+    }
+
+    /// Scores every available move from the perspective of the player making it.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::Game;
+    /// use games_rs::agents::connect_four_solver::{ConnectFourSolver, Score};
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// let mut game = ConnectFour::new();
+    /// for mv in [3, 0, 3, 0, 3, 0] {
+    ///     game.play(mv, game.get_current_player()).unwrap();
+    /// }
+    ///
+    /// let scores = ConnectFourSolver::new().analyze(&game);
+    /// assert!(scores.contains(&(3, Score::Win(1))));
+    /// ```
+    pub fn analyze(&self, game: &ConnectFour) -> Vec<(usize, Score)> {
+        self.move_values(game)
+            .into_iter()
+            .map(|(mv, value)| (mv, Score::from_value(value, game.move_count() as u8)))
+            .collect()
+    }
+
+    /// Returns the raw value of every available move for the player to move.
+    fn move_values(&self, game: &ConnectFour) -> Vec<(usize, i8)> {
+        let position = Position::from_game(game);
+        let player = game.get_current_player();
+        let mut game = *game;
+
+        game.get_available_moves()
+            .into_iter()
+            .map(|mv| {
+                if game.is_winning_move(mv) {
+                    return (mv, (CELLS + 1 - position.moves as i8) / 2);
+                }
+                let record = game.make_move(mv, player).unwrap();
+                let value = -self.solve_value(&game, Position::from_game(&game));
+                game.undo(record);
+                (mv, value)
+            })
+            .collect()
+    }
+
+    /// Solves a position, handling finished games and immediate wins.
+    fn solve_value(&self, game: &ConnectFour, position: Position) -> i8 {
+        match game.get_status() {
+            GameStatus::Win(_) => return -(CELLS + 2 - position.moves as i8) / 2,
+            GameStatus::Draw => return 0,
+            GameStatus::InProgress => {}
+        }
+
+        if position.can_win_next() {
+            return (CELLS + 1 - position.moves as i8) / 2;
+        }
+
+        // Narrow the score window with null-window searches
+        let mut min = -(CELLS - position.moves as i8) / 2;
+        let mut max = (CELLS + 1 - position.moves as i8) / 2;
+        while min < max {
+            let mut med = min + (max - min) / 2;
+            if med <= 0 && min / 2 < med {
+                med = min / 2;
+            } else if med >= 0 && max / 2 > med {
+                med = max / 2;
+            }
+
+            let value = self.negamax(position, med, med + 1);
+            if value <= med {
+                max = value;
+            } else {
+                min = value;
+            }
+        }
+        min
+    }
+
+    /// Negamax with alpha-beta pruning.
+    ///
+    /// Assumes nobody has won yet and the player to move cannot win immediately.
+    fn negamax(&self, position: Position, mut alpha: i8, mut beta: i8) -> i8 {
+        self.nodes.set(self.nodes.get() + 1);
+
+        let moves = position.non_losing_moves();
+        if moves == 0 {
+            return -(CELLS - position.moves as i8) / 2;
+        }
+
+        if position.moves as i8 >= CELLS - 2 {
+            return 0;
+        }
+
+        // The opponent cannot win with their next token
+        let min = -(CELLS - 2 - position.moves as i8) / 2;
+        if alpha < min {
+            alpha = min;
+            if alpha >= beta {
+                return alpha;
+            }
+        }
+
+        // We cannot win with our next token
+        let mut max = (CELLS - 1 - position.moves as i8) / 2;
+        if let Some(bound) = self.table.borrow().get(position.key()) {
+            max = bound + MIN_SCORE - 1;
+        }
+        if beta > max {
+            beta = max;
+            if alpha >= beta {
+                return beta;
+            }
+        }
+
+        let mut ordered = MOVE_ORDER
+            .iter()
+            .map(|&col| moves & (BOARD_MASK & (0b11_1111 << (col * 7))))
+            .filter(|&mv| mv != 0)
+            .map(|mv| (position.move_score(mv), mv))
+            .collect::<Vec<_>>();
+        // Stable sort keeps central columns first among equal scores
+        ordered.sort_by(|a, b| b.0.cmp(&a.0));
+
+        for (_, mv) in ordered {
+            let value = -self.negamax(position.play(mv), -beta, -alpha);
+            if value >= beta {
+                return value;
+            }
+            if value > alpha {
+                alpha = value;
+            }
+        }
+
+        self.table
+            .borrow_mut()
+            .put(position.key(), alpha - MIN_SCORE + 1);
+        alpha
+    }
+}
+
+impl Agent<ConnectFour> for ConnectFourSolver {
+    /// Plays the move with the best exact score, preferring central columns on ties.
+    fn get_move(&self, board: &ConnectFour) -> usize {
+        let values = self.move_values(board);
+        let mut best: Option<(usize, i8)> = None;
+        for &col in MOVE_ORDER.iter() {
+            if let Some(&(mv, value)) = values.iter().find(|(mv, _)| *mv == col) {
+                if best.is_none_or(|(_, best_value)| value > best_value) {
+                    best = Some((mv, value));
+                }
+            }
+        }
+        best.unwrap().0
+    }
+}
+
+mod test {
+    #[test]
+    fn test_solver_matches_exhaustive_search() {
+        use super::{CELLS, ConnectFourSolver, Score};
+        use crate::connect_four::ConnectFour;
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+
+        // Full-width negamax using the same scoring convention as the solver
+        fn exhaustive(game: &mut ConnectFour) -> i8 {
+            let moves = game.move_count() as i8;
+            if moves == CELLS {
+                return 0;
+            }
+            let player = game.get_current_player();
+            let mut best = -CELLS;
+            for mv in game.get_available_moves() {
+                if game.is_winning_move(mv) {
+                    return (CELLS + 1 - moves) / 2;
+                }
+                let record = game.make_move(mv, player).unwrap();
+                best = best.max(-exhaustive(game));
+                game.undo(record);
+            }
+            best
+        }
+
+        let mut rng = rand::rng();
+        let solver = ConnectFourSolver::with_table_size(4099);
+        let mut checked = 0;
+        while checked < 30 {
+            let mut game = ConnectFour::new();
+            while game.move_count() < 33 && game.get_status() == GameStatus::InProgress {
+                let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+                game.play(mv, game.get_current_player()).unwrap();
+            }
+            if game.get_status() != GameStatus::InProgress {
+                continue;
+            }
+
+            let expected = Score::from_value(exhaustive(&mut game), game.move_count() as u8);
+            assert_eq!(solver.solve(&game), expected, "{}", game);
+            checked += 1;
+        }
+    }
+
+    #[test]
+    fn test_score_distances() {
+        use super::{ConnectFourSolver, Score};
+        use crate::Game;
+        use crate::agents::Agent;
+        use crate::connect_four::ConnectFour;
+
+        let solver = ConnectFourSolver::new();
+        let play = |moves: &[usize]| {
+            let mut game = ConnectFour::new();
+            for &mv in moves {
+                game.play(mv, game.get_current_player()).unwrap();
+            }
+            game
+        };
+
+        // Red completes column 3 with its next token
+        let game = play(&[3, 0, 3, 0, 3, 0]);
+        assert_eq!(solver.solve(&game), Score::Win(1));
+        assert_eq!(solver.get_move(&game), 3);
+
+        // Red threatens both ends of the bottom row and wins on its next token
+        let game = play(&[2, 2, 3, 3, 4]);
+        assert_eq!(solver.solve(&game), Score::Loss(2));
+        assert!(
+            solver
+                .analyze(&game)
+                .iter()
+                .all(|(_, score)| score.is_loss())
+        );
+
+        // The game is already over for the player to move
+        let game = play(&[3, 0, 3, 0, 3, 0, 3]);
+        assert_eq!(solver.solve(&game), Score::Loss(0));
+    }
+
+    #[test]
+    fn test_table_treats_empty_slots_as_misses() {
+        use super::Table;
+
+        // The empty board has key 0, which a zero-filled table must not report as stored
+        let mut table = Table::new(7);
+        assert_eq!(table.get(0), None);
+
+        table.put(0, 5);
+        assert_eq!(table.get(0), Some(5));
+        table.put(8, 3);
+        assert_eq!(table.get(8), Some(3));
+        assert_eq!(table.get(1), None);
+
+        table.clear();
+        assert_eq!(table.get(0), None);
+        assert_eq!(table.get(8), None);
+    }
+
+    #[test]
+    fn test_known_positions() {
+        use super::{ConnectFourSolver, Score};
+        use crate::Game;
+        use crate::connect_four::ConnectFour;
+
+        let solver = ConnectFourSolver::new();
+        let play = |moves: &[usize]| {
+            let mut game = ConnectFour::new();
+            for &mv in moves {
+                game.play(mv, game.get_current_player()).unwrap();
+            }
+            game
+        };
+
+        // Column 3 filled, then alternating tokens in column 2
+        let game = play(&[3, 3, 3, 3, 3, 3, 2, 2, 2, 2, 2]);
+        assert_eq!(solver.solve(&game), Score::Loss(30));
+        let game = play(&[3, 3, 3, 3, 3, 3, 2, 2, 2, 2]);
+        assert_eq!(solver.solve(&game), Score::Win(3));
+    }
+
+    #[test]
+    #[ignore = "solves the opening; run with `cargo test --release -- --ignored`"]
+    fn test_opening_positions() {
+        use super::{ConnectFourSolver, Score};
+        use crate::Game;
+        use crate::connect_four::ConnectFour;
+
+        let solver = ConnectFourSolver::new();
+        let play = |moves: &[usize]| {
+            let mut game = ConnectFour::new();
+            for &mv in moves {
+                game.play(mv, game.get_current_player()).unwrap();
+            }
+            game
+        };
+
+        // The first player wins with their last token by starting in the centre
+        assert_eq!(solver.solve(&play(&[])), Score::Win(41));
+        assert_eq!(solver.solve(&play(&[3])), Score::Loss(40));
+        assert_eq!(solver.solve(&play(&[2])), Score::Draw);
+        assert_eq!(solver.solve(&play(&[0])), Score::Win(39));
+    }
+}
@@ -0,0 +1,299 @@
+//! Export of trained graphs for inspection in graph viewers.
+//!
+//! A [`GraphExport`] selects the part of a [`MonteCarloGraph`] reachable from a
+//! position, optionally limited in depth and to positions with enough visits, and
+//! renders it as Graphviz DOT or GraphML. Nodes are labelled with the board and edges
+//! with their results and win rate.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::agents::monte_carlo_graph::{EdgeWeight, Exact, MonteCarloGraph};
+
+/// Selection of a subgraph to export.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::graph_export::GraphExport;
+/// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+/// use games_rs::GameStatus;
+///
+/// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+/// graph.back_propogate(vec![0, 1, 2], GameStatus::Win(0));
+/// graph.back_propogate(vec![0, 3], GameStatus::Draw);
+///
+/// let export = GraphExport::new(&graph).with_max_depth(1);
+/// assert_eq!(export.select().0, vec![0, 1, 3]);
+/// assert!(export.to_dot().contains("n0 -> n1 [label=\"0/1/0\\n0.0%\"]"));
+/// ```
+pub struct GraphExport<'a, N, M = Exact>
+where
+    N: std::hash::Hash + Eq + Clone + Copy + Ord + Default + std::fmt::Debug,
+{
+    graph: &'a MonteCarloGraph<N, M>,
+    start: N,
+    max_depth: Option<usize>,
+    min_visits: usize,
+}
+
+impl<'a, N, M> GraphExport<'a, N, M>
+where
+    N: std::hash::Hash + Eq + Clone + Copy + Ord + Default + std::fmt::Debug + Display + Serialize,
+    for<'de> N: Deserialize<'de>,
+{
+    /// Selects everything reachable from the root of `graph`.
+    pub fn new(graph: &'a MonteCarloGraph<N, M>) -> Self {
+        GraphExport {
+            graph,
+            start: graph.root(),
+            max_depth: None,
+            min_visits: 0,
+        }
+    }
+
+    /// Starts the export at `start` instead of the root.
+    pub fn from_position(mut self, start: N) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Only exports positions at most `max_depth` moves from the start.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Only exports positions visited at least `min_visits` times.
+    pub fn with_min_visits(mut self, min_visits: usize) -> Self {
+        self.min_visits = min_visits;
+        self
+    }
+
+    /// Returns the selected nodes in breadth-first order and the edges between them.
+    ///
+    /// The start is always included, if it is in the graph.
+    pub fn select(&self) -> (Vec<N>, Vec<(N, N, EdgeWeight)>) {
+        if !self.graph.contains_node(&self.start) {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut nodes = vec![self.start];
+        let mut edges = Vec::new();
+        let mut depths = HashMap::from([(self.start, 0)]);
+        let mut queue = VecDeque::from([self.start]);
+        while let Some(n) = queue.pop_front() {
+            let depth = depths[&n];
+            if self.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+                continue;
+            }
+
+            for (child, weight) in self.graph.edges_from(&n) {
+                if self.graph.visits(&child) < self.min_visits {
+                    continue;
+                }
+                edges.push((n, child, weight));
+                if !depths.contains_key(&child) {
+                    depths.insert(child, depth + 1);
+                    nodes.push(child);
+                    queue.push_back(child);
+                }
+            }
+        }
+        (nodes, edges)
+    }
+
+    /// Renders the selection as a Graphviz DOT digraph.
+    ///
+    /// Nodes are named `n0`, `n1`, ... in breadth-first order from the start.
+    pub fn to_dot(&self) -> String {
+        let (nodes, edges) = self.select();
+        let ids: HashMap<N, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut dot = String::from("digraph MonteCarloGraph {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        for (i, n) in nodes.iter().enumerate() {
+            let label = board_label(n)
+                .lines()
+                .map(|line| escape_dot(line) + "\\l")
+                .collect::<String>();
+            writeln!(
+                dot,
+                "    n{} [label=\"{}visits: {}\\l\"];",
+                i,
+                label,
+                self.graph.visits(n)
+            )
+            .unwrap();
+        }
+        for (from, to, weight) in edges {
+            writeln!(
+                dot,
+                "    n{} -> n{} [label=\"{}\"];",
+                ids[&from],
+                ids[&to],
+                edge_label(&weight).replace('\n', "\\n")
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the selection as GraphML.
+    ///
+    /// Nodes carry the board and visit count; edges carry their wins, losses, draws and
+    /// win rate as separate attributes, plus a combined label.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::graph_export::GraphExport;
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate(vec![0, 1], GameStatus::Win(0));
+    ///
+    /// let graphml = GraphExport::new(&graph).to_graphml();
+    /// assert!(graphml.contains("<edge source=\"n0\" target=\"n1\">"));
+    /// assert!(graphml.contains("<data key=\"wins\">1</data>"));
+    /// ```
+    pub fn to_graphml(&self) -> String {
+        let (nodes, edges) = self.select();
+        let ids: HashMap<N, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        for (id, target, kind) in [
+            ("board", "node", "string"),
+            ("visits", "node", "long"),
+            ("wins", "edge", "long"),
+            ("losses", "edge", "long"),
+            ("draws", "edge", "long"),
+            ("win_rate", "edge", "double"),
+            ("label", "edge", "string"),
+        ] {
+            writeln!(
+                xml,
+                "  <key id=\"{0}\" for=\"{1}\" attr.name=\"{0}\" attr.type=\"{2}\"/>",
+                id, target, kind
+            )
+            .unwrap();
+        }
+
+        xml.push_str("  <graph id=\"MonteCarloGraph\" edgedefault=\"directed\">\n");
+        for (i, n) in nodes.iter().enumerate() {
+            writeln!(xml, "    <node id=\"n{}\">", i).unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"board\">{}</data>",
+                escape_xml(&board_label(n))
+            )
+            .unwrap();
+            writeln!(
+                xml,
+                "      <data key=\"visits\">{}</data>",
+                self.graph.visits(n)
+            )
+            .unwrap();
+            xml.push_str("    </node>\n");
+        }
+        for (from, to, weight) in edges {
+            writeln!(
+                xml,
+                "    <edge source=\"n{}\" target=\"n{}\">",
+                ids[&from], ids[&to]
+            )
+            .unwrap();
+            for (key, value) in [
+                ("wins", weight.wins().to_string()),
+                ("losses", weight.losses().to_string()),
+                ("draws", weight.draws().to_string()),
+                ("win_rate", weight.win_rate().to_string()),
+                ("label", escape_xml(&edge_label(&weight))),
+            ] {
+                writeln!(xml, "      <data key=\"{}\">{}</data>", key, value).unwrap();
+            }
+            xml.push_str("    </edge>\n");
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+/// Renders a board without trailing blank lines.
+fn board_label<N: Display>(n: &N) -> String {
+    n.to_string().trim_end().to_string()
+}
+
+/// Renders edge statistics as `wins/losses/draws` and the win rate on a second line.
+fn edge_label(weight: &EdgeWeight) -> String {
+    format!(
+        "{}/{}/{}\n{:.1}%",
+        weight.wins(),
+        weight.losses(),
+        weight.draws(),
+        100.0 * weight.win_rate()
+    )
+}
+
+/// Escapes a string for use inside a quoted DOT label.
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes a string for use as XML character data.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+mod test {
+    #[test]
+    fn test_export_limits_and_escaping() {
+        use super::GraphExport;
+        use crate::agents::monte_carlo_graph::MonteCarloGraph;
+        use crate::connect_four::ConnectFour;
+        use crate::{Game, GameStatus};
+
+        let root = ConnectFour::default();
+        let player = root.get_current_player();
+        let mut path = vec![root];
+        for mv in [3, 3, 4] {
+            let mut next = *path.last().unwrap();
+            next.play(mv, next.get_current_player()).unwrap();
+            path.push(next);
+        }
+        let mut graph = MonteCarloGraph::<ConnectFour>::new();
+        graph.back_propogate(path.clone(), GameStatus::Draw);
+        graph.back_propogate(path[..3].to_vec(), GameStatus::Draw);
+        let mut side = root;
+        side.play(0, player).unwrap();
+        graph.back_propogate(vec![root, side], GameStatus::Win(0));
+
+        // Depth and visit limits prune the selection
+        let (nodes, edges) = GraphExport::new(&graph).select();
+        assert_eq!((nodes.len(), edges.len()), (5, 4));
+        let (nodes, _) = GraphExport::new(&graph).with_max_depth(1).select();
+        assert_eq!(nodes, vec![root, path[1], side]);
+        let (nodes, _) = GraphExport::new(&graph).with_min_visits(2).select();
+        assert_eq!(nodes, path[..3].to_vec());
+        let (nodes, _) = GraphExport::new(&graph).from_position(path[2]).select();
+        assert_eq!(nodes, path[2..].to_vec());
+
+        // Every line of the board becomes a left-justified line of the label
+        let dot = GraphExport::new(&graph).with_max_depth(0).to_dot();
+        let rows = root.to_string().trim_end().lines().count();
+        assert_eq!(dot.matches("\\l").count(), rows + 1);
+
+        let graphml = GraphExport::new(&graph).to_graphml();
+        assert_eq!(graphml.matches("<node ").count(), 5);
+        assert_eq!(graphml.matches("<edge ").count(), 4);
+        assert!(!graphml.contains("<data key=\"board\"><"));
+    }
+}
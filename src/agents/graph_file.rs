@@ -0,0 +1,631 @@
+//! On-disk format for trained graphs.
+//!
+//! A graph file starts with a header identifying the file, the schema version of the
+//! payload, the game the graph was trained on and how many samples went into it. The
+//! bitcode payload follows, optionally compressed, and is checked against the CRC-32
+//! stored in the header when loaded.
+//!
+//! All integers are little-endian:
+//!
+//! | Field       | Size | Contents                                   |
+//! |-------------|------|--------------------------------------------|
+//! | magic       | 8    | `MCGRAPH\0`                                |
+//! | version     | 4    | schema version of the payload              |
+//! | compression | 1    | 0 for none, 1 for deflate                  |
+//! | layout      | 1    | 0 for full graphs, 1 for compact graphs    |
+//! | mapping     | 1    | 0 for exact nodes, 1 for canonical nodes   |
+//! | game length | 2    | length of the game name in bytes           |
+//! | game        | n    | [`Game::name`](crate::Game::name), UTF-8   |
+//! | samples     | 8    | number of training samples                 |
+//! | created     | 8    | seconds since the Unix epoch               |
+//! | checksum    | 4    | CRC-32 of the uncompressed payload         |
+//! | length      | 8    | length of the stored payload in bytes      |
+//!
+//! Files written before the header was introduced are raw bitcode and are read as
+//! schema version 0. Nodes of version 0 files are decoded with the game layouts of that
+//! time, see [`GraphNode::Legacy`].
+//!
+//! When the payload layout changes, [`SCHEMA_VERSION`] is bumped and a frozen copy of
+//! the old layout is kept to migrate from, as is done for version 0. No copy was kept
+//! of the payloads of versions 1 and 2, which predate the current graph and Rummy
+//! layouts, so files from before [`OLDEST_VERSION`] are rejected rather than misread.
+//!
+//! Files are written to a temporary file next to the target and renamed over it, so
+//! an interrupted write leaves the previous file intact.
+
+use std::{
+    fmt::Display,
+    hash::Hash,
+    io::{Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{Compression as Level, read::DeflateDecoder, write::DeflateEncoder};
+
+use serde::Deserialize;
+
+use crate::{
+    Game,
+    agents::monte_carlo_graph::{Canonical, Exact},
+    connect_four::{ConnectFour, LegacyConnectFour},
+    rummy::{GinRummyMatch, LegacyRummy, Rummy},
+    ultimate_ttt::{LegacyUltimateTTT, UltimateTTT},
+};
+
+use derive_aliases::derive;
+
+/// Node types a graph file can be saved with, identified by name in the header.
+///
+/// Games are identified by [`Game::name`]; integers are accepted for graphs built
+/// directly from node paths.
+///
+/// Files without a header store nodes in the layout they had at the time, which is
+/// named by [`GraphNode::Legacy`] and converted on load.
+pub trait GraphNode: Sized {
+    /// Name written to and checked against the header.
+    const NAME: &'static str;
+
+    /// Frozen layout of the node in files without a header.
+    type Legacy: for<'a> Deserialize<'a> + Hash + Ord + Copy;
+
+    /// Converts a node read from a file without a header, or returns `None` if the
+    /// type did not exist when such files were written.
+    fn from_legacy(node: Self::Legacy) -> Option<Self>;
+}
+
+impl GraphNode for ConnectFour {
+    const NAME: &'static str = ConnectFour::name;
+    type Legacy = LegacyConnectFour;
+
+    fn from_legacy(node: LegacyConnectFour) -> Option<Self> {
+        Some(node.into())
+    }
+}
+
+impl GraphNode for UltimateTTT {
+    const NAME: &'static str = UltimateTTT::name;
+    type Legacy = LegacyUltimateTTT;
+
+    fn from_legacy(node: LegacyUltimateTTT) -> Option<Self> {
+        Some(node.into())
+    }
+}
+
+impl GraphNode for Rummy {
+    const NAME: &'static str = Rummy::name;
+    type Legacy = LegacyRummy;
+
+    fn from_legacy(node: LegacyRummy) -> Option<Self> {
+        Some(node.into())
+    }
+}
+
+impl GraphNode for GinRummyMatch {
+    const NAME: &'static str = GinRummyMatch::name;
+    type Legacy = ();
+
+    fn from_legacy(_: ()) -> Option<Self> {
+        None
+    }
+}
+
+impl GraphNode for u32 {
+    const NAME: &'static str = "u32";
+    type Legacy = u32;
+
+    fn from_legacy(node: u32) -> Option<Self> {
+        Some(node)
+    }
+}
+
+impl GraphNode for u64 {
+    const NAME: &'static str = "u64";
+    type Legacy = u64;
+
+    fn from_legacy(node: u64) -> Option<Self> {
+        Some(node)
+    }
+}
+
+/// Node mappings a graph file can be saved with, identified by [`Mapping`] in the header.
+///
+/// A graph trained with symmetric positions merged only holds canonical forms, so it
+/// must be loaded with the same mapping to be looked up correctly.
+pub trait GraphMapping {
+    /// Mapping written to and checked against the header.
+    const MAPPING: Mapping;
+}
+
+impl GraphMapping for Exact {
+    const MAPPING: Mapping = Mapping::Exact;
+}
+
+impl GraphMapping for Canonical {
+    const MAPPING: Mapping = Mapping::Canonical;
+}
+
+/// Bytes every graph file starts with.
+pub const MAGIC: [u8; 8] = *b"MCGRAPH\0";
+
+/// Schema version written by this build.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Oldest schema version with a header that this build can read.
+pub const OLDEST_VERSION: u32 = 3;
+
+/// How the payload of a graph file is stored.
+#[derive(..StdTraits, Debug, Default)]
+pub enum Compression {
+    /// Stored as is
+    None,
+    /// Compressed with deflate
+    #[default]
+    Deflate,
+}
+
+/// Which structure the payload of a graph file holds.
+#[derive(..StdTraits, Debug, Default)]
+pub enum Layout {
+    /// A [`MonteCarloGraph`](crate::agents::monte_carlo_graph::MonteCarloGraph)
+    #[default]
+    Graph,
+    /// A [`CompactGraph`](crate::agents::compact_graph::CompactGraph)
+    Compact,
+}
+
+/// Which positions the nodes of a stored graph stand for.
+#[derive(..StdTraits, Debug, Default)]
+pub enum Mapping {
+    /// Every position is its own node, see [`Exact`]
+    #[default]
+    Exact,
+    /// Symmetric positions share the node of their canonical form, see [`Canonical`]
+    Canonical,
+}
+
+/// Metadata stored at the start of a graph file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphHeader {
+    /// Schema version of the payload, 0 for files without a header
+    pub version: u32,
+    /// Name of the game the graph was trained on, empty for files without a header
+    pub game: String,
+    /// Number of samples the graph was trained on
+    pub samples: u64,
+    /// Seconds since the Unix epoch at which the file was written
+    pub created: u64,
+    /// How the payload is stored
+    pub compression: Compression,
+    /// Which structure the payload holds
+    pub layout: Layout,
+    /// Which positions the nodes stand for
+    pub mapping: Mapping,
+    /// CRC-32 of the uncompressed payload
+    pub checksum: u32,
+}
+
+impl GraphHeader {
+    /// Returns the time at which the file was written.
+    pub fn created_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+}
+
+/// Reasons a graph file could not be written or loaded.
+#[derive(Debug)]
+pub enum GraphFileError {
+    /// The file could not be read or written
+    Io(std::io::Error),
+    /// The file ended before the header or payload was complete
+    Truncated,
+    /// The file was written by a newer version of the format, or by an old version
+    /// whose payload cannot be migrated
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// The graph was trained on a different game
+    WrongGame { expected: String, found: String },
+    /// The file holds a different kind of graph
+    WrongLayout { expected: Layout, found: Layout },
+    /// The graph was trained with a different node mapping
+    WrongMapping { expected: Mapping, found: Mapping },
+    /// The payload does not match the checksum in the header
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The file has no header and the game did not exist when such files were written
+    NoLegacyLayout { game: String },
+    /// The payload could not be decoded
+    Decode(String),
+    /// The graph could not be encoded
+    Encode(String),
+}
+
+impl Display for GraphFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphFileError::Io(err) => write!(f, "I/O error: {}", err),
+            GraphFileError::Truncated => write!(f, "File is truncated"),
+            GraphFileError::UnsupportedVersion { found, supported } if found > supported => {
+                write!(
+                    f,
+                    "Schema version {} is newer than the supported version {}",
+                    found, supported
+                )
+            }
+            GraphFileError::UnsupportedVersion { found, .. } => write!(
+                f,
+                "Schema version {} is older than the oldest supported version {}",
+                found, OLDEST_VERSION
+            ),
+            GraphFileError::WrongGame { expected, found } => write!(
+                f,
+                "Graph was trained on {} but {} was expected",
+                found, expected
+            ),
+            GraphFileError::WrongLayout { expected, found } => write!(
+                f,
+                "File holds a {:?} graph but a {:?} graph was expected",
+                found, expected
+            ),
+            GraphFileError::WrongMapping { expected, found } => write!(
+                f,
+                "Graph was trained with {:?} nodes but {:?} nodes were expected",
+                found, expected
+            ),
+            GraphFileError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "Checksum mismatch: expected {:08x}, found {:08x}",
+                expected, found
+            ),
+            GraphFileError::NoLegacyLayout { game } => {
+                write!(f, "Files without a header cannot hold {} graphs", game)
+            }
+            GraphFileError::Decode(err) => write!(f, "Could not decode graph: {}", err),
+            GraphFileError::Encode(err) => write!(f, "Could not encode graph: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for GraphFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphFileError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GraphFileError {
+    fn from(value: std::io::Error) -> Self {
+        GraphFileError::Io(value)
+    }
+}
+
+/// Splits `n` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], GraphFileError> {
+    if bytes.len() < n {
+        return Err(GraphFileError::Truncated);
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
+
+/// Splits a fixed number of bytes off the front of `bytes`.
+fn take_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], GraphFileError> {
+    Ok(take(bytes, N)?.try_into().unwrap())
+}
+
+/// Parses the header at the start of `bytes`, returning it with the stored payload.
+///
+/// Returns `Ok(None)` if the data does not start with [`MAGIC`].
+fn parse(mut bytes: &[u8]) -> Result<Option<(GraphHeader, &[u8])>, GraphFileError> {
+    if !bytes.starts_with(&MAGIC) {
+        return Ok(None);
+    }
+    let bytes = &mut bytes;
+    take(bytes, MAGIC.len())?;
+
+    let version = u32::from_le_bytes(take_array(bytes)?);
+    if !(OLDEST_VERSION..=SCHEMA_VERSION).contains(&version) {
+        return Err(GraphFileError::UnsupportedVersion {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    let compression = match u8::from_le_bytes(take_array(bytes)?) {
+        0 => Compression::None,
+        1 => Compression::Deflate,
+        other => {
+            return Err(GraphFileError::Decode(format!(
+                "Unknown compression {}",
+                other
+            )));
+        }
+    };
+    let layout = match u8::from_le_bytes(take_array(bytes)?) {
+        0 => Layout::Graph,
+        1 => Layout::Compact,
+        other => {
+            return Err(GraphFileError::Decode(format!("Unknown layout {}", other)));
+        }
+    };
+    let mapping = match u8::from_le_bytes(take_array(bytes)?) {
+        0 => Mapping::Exact,
+        1 => Mapping::Canonical,
+        other => {
+            return Err(GraphFileError::Decode(format!("Unknown mapping {}", other)));
+        }
+    };
+    let game_len = u16::from_le_bytes(take_array(bytes)?) as usize;
+    let game = String::from_utf8(take(bytes, game_len)?.to_vec())
+        .map_err(|err| GraphFileError::Decode(err.to_string()))?;
+    let samples = u64::from_le_bytes(take_array(bytes)?);
+    let created = u64::from_le_bytes(take_array(bytes)?);
+    let checksum = u32::from_le_bytes(take_array(bytes)?);
+    let length = u64::from_le_bytes(take_array(bytes)?) as usize;
+    let payload = take(bytes, length)?;
+
+    let header = GraphHeader {
+        version,
+        game,
+        samples,
+        created,
+        compression,
+        layout,
+        mapping,
+        checksum,
+    };
+    Ok(Some((header, payload)))
+}
+
+/// Describes a file written before headers were introduced.
+fn legacy_header(data: &[u8]) -> GraphHeader {
+    GraphHeader {
+        version: 0,
+        game: String::new(),
+        samples: 0,
+        created: 0,
+        compression: Compression::None,
+        layout: Layout::Graph,
+        mapping: Mapping::Exact,
+        checksum: crc32fast::hash(data),
+    }
+}
+
+/// Reads the header of a graph file without decoding the payload.
+///
+/// Files written before headers were introduced report version 0 and no game.
+///
+/// # Examples
+/// ```no_run
+/// use games_rs::agents::graph_file::read_header;
+///
+/// let header = read_header("graph.bin").unwrap();
+/// println!("{} samples of {}", header.samples, header.game);
+/// ```
+pub fn read_header(path: &str) -> Result<GraphHeader, GraphFileError> {
+    let data = std::fs::read(path)?;
+    Ok(match parse(&data)? {
+        Some((header, _)) => header,
+        None => legacy_header(&data),
+    })
+}
+
+/// Writes a header for `game`, `samples` and `mapping` followed by the payload.
+///
+/// The data is written and synced to `{path}.tmp`, which then replaces `path`.
+pub(crate) fn write(
+    path: &str,
+    game: &str,
+    samples: u64,
+    layout: Layout,
+    mapping: Mapping,
+    payload: &[u8],
+    compression: Compression,
+) -> Result<(), GraphFileError> {
+    let stored = match compression {
+        Compression::None => payload.to_vec(),
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(payload)?;
+            encoder.finish()?
+        }
+    };
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let game_len = u16::try_from(game.len())
+        .map_err(|_| GraphFileError::Encode(format!("Game name {} is too long", game)))?;
+
+    let mut data = Vec::with_capacity(stored.len() + 64);
+    data.extend_from_slice(&MAGIC);
+    data.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    data.push(match compression {
+        Compression::None => 0,
+        Compression::Deflate => 1,
+    });
+    data.push(match layout {
+        Layout::Graph => 0,
+        Layout::Compact => 1,
+    });
+    data.push(match mapping {
+        Mapping::Exact => 0,
+        Mapping::Canonical => 1,
+    });
+    data.extend_from_slice(&game_len.to_le_bytes());
+    data.extend_from_slice(game.as_bytes());
+    data.extend_from_slice(&samples.to_le_bytes());
+    data.extend_from_slice(&created.to_le_bytes());
+    data.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    data.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+    data.extend_from_slice(&stored);
+
+    let staging = format!("{}.tmp", path);
+    let mut file = std::fs::File::create(&staging)?;
+    file.write_all(&data)?;
+    file.sync_all()?;
+    std::fs::rename(&staging, path)?;
+    Ok(())
+}
+
+/// Reads a graph file, returning its header and the verified, uncompressed payload.
+///
+/// Files without a header are returned whole as a version 0 payload.
+///
+/// # Errors
+/// Besides the format errors, fails if the file was written for a game other than
+/// `game`, or holds a layout or mapping other than `layout` and `mapping`. Files
+/// without a header name no game and are not checked.
+pub(crate) fn read(
+    path: &str,
+    game: &str,
+    layout: Layout,
+    mapping: Mapping,
+) -> Result<(GraphHeader, Vec<u8>), GraphFileError> {
+    let data = std::fs::read(path)?;
+    let Some((header, stored)) = parse(&data)? else {
+        let header = legacy_header(&data);
+        if header.mapping != mapping {
+            return Err(GraphFileError::WrongMapping {
+                expected: mapping,
+                found: header.mapping,
+            });
+        }
+        return Ok((header, data));
+    };
+    if header.game != game {
+        return Err(GraphFileError::WrongGame {
+            expected: game.to_string(),
+            found: header.game,
+        });
+    }
+    if header.layout != layout {
+        return Err(GraphFileError::WrongLayout {
+            expected: layout,
+            found: header.layout,
+        });
+    }
+    if header.mapping != mapping {
+        return Err(GraphFileError::WrongMapping {
+            expected: mapping,
+            found: header.mapping,
+        });
+    }
+
+    let payload = match header.compression {
+        Compression::None => stored.to_vec(),
+        Compression::Deflate => {
+            let mut payload = Vec::new();
+            DeflateDecoder::new(stored)
+                .read_to_end(&mut payload)
+                .map_err(|err| GraphFileError::Decode(err.to_string()))?;
+            payload
+        }
+    };
+
+    let found = crc32fast::hash(&payload);
+    if found != header.checksum {
+        return Err(GraphFileError::ChecksumMismatch {
+            expected: header.checksum,
+            found,
+        });
+    }
+    Ok((header, payload))
+}
+
+mod test {
+    #[test]
+    fn test_header_round_trip_and_corruption() {
+        use super::{
+            Compression, GraphFileError, Layout, Mapping, OLDEST_VERSION, SCHEMA_VERSION, read,
+            read_header, write,
+        };
+
+        let path = std::env::temp_dir().join(format!("graph_file_test_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let payload: Vec<u8> = (0..1000u32).flat_map(|i| (i % 7).to_le_bytes()).collect();
+
+        for compression in [Compression::None, Compression::Deflate] {
+            write(
+                path,
+                "Connect Four",
+                42,
+                Layout::Graph,
+                Mapping::Canonical,
+                &payload,
+                compression,
+            )
+            .unwrap();
+            let header = read_header(path).unwrap();
+            assert_eq!(header.version, SCHEMA_VERSION);
+            assert_eq!(header.game, "Connect Four");
+            assert_eq!(header.samples, 42);
+            assert_eq!(header.compression, compression);
+            assert_eq!(header.mapping, Mapping::Canonical);
+            assert_eq!(
+                read(path, "Connect Four", Layout::Graph, Mapping::Canonical)
+                    .unwrap()
+                    .1,
+                payload
+            );
+        }
+        assert!(matches!(
+            read(path, "Connect Four", Layout::Compact, Mapping::Canonical),
+            Err(GraphFileError::WrongLayout { .. })
+        ));
+        assert!(matches!(
+            read(path, "Connect Four", Layout::Graph, Mapping::Exact),
+            Err(GraphFileError::WrongMapping {
+                expected: Mapping::Exact,
+                found: Mapping::Canonical
+            })
+        ));
+        assert!(std::fs::metadata(path).unwrap().len() < payload.len() as u64 / 4);
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+
+        // Flipping a payload byte is caught by the checksum
+        write(
+            path,
+            "Connect Four",
+            42,
+            Layout::Graph,
+            Mapping::Exact,
+            &payload,
+            Compression::None,
+        )
+        .unwrap();
+        let mut data = std::fs::read(path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        std::fs::write(path, &data).unwrap();
+        assert!(matches!(
+            read(path, "Connect Four", Layout::Graph, Mapping::Exact),
+            Err(GraphFileError::ChecksumMismatch { .. })
+        ));
+
+        data.truncate(last);
+        std::fs::write(path, &data).unwrap();
+        assert!(matches!(
+            read(path, "Connect Four", Layout::Graph, Mapping::Exact),
+            Err(GraphFileError::Truncated)
+        ));
+
+        // Files from a newer version are rejected
+        data[8..12].copy_from_slice(&(SCHEMA_VERSION + 1).to_le_bytes());
+        std::fs::write(path, &data).unwrap();
+        assert!(matches!(
+            read_header(path),
+            Err(GraphFileError::UnsupportedVersion { .. })
+        ));
+
+        // So are versions whose payload layout was not kept
+        for version in 1..OLDEST_VERSION {
+            data[8..12].copy_from_slice(&version.to_le_bytes());
+            std::fs::write(path, &data).unwrap();
+            assert!(matches!(
+                read(path, "Connect Four", Layout::Graph, Mapping::Exact),
+                Err(GraphFileError::UnsupportedVersion { found, .. }) if found == version
+            ));
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,427 @@
+//! Information-Set Monte Carlo Tree Search for Gin Rummy.
+//!
+//! [`InformationSetMcts`] only ever looks at a [`RummyObservation`]: each iteration
+//! samples the unseen cards, the deck and the opponent's hand, consistently with what the
+//! player has seen, and runs one tree descent on that sample. Tree nodes are keyed by what
+//! a player observes of each move rather than by position, so the statistics of every
+//! sample are pooled into the same information sets.
+
+use std::cell::Cell;
+use std::time::Instant;
+
+use rand::seq::IndexedRandom;
+
+use crate::{
+    Game, GameStatus, ImperfectInfoGame,
+    agents::{
+        Agent, ObservingAgent, RandomAgent,
+        mcts::{SearchBudget, SearchStats},
+        monte_carlo_graph::EdgeWeight,
+        selection::SelectionPolicy,
+    },
+    cards::Card,
+    rummy::{Action, Player, Rummy, RummyObservation},
+};
+
+use derive_aliases::derive;
+
+/// Which trees an [`InformationSetMcts`] search builds.
+#[derive(..StdTraits, Debug, Default)]
+pub enum Observers {
+    /// One tree from the searching player's view, used to choose both players' moves
+    #[default]
+    Single,
+    /// One tree per player, each choosing that player's moves from their own view
+    Multiple,
+}
+
+/// What a player observes of a move.
+#[derive(..StdTraits, Debug)]
+enum View {
+    /// The move itself, which is public
+    Action(Action),
+    /// The card a player drew from the deck, seen only by that player
+    Drawn(Card),
+}
+
+/// A node of a search tree, stored in an arena.
+struct Node {
+    /// Observed move leading to this node from its parent
+    view: Option<View>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Player who made the move
+    player: Player,
+    /// Outcomes of the playouts through this node, from the perspective of `player`
+    outcomes: EdgeWeight,
+    /// Number of descents in which the move was legal at the parent
+    availability: usize,
+}
+
+/// A search tree from the view of `owner`.
+struct Tree {
+    owner: Player,
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn new(owner: Player) -> Self {
+        Tree {
+            owner,
+            nodes: vec![Node {
+                view: None,
+                parent: None,
+                children: Vec::new(),
+                player: owner,
+                outcomes: EdgeWeight::default(),
+                availability: 0,
+            }],
+        }
+    }
+
+    fn find(&self, parent: usize, view: View) -> Option<usize> {
+        self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].view == Some(view))
+    }
+
+    /// Returns the child of `parent` for `view`, adding it if needed.
+    ///
+    /// The flag is set if the child was added.
+    fn child(&mut self, parent: usize, view: View, player: Player) -> (usize, bool) {
+        if let Some(child) = self.find(parent, view) {
+            return (child, false);
+        }
+
+        let child = self.nodes.len();
+        self.nodes.push(Node {
+            view: Some(view),
+            parent: Some(parent),
+            children: Vec::new(),
+            player,
+            outcomes: EdgeWeight::default(),
+            availability: 0,
+        });
+        self.nodes[parent].children.push(child);
+        (child, true)
+    }
+}
+
+/// An Information-Set Monte Carlo Tree Search agent for Gin Rummy.
+///
+/// The agent never reads the opponent's hand or the deck order: each iteration deals
+/// the unseen cards at random with [`RummyObservation::determinize`], descends the tree
+/// among the moves legal in that sample, expands one node, finishes the hand with random
+/// play and credits every node on the path with the result. Children are selected with a
+/// [`SelectionPolicy`] (UCB1 by default) over the number of descents in which they were
+/// available, since a move may only be legal in some samples. The most visited move is
+/// played.
+///
+/// With [`Observers::Single`] one tree, from the searching player's view, selects the
+/// moves of both players. With [`Observers::Multiple`] each player selects their moves in
+/// their own tree, so the opponent's choices cannot depend on cards they have not seen.
+///
+/// # Examples
+/// ```
+/// use games_rs::ImperfectInfoGame;
+/// use games_rs::agents::ObservingAgent;
+/// use games_rs::agents::ismcts::{InformationSetMcts, Observers};
+/// use games_rs::agents::mcts::SearchBudget;
+/// use games_rs::rummy::{Player, Rummy};
+///
+/// let game = Rummy::dealt_by(Player::Player2);
+/// let observation = game.observe(Player::Player1);
+///
+/// let agent = InformationSetMcts::new(SearchBudget::Iterations(100))
+///     .with_observers(Observers::Multiple);
+/// let mv = agent.get_observed_move(&observation);
+/// assert!(observation.available_moves().contains(&mv));
+/// assert_eq!(agent.last_search_stats().iterations, 100);
+/// ```
+pub struct InformationSetMcts {
+    budget: SearchBudget,
+    policy: SelectionPolicy,
+    observers: Observers,
+    stats: Cell<SearchStats>,
+}
+
+impl InformationSetMcts {
+    /// Creates a single-observer agent.
+    pub fn new(budget: SearchBudget) -> Self {
+        InformationSetMcts {
+            budget,
+            policy: SelectionPolicy::default(),
+            observers: Observers::default(),
+            stats: Cell::new(SearchStats::default()),
+        }
+    }
+
+    /// Sets the policy used to select moves during the tree descent.
+    pub fn with_policy(mut self, policy: SelectionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets whether one tree or one tree per player is searched.
+    pub fn with_observers(mut self, observers: Observers) -> Self {
+        self.observers = observers;
+        self
+    }
+
+    /// Returns statistics about the most recent search.
+    ///
+    /// Trees are not kept between moves, so no nodes are ever reused.
+    pub fn last_search_stats(&self) -> SearchStats {
+        self.stats.get()
+    }
+
+    /// Returns the index of the tree choosing the moves of `player`.
+    fn tree_of(&self, trees: &[Tree], player: Player) -> usize {
+        trees
+            .iter()
+            .position(|tree| tree.owner == player)
+            .unwrap_or(0)
+    }
+
+    /// Chooses a move for the player to move in `state`, at `node` of `tree`.
+    ///
+    /// Returns the move and whether it still has to be expanded.
+    fn select(&self, tree: &mut Tree, node: usize, state: &Rummy) -> (Action, bool) {
+        let moves = state.get_available_moves();
+        let children = moves
+            .iter()
+            .map(|&mv| tree.find(node, View::Action(mv)))
+            .collect::<Vec<_>>();
+
+        let untried = moves
+            .iter()
+            .zip(&children)
+            .filter(|(_, child)| child.is_none())
+            .map(|(&mv, _)| mv)
+            .collect::<Vec<_>>();
+        if let Some(&mv) = untried.choose(&mut rand::rng()) {
+            return (mv, true);
+        }
+
+        let prior = 1.0 / moves.len() as f64;
+        let mut best = Vec::new();
+        let mut best_value = f64::NEG_INFINITY;
+        for (i, child) in children.into_iter().flatten().enumerate() {
+            let child = &mut tree.nodes[child];
+            child.availability += 1;
+            let value = self
+                .policy
+                .value(Some(&child.outcomes), child.availability, prior);
+            if value > best_value {
+                best_value = value;
+                best.clear();
+            }
+            if value == best_value {
+                best.push(i);
+            }
+        }
+        (moves[*best.choose(&mut rand::rng()).unwrap()], false)
+    }
+
+    /// Runs one determinization, selection, expansion, rollout and backpropagation pass.
+    fn iterate(&self, trees: &mut [Tree], observation: &RummyObservation) {
+        let mut state = observation.determinize(&mut rand::rng());
+        let mut paths = vec![vec![0]; trees.len()];
+        let mut expanded = false;
+
+        // Selection and expansion, following the move in every tree
+        while !expanded && state.get_status() == GameStatus::InProgress {
+            let player = state.get_current_player();
+            let owner = self.tree_of(trees, player);
+            let (mv, expand) =
+                self.select(&mut trees[owner], *paths[owner].last().unwrap(), &state);
+            state.play(mv, player).unwrap();
+            expanded = expand;
+
+            let drawn = match mv {
+                Action::DrawFromDeck => state.get_hand(player).unwrap().last().copied(),
+                _ => None,
+            };
+            for (tree, path) in trees.iter_mut().zip(&mut paths) {
+                let mut views = vec![View::Action(mv)];
+                if tree.owner == player {
+                    views.extend(drawn.map(View::Drawn));
+                }
+                for view in views {
+                    let (child, added) = tree.child(*path.last().unwrap(), view, player);
+                    path.push(child);
+                    expanded |= added;
+                }
+            }
+        }
+
+        // Rollout
+        let rollout = RandomAgent::new();
+        while state.get_status() == GameStatus::InProgress {
+            let mv = rollout.get_move(&state);
+            state.play(mv, state.get_current_player()).unwrap();
+        }
+        let status = state.get_status();
+
+        // Backpropagation
+        for (tree, path) in trees.iter_mut().zip(paths) {
+            for index in path {
+                let node = &mut tree.nodes[index];
+                node.outcomes += match status {
+                    GameStatus::Win(winner) if winner == node.player.into() => (1, 0, 0),
+                    GameStatus::Draw => (0, 0, 1),
+                    _ => (0, 1, 0),
+                }
+                .into();
+            }
+        }
+    }
+}
+
+impl ObservingAgent<Rummy> for InformationSetMcts {
+    /// Searches from `observation` within the budget and plays the most visited move.
+    fn get_observed_move(&self, observation: &RummyObservation) -> Action {
+        let player = observation.player;
+        let mut trees = match self.observers {
+            Observers::Single => vec![Tree::new(player)],
+            Observers::Multiple => vec![Tree::new(player), Tree::new(player.opponent())],
+        };
+
+        let start = Instant::now();
+        let mut iterations = 0;
+        while iterations == 0 || !self.budget.exhausted(iterations, start) {
+            self.iterate(&mut trees, observation);
+            iterations += 1;
+        }
+
+        let moves = observation.available_moves();
+        let tree = &trees[0];
+        let best = moves.iter().copied().max_by_key(|&mv| {
+            tree.find(0, View::Action(mv))
+                .map_or(0, |child| tree.nodes[child].outcomes.simulations())
+        });
+
+        self.stats.set(SearchStats {
+            iterations,
+            reused_nodes: 0,
+            tree_size: trees.iter().map(|tree| tree.nodes.len()).sum(),
+        });
+
+        best.unwrap()
+    }
+}
+
+impl Agent<Rummy> for InformationSetMcts {
+    /// Searches from the current player's observation of `board`, ignoring everything
+    /// they cannot see.
+    fn get_move(&self, board: &Rummy) -> Action {
+        self.get_observed_move(&board.observe(board.get_current_player()))
+    }
+}
+
+mod test {
+    #[test]
+    fn test_ismcts_knocks_before_the_deck_runs_out() {
+        use super::{InformationSetMcts, Observers};
+        use crate::agents::ObservingAgent;
+        use crate::agents::mcts::SearchBudget;
+        use crate::cards::{Card, Deck, Rank, Suit};
+        use crate::rummy::{Action, Hand, Player, RummyObservation};
+
+        let card = |rank, suit| Card::new(suit, rank);
+        let hand: Hand = [
+            card(Rank::Five, Suit::Diamonds),
+            card(Rank::Six, Suit::Diamonds),
+            card(Rank::Seven, Suit::Diamonds),
+            card(Rank::Nine, Suit::Clubs),
+            card(Rank::Nine, Suit::Spades),
+            card(Rank::Nine, Suit::Hearts),
+            card(Rank::Ace, Suit::Clubs),
+            card(Rank::Two, Suit::Diamonds),
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Four, Suit::Clubs),
+            card(Rank::King, Suit::Spades),
+        ]
+        .into_iter()
+        .collect();
+
+        // All but twelve unseen cards are in the discard pile
+        let mut discard = Deck::new_empty();
+        for card in Deck::new()
+            .iter()
+            .filter(|card| !hand.contains(card))
+            .take(29)
+        {
+            discard.push_top(*card);
+        }
+
+        let observation = RummyObservation {
+            player: Player::Player1,
+            current_player: Player::Player1,
+            hand,
+            revealed: Hand::new(),
+            discard,
+            opponent_known: Hand::new(),
+            opponent_hand_size: 10,
+            deck_size: 2,
+            result: None,
+        };
+
+        // Any discard leaves too few cards to draw, so knocking is the only way to win
+        for observers in [Observers::Single, Observers::Multiple] {
+            let agent =
+                InformationSetMcts::new(SearchBudget::Iterations(500)).with_observers(observers);
+            assert_eq!(
+                agent.get_observed_move(&observation),
+                Action::Knock(card(Rank::King, Suit::Spades))
+            );
+            let stats = agent.last_search_stats();
+            assert_eq!(stats.iterations, 500);
+            assert_eq!(stats.reused_nodes, 0);
+            assert!(stats.tree_size > 1);
+        }
+    }
+
+    #[test]
+    fn test_determinizations_match_observation() {
+        use crate::agents::ismcts::{InformationSetMcts, Observers};
+        use crate::agents::mcts::SearchBudget;
+        use crate::agents::{Agent, RandomAgent};
+        use crate::rummy::{Action, Rummy};
+        use crate::{Game, GameStatus, ImperfectInfoGame};
+
+        let agent = InformationSetMcts::new(SearchBudget::Iterations(20))
+            .with_observers(Observers::Multiple);
+        let mut game = Rummy::default();
+        let mut samples = std::collections::HashSet::new();
+        let mut turn = 0;
+        while game.get_status() == GameStatus::InProgress {
+            let player = game.get_current_player();
+            let observation = game.observe(player);
+            for _ in 0..5 {
+                let sample = observation.determinize(&mut rand::rng());
+                assert_eq!(sample.observe(player), observation);
+                assert_eq!(
+                    sample.observe(player.opponent()).opponent_known,
+                    observation.revealed
+                );
+                samples.insert(sample.hash_key());
+            }
+
+            // Take from the discard pile now and then so some cards become known
+            let moves = observation.available_moves();
+            let mv = if moves.contains(&Action::DrawFromDiscard) && turn % 3 == 0 {
+                Action::DrawFromDiscard
+            } else if turn % 10 == 0 {
+                agent.get_move(&game)
+            } else {
+                RandomAgent::new().get_move(&game)
+            };
+            game.play(mv, player).unwrap();
+            turn += 1;
+        }
+        assert!(samples.len() > 5);
+    }
+}
@@ -0,0 +1,386 @@
+//! Online Monte Carlo Tree Search.
+//!
+//! Unlike [`MonteCarloGraphSearch`](crate::agents::MonteCarloGraphSearch), which only
+//! reads statistics gathered offline, [`MonteCarloTreeSearch`] searches from the current
+//! position every time it is asked for a move: it repeatedly selects a path down the tree,
+//! expands one new node, plays the game out with a rollout policy and backpropagates the
+//! result. The subtree below the chosen move is kept for the next decision.
+
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+use rand::seq::{IndexedRandom, SliceRandom};
+
+use crate::{
+    Game, GameStatus,
+    agents::{Agent, RandomAgent, monte_carlo_graph::EdgeWeight, selection::SelectionPolicy},
+};
+
+use derive_aliases::derive;
+
+/// How long a search may run before a move is chosen.
+#[derive(..StdTraits, Debug)]
+pub enum SearchBudget {
+    /// Run a fixed number of iterations
+    Iterations(usize),
+    /// Run until the given time has elapsed
+    Time(Duration),
+    /// Stop at whichever limit is reached first
+    IterationsOrTime(usize, Duration),
+}
+
+impl SearchBudget {
+    pub(crate) fn exhausted(&self, iterations: usize, start: Instant) -> bool {
+        match *self {
+            SearchBudget::Iterations(limit) => iterations >= limit,
+            SearchBudget::Time(limit) => start.elapsed() >= limit,
+            SearchBudget::IterationsOrTime(max, limit) => {
+                iterations >= max || start.elapsed() >= limit
+            }
+        }
+    }
+}
+
+/// Statistics about the most recent search.
+#[derive(..Copy, Debug, Default)]
+pub struct SearchStats {
+    /// Number of iterations run
+    pub iterations: usize,
+    /// Number of nodes carried over from the previous search
+    pub reused_nodes: usize,
+    /// Number of nodes in the tree when the search finished
+    pub tree_size: usize,
+}
+
+/// A node of the search tree, stored in an arena.
+struct Node<M> {
+    /// Move leading to this node from its parent
+    mv: Option<M>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// Moves that have not been expanded yet, in random order
+    untried: Vec<M>,
+    /// Player who made `mv`
+    player: u8,
+    /// Hash key of the position, used to find the position again when reusing the tree
+    key: u64,
+    /// Outcomes of the playouts through this node, from the perspective of `player`
+    outcomes: EdgeWeight,
+}
+
+impl<M: Copy> Node<M> {
+    fn new<G: Game<MoveType = M>>(
+        state: &G,
+        mv: Option<M>,
+        parent: Option<usize>,
+        player: u8,
+    ) -> Self {
+        let mut untried = if state.get_status() == GameStatus::InProgress {
+            state.get_available_moves()
+        } else {
+            Vec::new()
+        };
+        untried.shuffle(&mut rand::rng());
+
+        Node {
+            mv,
+            parent,
+            children: Vec::new(),
+            untried,
+            player,
+            key: state.hash_key(),
+            outcomes: EdgeWeight::default(),
+        }
+    }
+}
+
+/// A search tree rooted at `state`.
+struct Tree<G: Game> {
+    state: G,
+    nodes: Vec<Node<G::MoveType>>,
+}
+
+impl<G: Game> Tree<G> {
+    fn new(state: G) -> Self {
+        Tree {
+            state,
+            nodes: vec![Node::new(&state, None, None, 0)],
+        }
+    }
+
+    /// Re-roots the tree at a child or grandchild matching `board`.
+    ///
+    /// Returns `None` if `board` was not reached from the old root in one or two moves.
+    fn reroot(self, board: &G) -> Option<Self> {
+        let key = board.hash_key();
+        let candidates = self.nodes[0]
+            .children
+            .iter()
+            .flat_map(|&child| std::iter::once(child).chain(self.nodes[child].children.clone()))
+            .collect::<Vec<_>>();
+
+        let new_root = candidates.into_iter().find(|&index| {
+            self.nodes[index].key == key && self.replay(index).as_ref() == Some(board)
+        })?;
+
+        // Move the subtree into a fresh arena in breadth-first order
+        let mut old_nodes = self.nodes.into_iter().map(Some).collect::<Vec<_>>();
+        let mut nodes: Vec<Node<G::MoveType>> = Vec::new();
+        let mut queue = std::collections::VecDeque::from([(new_root, None)]);
+        while let Some((old, parent)) = queue.pop_front() {
+            let index = nodes.len();
+            let mut node = old_nodes[old].take().unwrap();
+            for &child in &node.children {
+                queue.push_back((child, Some(index)));
+            }
+            node.parent = parent;
+            node.children.clear();
+            if let Some(parent) = parent {
+                nodes[parent].children.push(index);
+            }
+            nodes.push(node);
+        }
+        nodes[0].mv = None;
+
+        Some(Tree {
+            state: *board,
+            nodes,
+        })
+    }
+
+    /// Returns the position of a node by replaying the moves from the root.
+    fn replay(&self, mut index: usize) -> Option<G> {
+        let mut path = Vec::new();
+        while let Some(parent) = self.nodes[index].parent {
+            path.push(index);
+            index = parent;
+        }
+
+        let mut state = self.state;
+        for &index in path.iter().rev() {
+            let node = &self.nodes[index];
+            state.play(node.mv?, node.player.into()).ok()?;
+        }
+        Some(state)
+    }
+}
+
+/// An agent that runs Monte Carlo Tree Search from the current position.
+///
+/// Each iteration selects a path with a [`SelectionPolicy`] (UCB1 by default), expands one
+/// untried move, finishes the game with the rollout policy and credits every node on
+/// the path with the result. The most visited move is played. Works for any [`Game`].
+///
+/// # Examples
+/// ```
+/// use games_rs::Game;
+/// use games_rs::agents::Agent;
+/// use games_rs::agents::mcts::{MonteCarloTreeSearch, SearchBudget};
+/// use games_rs::connect_four::ConnectFour;
+///
+/// let mut game = ConnectFour::new();
+/// for mv in [3, 0, 3, 0, 3, 0] {
+///     game.play(mv, game.get_current_player()).unwrap();
+/// }
+///
+/// let agent = MonteCarloTreeSearch::new(SearchBudget::Iterations(1000));
+/// assert_eq!(agent.get_move(&game), 3);
+/// ```
+pub struct MonteCarloTreeSearch<G: Game> {
+    budget: SearchBudget,
+    policy: SelectionPolicy,
+    rollout: Box<dyn Agent<G>>,
+    reuse_tree: bool,
+    tree: RefCell<Option<Tree<G>>>,
+    stats: Cell<SearchStats>,
+}
+
+impl<G: Game + 'static> MonteCarloTreeSearch<G> {
+    /// Creates an agent with uniformly random rollouts.
+    pub fn new(budget: SearchBudget) -> Self {
+        Self::with_rollout_policy(budget, Box::new(RandomAgent::new()))
+    }
+}
+
+impl<G: Game> MonteCarloTreeSearch<G> {
+    /// Creates an agent whose rollouts are played by `rollout`.
+    pub fn with_rollout_policy(budget: SearchBudget, rollout: Box<dyn Agent<G>>) -> Self {
+        MonteCarloTreeSearch {
+            budget,
+            policy: SelectionPolicy::default(),
+            rollout,
+            reuse_tree: true,
+            tree: RefCell::new(None),
+            stats: Cell::new(SearchStats::default()),
+        }
+    }
+
+    /// Sets the policy used to select moves during the tree descent.
+    pub fn with_policy(mut self, policy: SelectionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Enables or disables keeping the subtree of the previous search.
+    pub fn with_tree_reuse(mut self, reuse_tree: bool) -> Self {
+        self.reuse_tree = reuse_tree;
+        self
+    }
+
+    /// Returns statistics about the most recent search.
+    pub fn last_search_stats(&self) -> SearchStats {
+        self.stats.get()
+    }
+
+    /// Selects the child of `parent` valued highest by the selection policy.
+    fn select_child(&self, nodes: &[Node<G::MoveType>], parent: usize) -> usize {
+        let children = &nodes[parent].children;
+        let stats = children
+            .iter()
+            .map(|&child| Some(nodes[child].outcomes))
+            .collect::<Vec<_>>();
+
+        let best = self.policy.best_moves(&stats);
+        children[*best.choose(&mut rand::rng()).unwrap()]
+    }
+
+    /// Runs one selection, expansion, rollout and backpropagation pass.
+    fn iterate(&self, tree: &mut Tree<G>) {
+        let Tree { state, nodes } = tree;
+        let mut records = Vec::new();
+        let mut index = 0;
+
+        // Selection
+        while nodes[index].untried.is_empty() && !nodes[index].children.is_empty() {
+            index = self.select_child(nodes, index);
+            let node = &nodes[index];
+            records.push(
+                state
+                    .make_move(node.mv.unwrap(), node.player.into())
+                    .unwrap(),
+            );
+        }
+
+        // Expansion
+        if let Some(mv) = nodes[index].untried.pop() {
+            let player = state.get_current_player();
+            records.push(state.make_move(mv, player).unwrap());
+            let child = nodes.len();
+            nodes.push(Node::new(state, Some(mv), Some(index), player.into()));
+            nodes[index].children.push(child);
+            index = child;
+        }
+
+        // Rollout
+        let mut rollout = *state;
+        while rollout.get_status() == GameStatus::InProgress {
+            let mv = self.rollout.get_move(&rollout);
+            rollout.play(mv, rollout.get_current_player()).unwrap();
+        }
+        let status = rollout.get_status();
+
+        for record in records.into_iter().rev() {
+            state.undo(record);
+        }
+
+        // Backpropagation
+        let mut current = Some(index);
+        while let Some(index) = current {
+            let node = &mut nodes[index];
+            node.outcomes += match status {
+                GameStatus::Win(winner) if winner == node.player => (1, 0, 0),
+                GameStatus::Draw => (0, 0, 1),
+                _ => (0, 1, 0),
+            }
+            .into();
+            current = node.parent;
+        }
+    }
+}
+
+impl<G: Game> Agent<G> for MonteCarloTreeSearch<G> {
+    /// Searches from `board` within the budget and plays the most visited move.
+    fn get_move(&self, board: &G) -> <G as Game>::MoveType {
+        let previous = self.tree.borrow_mut().take();
+        let mut tree = previous
+            .filter(|_| self.reuse_tree)
+            .and_then(|tree| tree.reroot(board))
+            .unwrap_or_else(|| Tree::new(*board));
+        let reused_nodes = tree.nodes.len() - 1;
+
+        let start = Instant::now();
+        let mut iterations = 0;
+        while iterations == 0 || !self.budget.exhausted(iterations, start) {
+            self.iterate(&mut tree);
+            iterations += 1;
+        }
+
+        let nodes = &tree.nodes;
+        let best = nodes[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| nodes[child].outcomes.simulations())
+            .map(|&child| nodes[child].mv.unwrap());
+
+        self.stats.set(SearchStats {
+            iterations,
+            reused_nodes,
+            tree_size: nodes.len(),
+        });
+        *self.tree.borrow_mut() = Some(tree);
+
+        best.unwrap_or_else(|| board.get_available_moves()[0])
+    }
+}
+
+mod test {
+    #[test]
+    fn test_mcts_finds_tactics() {
+        use super::{MonteCarloTreeSearch, SearchBudget};
+        use crate::Game;
+        use crate::agents::Agent;
+        use crate::connect_four::ConnectFour;
+
+        let play = |moves: &[usize]| {
+            let mut game = ConnectFour::new();
+            for &mv in moves {
+                game.play(mv, game.get_current_player()).unwrap();
+            }
+            game
+        };
+
+        // Red must block the vertical threat in column 0
+        let agent = MonteCarloTreeSearch::new(SearchBudget::Iterations(3000));
+        assert_eq!(agent.get_move(&play(&[0, 3, 0, 6, 0])), 0);
+
+        // Red wins immediately in column 3
+        assert_eq!(agent.get_move(&play(&[3, 0, 3, 0, 3, 0])), 3);
+    }
+
+    #[test]
+    fn test_mcts_reuses_subtree() {
+        use super::{MonteCarloTreeSearch, SearchBudget};
+        use crate::Game;
+        use crate::agents::Agent;
+        use crate::ultimate_ttt::UltimateTTT;
+
+        let agent = MonteCarloTreeSearch::new(SearchBudget::Iterations(2000));
+        let mut game = UltimateTTT::new();
+
+        let mv = agent.get_move(&game);
+        game.play(mv, game.get_current_player()).unwrap();
+        let reply = game.get_available_moves()[0];
+        game.play(reply, game.get_current_player()).unwrap();
+
+        agent.get_move(&game);
+        let stats = agent.last_search_stats();
+        assert!(stats.reused_nodes > 0);
+        assert_eq!(stats.iterations, 2000);
+        assert!(stats.tree_size > stats.reused_nodes);
+        assert!(stats.tree_size <= stats.reused_nodes + 2001);
+
+        // An unrelated position starts from scratch
+        agent.get_move(&UltimateTTT::new());
+        assert_eq!(agent.last_search_stats().reused_nodes, 0);
+    }
+}
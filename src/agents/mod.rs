@@ -4,16 +4,33 @@
 //! implementing the `GameBoard` trait. Agents range from human players to
 //! sophisticated Monte Carlo graph search algorithms.
 
+pub mod analysis;
+pub mod compact_graph;
+pub mod connect_four_solver;
+pub mod graph_export;
+pub mod graph_file;
+pub mod ismcts;
+pub mod mcts;
 pub mod monte_carlo_graph;
 pub mod scorer;
+pub mod selection;
 pub mod train;
+pub mod transposition;
 
 use rand::Rng;
 use rand::seq::IndexedRandom;
+use std::cell::{Cell, RefCell};
 use std::cmp::max;
 use std::cmp::min;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::{Game, GameStatus, agents::monte_carlo_graph::MonteCarloGraph};
+use crate::{
+    Game, GameStatus, ImperfectInfoGame,
+    agents::monte_carlo_graph::{Exact, GraphPosition, MonteCarloGraph, NodeMapping},
+    agents::selection::SelectionPolicy,
+    agents::transposition::{Bound, Entry, ReplacementPolicy, TableStats, TranspositionTable},
+};
 
 /// Trait for game-playing agents.
 ///
@@ -30,6 +47,38 @@ pub trait Agent<G: Game> {
     fn get_move(&self, board: &G) -> <G as Game>::MoveType;
 }
 
+/// Trait for agents of imperfect-information games, which only see what their player
+/// may observe.
+pub trait ObservingAgent<G: ImperfectInfoGame> {
+    /// Selects a move from the observing player's view of the game.
+    fn get_observed_move(&self, observation: &G::Observation) -> <G as Game>::MoveType;
+}
+
+/// Shares one agent between threads, e.g. a search over a large trained graph used by
+/// every game of a parallel batch.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+/// use games_rs::agents::{Agent, MonteCarloGraphSearch};
+/// use games_rs::agents::train::play_batch_parallel;
+/// use games_rs::connect_four::ConnectFour;
+///
+/// let agent = Arc::new(MonteCarloGraphSearch::<ConnectFour>::new());
+/// let samples = play_batch_parallel::<ConnectFour, _, _>(
+///     || Box::new(agent.clone()),
+///     || Box::new(agent.clone()),
+///     4,
+///     None,
+/// );
+/// assert_eq!(samples.len(), 4);
+/// ```
+impl<G: Game, A: Agent<G> + ?Sized> Agent<G> for Arc<A> {
+    fn get_move(&self, board: &G) -> <G as Game>::MoveType {
+        (**self).get_move(board)
+    }
+}
+
 /// An interactive agent that prompts a human player for moves via stdin.
 ///
 /// This agent displays the current board state and requests move input from
@@ -107,85 +156,96 @@ impl<G: Game> Agent<G> for RandomAgent<G> {
     }
 }
 
-/// An agent using Monte Carlo Graph Search with UCT (Upper Confidence bounds applied to Trees).
+impl<G: ImperfectInfoGame> ObservingAgent<G> for RandomAgent<G> {
+    /// Selects a random move from the moves available to the observing player.
+    fn get_observed_move(&self, observation: &G::Observation) -> <G as Game>::MoveType {
+        let available_moves = G::observed_moves(observation);
+        let mut rng = rand::rng();
+        *available_moves.choose(&mut rng).unwrap()
+    }
+}
+
+/// An agent using Monte Carlo Graph Search over learned statistics.
 ///
 /// This agent maintains a graph of game states and transitions, learning from game outcomes
-/// to make increasingly better decisions. It uses the UCT formula to balance exploration
-/// and exploitation when selecting moves.
-pub struct MonteCarloGraphSearch<G: Game> {
-    graph: MonteCarloGraph<G>,
+/// to make increasingly better decisions. Moves are valued from the stored edge statistics
+/// with a [`SelectionPolicy`], UCB1 by default. Positions are looked up through the graph's
+/// node mapping `M`, so a graph trained with merged symmetries is queried the same way.
+pub struct MonteCarloGraphSearch<G: Game, M = Exact> {
+    graph: MonteCarloGraph<G, M>,
+    policy: SelectionPolicy,
 }
 
-impl<G: Game> MonteCarloGraphSearch<G> {
+impl<G: Game + GraphPosition> MonteCarloGraphSearch<G> {
     /// Creates a new Monte Carlo Graph Search agent with an empty graph.
     pub fn new() -> Self {
         MonteCarloGraphSearch {
             graph: MonteCarloGraph::new(),
+            policy: SelectionPolicy::default(),
         }
     }
+}
 
+impl<G: Game, M: NodeMapping<G>> MonteCarloGraphSearch<G, M> {
     /// Creates a Monte Carlo Graph Search agent from an existing graph.
     ///
     /// This allows loading a pre-trained graph to continue learning or use learned strategies.
     ///
     /// # Arguments
     /// * `graph` - A pre-existing Monte Carlo graph
-    pub fn from_graph(graph: MonteCarloGraph<G>) -> Self {
-        MonteCarloGraphSearch { graph }
+    pub fn from_graph(graph: MonteCarloGraph<G, M>) -> Self {
+        MonteCarloGraphSearch {
+            graph,
+            policy: SelectionPolicy::default(),
+        }
     }
-}
 
-impl<G: Game> Agent<G> for MonteCarloGraphSearch<G> {
-    /// Selects a move using the UCT (Upper Confidence bounds applied to Trees) formula.
+    /// Sets the policy used to value moves.
     ///
-    /// For each available move, calculates a UCT value that balances:
-    /// - Exploitation: moves with high win rates
-    /// - Exploration: moves that haven't been tried much
+    /// Use [`SelectionPolicy::greedy`] or [`SelectionPolicy::robust`] to play the
+    /// strongest known move without exploring.
     ///
-    /// The formula used is: w/n + sqrt(2 * ln(N) / n)
-    /// where w = wins, n = simulations for this move, N = total simulations from resulting state.
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::MonteCarloGraphSearch;
+    /// use games_rs::agents::selection::SelectionPolicy;
+    /// use games_rs::connect_four::ConnectFour;
     ///
-    /// Returns a random choice among the highest-valued moves.
+    /// let agent = MonteCarloGraphSearch::<ConnectFour>::new()
+    ///     .with_policy(SelectionPolicy::ucb1(0.5).with_draw_value(0.3));
+    /// ```
+    pub fn with_policy(mut self, policy: SelectionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<G: Game, M: NodeMapping<G>> Agent<G> for MonteCarloGraphSearch<G, M> {
+    /// Selects the move valued highest by the selection policy.
+    ///
+    /// Each available move is valued from the statistics of the edge it leads along;
+    /// moves without an edge are unvisited. Returns a random choice among the
+    /// highest-valued moves.
     fn get_move(&self, board: &G) -> <G as Game>::MoveType {
         let available_moves = board.get_available_moves();
+        let player = board.get_current_player();
+        let node = M::node(board);
+        let mut next_board = *board;
 
-        let values = available_moves
+        let stats = available_moves
             .iter()
             .map(|mv| {
-                let next_board = {
-                    let mut next_board = board.clone();
-                    let _ = next_board.play(*mv, board.get_current_player());
-                    next_board
-                };
-                let edge_weight = self.graph.edge_weight(board.clone(), next_board.clone());
-                if edge_weight.is_none() {
-                    (mv, 1f64 + 2f64.sqrt())
-                } else {
-                    let edge_weight = edge_weight.unwrap();
-                    let target_count = self.graph.get_aggregate_outcomes(&next_board).simulations();
-                    let w = (edge_weight.wins() + 1) as f64;
-                    let n = (edge_weight.simulations() + 1) as f64;
-                    let N = (target_count + 1) as f64;
-                    (mv, w / n + (2.0 * N.ln() / n).sqrt())
-                }
+                let record = next_board.make_move(*mv, player).unwrap();
+                let edge_weight = self.graph.edge_weight(node, M::node(&next_board)).copied();
+                next_board.undo(record);
+                edge_weight
             })
             .collect::<Vec<_>>();
 
-        let max = values
-            .iter()
-            .max_by(|a, b| b.1.partial_cmp(&a.1).unwrap())
-            .unwrap()
-            .1;
-
-        let maximizers = values
-            .iter()
-            .filter(|(_, v)| *v > max - 1e-6)
-            .collect::<Vec<_>>();
+        let maximizers = self.policy.best_moves(&stats);
 
         let mut random = rand::rng();
-        let choice = maximizers[random.random_range(0..maximizers.len())];
-
-        *choice.0
+        available_moves[maximizers[random.random_range(0..maximizers.len())]]
     }
 }
 
@@ -197,31 +257,140 @@ pub trait ScoreFunction<G: Game> {
     }
 }
 
+/// A depth-limited minimax agent with alpha-beta pruning.
+///
+/// Leaves are evaluated with a [`ScoreFunction`]. An optional [`TranspositionTable`]
+/// caches search results by [`Game::hash_key`], so positions reached through different
+/// move orders are searched once and the best move found is tried first on revisits.
 pub struct MinimaxAgent<G: Game, ScoreFn: ScoreFunction<G>> {
     depth: usize,
     score_fn: ScoreFn,
+    table: Option<RefCell<TranspositionTable<G::MoveType>>>,
+    time_budget: Option<Duration>,
+    aspiration_window: f32,
+    search: SearchState,
     _marker: std::marker::PhantomData<G>,
 }
 
+/// Bookkeeping for a time-controlled search.
+#[derive(Default)]
+struct SearchState {
+    deadline: Cell<Option<Instant>>,
+    aborted: Cell<bool>,
+    nodes: Cell<u64>,
+    /// Set when some line was cut off by the depth limit rather than the end of the game
+    horizon_reached: Cell<bool>,
+    completed_depth: Cell<usize>,
+}
+
 impl<G: Game, ScoreFn: ScoreFunction<G>> MinimaxAgent<G, ScoreFn> {
     pub fn new(depth: usize, score_fn: ScoreFn) -> Self {
         MinimaxAgent {
             depth,
             score_fn,
+            table: None,
+            time_budget: None,
+            aspiration_window: Self::DEFAULT_ASPIRATION_WINDOW,
+            search: SearchState::default(),
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Default half-width of the aspiration window, in score units.
+    pub const DEFAULT_ASPIRATION_WINDOW: f32 = 10.0;
+
+    /// Switches to iterative deepening under a time budget per move.
+    ///
+    /// Each iteration searches one ply deeper, starting with the previous best move and
+    /// a narrow aspiration window around the previous score. When the budget runs out
+    /// the unfinished iteration is discarded and the best move of the last completed
+    /// iteration is played. The depth given to [`MinimaxAgent::new`] becomes the maximum
+    /// depth.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use games_rs::agents::{Agent, MinimaxAgent, scorer::naive_scorer::NaiveScorer};
+    /// use games_rs::ultimate_ttt::UltimateTTT;
+    ///
+    /// let agent = MinimaxAgent::new(usize::MAX, NaiveScorer::<UltimateTTT>::new())
+    ///     .with_time_budget(Duration::from_millis(50));
+    /// agent.get_move(&UltimateTTT::new());
+    ///
+    /// assert!(agent.last_search_depth() >= 1);
+    /// ```
+    pub fn with_time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Sets the half-width of the aspiration window used by iterative deepening.
+    ///
+    /// Pass `f32::INFINITY` to always search with a full window.
+    pub fn with_aspiration_window(mut self, window: f32) -> Self {
+        self.aspiration_window = window;
+        self
+    }
+
+    /// Returns the depth of the last search that ran to completion.
+    pub fn last_search_depth(&self) -> usize {
+        self.search.completed_depth.get()
+    }
+
+    /// Adds a transposition table using at most `bytes` bytes of memory.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::{Agent, MinimaxAgent, scorer::naive_scorer::NaiveScorer};
+    /// use games_rs::agents::transposition::ReplacementPolicy;
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// let agent = MinimaxAgent::new(4, NaiveScorer::<ConnectFour>::new())
+    ///     .with_transposition_table(1 << 20, ReplacementPolicy::DepthPreferred);
+    /// agent.get_move(&ConnectFour::new());
+    ///
+    /// assert!(agent.table_stats().unwrap().hits > 0);
+    /// ```
+    pub fn with_transposition_table(mut self, bytes: usize, policy: ReplacementPolicy) -> Self {
+        self.table = Some(RefCell::new(TranspositionTable::with_memory(bytes, policy)));
+        self
+    }
+
+    /// Returns the transposition table statistics, if the agent has a table.
+    pub fn table_stats(&self) -> Option<TableStats> {
+        self.table.as_ref().map(|table| table.borrow().stats())
+    }
+
+    /// Clears the transposition table and its statistics.
+    pub fn clear_table(&self) {
+        if let Some(table) = &self.table {
+            let mut table = table.borrow_mut();
+            table.clear();
+            table.reset_stats();
+        }
+    }
+
+    /// Alpha-beta search that plays and undoes moves on `board` in place.
+    ///
+    /// Values are from the perspective of `player`. `board` is restored to its original
+    /// state before returning.
     fn alpha_beta(
         &self,
-        board: &G,
+        board: &mut G,
         mv: G::MoveType,
         depth: usize,
         alpha: f32,
         beta: f32,
         player: G::PlayerType,
     ) -> f32 {
+        if self.out_of_time() {
+            return 0.0;
+        }
+
         if depth == 0 || board.get_status() != GameStatus::InProgress {
+            if depth == 0 && board.get_status() == GameStatus::InProgress {
+                self.search.horizon_reached.set(true);
+            }
             let sign = if player == board.get_current_player() {
                 1.0
             } else {
@@ -230,15 +399,46 @@ impl<G: Game, ScoreFn: ScoreFunction<G>> MinimaxAgent<G, ScoreFn> {
             return sign * self.score_fn.score(board, &mv, board.get_current_player());
         }
 
+        let (alpha_orig, beta_orig) = (alpha, beta);
         let mut alpha = alpha;
         let mut beta = beta;
+        let current_player = board.get_current_player();
+        let maximizing = player == current_player;
+        let sign = if maximizing { 1.0 } else { -1.0 };
+
+        // Table entries are stored from the perspective of the player to move
+        let mut moves = board.get_available_moves();
+        if let Some(table) = &self.table {
+            let mut table = table.borrow_mut();
+            if let Some(entry) = table.probe(board.hash_key()) {
+                if entry.depth as usize >= depth {
+                    let (lo, hi) = if maximizing {
+                        (alpha, beta)
+                    } else {
+                        (-beta, -alpha)
+                    };
+                    if let Some(value) = entry.cutoff(lo, hi) {
+                        return sign * value;
+                    }
+                }
+                if let Some(best) = entry.best_move
+                    && let Some(index) = moves.iter().position(|mv| *mv == best)
+                {
+                    moves[..=index].rotate_right(1);
+                }
+            }
+        }
 
-        if player == board.get_current_player() {
+        let mut best_move = None;
+        let value = if maximizing {
             let mut max_eval = f32::NEG_INFINITY;
-            for mv in board.get_available_moves() {
-                let mut new_board = board.clone();
-                new_board.play(mv, board.get_current_player()).unwrap();
-                let eval = self.alpha_beta(&new_board, mv, depth - 1, alpha, beta, player);
+            for mv in moves {
+                let record = board.make_move(mv, current_player).unwrap();
+                let eval = self.alpha_beta(board, mv, depth - 1, alpha, beta, player);
+                board.undo(record);
+                if eval > max_eval || best_move.is_none() {
+                    best_move = Some(mv);
+                }
                 max_eval = f32::max(max_eval, eval);
                 alpha = f32::max(alpha, eval);
                 if beta <= alpha {
@@ -248,10 +448,13 @@ impl<G: Game, ScoreFn: ScoreFunction<G>> MinimaxAgent<G, ScoreFn> {
             max_eval
         } else {
             let mut min_eval = f32::INFINITY;
-            for mv in board.get_available_moves() {
-                let mut new_board = board.clone();
-                new_board.play(mv, board.get_current_player()).unwrap();
-                let eval = self.alpha_beta(&new_board, mv, depth - 1, alpha, beta, player);
+            for mv in moves {
+                let record = board.make_move(mv, current_player).unwrap();
+                let eval = self.alpha_beta(board, mv, depth - 1, alpha, beta, player);
+                board.undo(record);
+                if eval < min_eval || best_move.is_none() {
+                    best_move = Some(mv);
+                }
                 min_eval = f32::min(min_eval, eval);
                 beta = f32::min(beta, eval);
                 if beta <= alpha {
@@ -259,30 +462,176 @@ impl<G: Game, ScoreFn: ScoreFunction<G>> MinimaxAgent<G, ScoreFn> {
                 }
             }
             min_eval
+        };
+
+        if self.search.aborted.get() {
+            return value;
+        }
+
+        if let Some(table) = &self.table {
+            // Failing low for `player` is failing high for a minimizing mover
+            let bound = if value <= alpha_orig {
+                if maximizing {
+                    Bound::Upper
+                } else {
+                    Bound::Lower
+                }
+            } else if value >= beta_orig {
+                if maximizing {
+                    Bound::Lower
+                } else {
+                    Bound::Upper
+                }
+            } else {
+                Bound::Exact
+            };
+            table.borrow_mut().store(Entry::new(
+                board.hash_key(),
+                depth.min(u8::MAX as usize) as u8,
+                sign * value,
+                bound,
+                best_move,
+            ));
+        }
+
+        value
+    }
+
+    /// Returns `true` once the deadline of a time-controlled search has passed.
+    ///
+    /// The clock is only read every few hundred nodes.
+    fn out_of_time(&self) -> bool {
+        let Some(deadline) = self.search.deadline.get() else {
+            return false;
+        };
+        if !self.search.aborted.get() {
+            let nodes = self.search.nodes.get().wrapping_add(1);
+            self.search.nodes.set(nodes);
+            if nodes % 256 == 0 && Instant::now() >= deadline {
+                self.search.aborted.set(true);
+            }
         }
+        self.search.aborted.get()
+    }
+
+    /// Searches the root moves in order with window `(alpha, beta)`.
+    ///
+    /// Returns the best move and its score, or `None` if the deadline passed.
+    fn search_root(
+        &self,
+        board: &mut G,
+        moves: &[G::MoveType],
+        depth: usize,
+        mut alpha: f32,
+        beta: f32,
+    ) -> Option<(G::MoveType, f32)> {
+        let player = board.get_current_player();
+        let mut best: Option<(G::MoveType, f32)> = None;
+
+        for &mv in moves {
+            let record = board.make_move(mv, player).unwrap();
+            let score = self.alpha_beta(board, mv, depth - 1, alpha, beta, player);
+            board.undo(record);
+            if self.search.aborted.get() {
+                return None;
+            }
+
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((mv, score));
+            }
+            alpha = f32::max(alpha, score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best
+    }
+
+    /// Iterative deepening until the time budget runs out or the maximum depth is reached.
+    fn iterative_deepening(&self, board: &G, budget: Duration) -> G::MoveType {
+        let mut board = *board;
+        let mut moves = board.get_available_moves();
+
+        self.search.deadline.set(Some(Instant::now() + budget));
+        self.search.aborted.set(false);
+        self.search.completed_depth.set(0);
+        if let Some(table) = &self.table {
+            table.borrow_mut().new_search();
+        }
+
+        let mut best_move = moves[0];
+        let mut previous_score = None;
+
+        for depth in 1..=self.depth.max(1) {
+            self.search.horizon_reached.set(false);
+
+            let (alpha, beta) = match previous_score {
+                Some(score) => (
+                    score - self.aspiration_window,
+                    score + self.aspiration_window,
+                ),
+                None => (f32::NEG_INFINITY, f32::INFINITY),
+            };
+            let mut result = self.search_root(&mut board, &moves, depth, alpha, beta);
+            if let Some((_, score)) = result
+                && (score <= alpha || score >= beta)
+            {
+                // The score fell outside the aspiration window, so it is only a bound
+                result =
+                    self.search_root(&mut board, &moves, depth, f32::NEG_INFINITY, f32::INFINITY);
+            }
+
+            let Some((mv, score)) = result else {
+                break;
+            };
+            best_move = mv;
+            previous_score = Some(score);
+            self.search.completed_depth.set(depth);
+
+            // Search the best move first in the next iteration
+            let index = moves.iter().position(|m| *m == mv).unwrap();
+            moves[..=index].rotate_right(1);
+
+            if !self.search.horizon_reached.get() {
+                // Every line ended with the game, so deeper searches cannot change anything
+                break;
+            }
+        }
+
+        self.search.deadline.set(None);
+        best_move
     }
 }
 
 impl<G: Game, ScoreFn: ScoreFunction<G>> Agent<G> for MinimaxAgent<G, ScoreFn> {
     fn get_move(&self, board: &G) -> <G as Game>::MoveType {
+        if let Some(budget) = self.time_budget {
+            return self.iterative_deepening(board, budget);
+        }
+
         let available_moves = board.get_available_moves();
+        let player = board.get_current_player();
+        let mut board = *board;
+
+        if let Some(table) = &self.table {
+            table.borrow_mut().new_search();
+        }
 
         let mut best_move = available_moves[0];
         let mut best_score = f32::NEG_INFINITY;
 
         for mv in available_moves {
+            let record = board.make_move(mv, player).unwrap();
             let score = self.alpha_beta(
-                &{
-                    let mut tmp_board = board.clone();
-                    tmp_board.play(mv, board.get_current_player()).unwrap();
-                    tmp_board
-                },
+                &mut board,
                 mv,
                 self.depth - 1,
                 f32::NEG_INFINITY,
                 f32::INFINITY,
-                board.get_current_player(),
+                player,
             );
+            board.undo(record);
 
             if score > best_score {
                 best_score = score;
@@ -293,3 +642,60 @@ impl<G: Game, ScoreFn: ScoreFunction<G>> Agent<G> for MinimaxAgent<G, ScoreFn> {
         best_move
     }
 }
+
+mod test {
+    #[test]
+    fn test_transposition_table_preserves_choices() {
+        use super::{Agent, MinimaxAgent, scorer::naive_scorer::NaiveScorer};
+        use crate::agents::transposition::ReplacementPolicy;
+        use crate::connect_four::ConnectFour;
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+
+        let plain = MinimaxAgent::new(4, NaiveScorer::<ConnectFour>::new());
+        let cached = MinimaxAgent::new(4, NaiveScorer::<ConnectFour>::new())
+            .with_transposition_table(1 << 20, ReplacementPolicy::DepthPreferred);
+
+        let mut rng = rand::rng();
+        let mut game = ConnectFour::new();
+        let mut hits = 0;
+        while game.get_status() == GameStatus::InProgress {
+            // Deeper entries from earlier searches may legitimately change the choice
+            cached.clear_table();
+            assert_eq!(plain.get_move(&game), cached.get_move(&game));
+            hits += cached.table_stats().unwrap().hits;
+            let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+            game.play(mv, game.get_current_player()).unwrap();
+        }
+
+        assert!(hits > 0);
+    }
+
+    #[test]
+    fn test_time_budget_is_respected() {
+        use super::{Agent, MinimaxAgent, scorer::naive_scorer::NaiveScorer};
+        use crate::Game;
+        use crate::agents::transposition::ReplacementPolicy;
+        use crate::connect_four::ConnectFour;
+        use crate::ultimate_ttt::UltimateTTT;
+        use std::time::{Duration, Instant};
+
+        let agent = MinimaxAgent::new(usize::MAX, NaiveScorer::<UltimateTTT>::new())
+            .with_time_budget(Duration::from_millis(100))
+            .with_transposition_table(1 << 20, ReplacementPolicy::DepthPreferred);
+        let start = Instant::now();
+        let mv = agent.get_move(&UltimateTTT::new());
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(UltimateTTT::new().get_available_moves().contains(&mv));
+        assert!(agent.last_search_depth() >= 2);
+
+        // Red wins immediately in column 3
+        let mut game = ConnectFour::new();
+        for mv in [3, 0, 3, 0, 3, 0] {
+            game.play(mv, game.get_current_player()).unwrap();
+        }
+        let agent = MinimaxAgent::new(usize::MAX, NaiveScorer::<ConnectFour>::new())
+            .with_time_budget(Duration::from_millis(50));
+        assert_eq!(agent.get_move(&game), 3);
+    }
+}
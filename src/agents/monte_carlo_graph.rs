@@ -5,8 +5,10 @@
 //! win/simulation statistics.
 
 use std::{
-    collections::HashSet,
-    ops::{Add, AddAssign},
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    marker::PhantomData,
+    ops::{Add, AddAssign, Sub},
 };
 
 use petgraph::Direction;
@@ -14,7 +16,18 @@ use petgraph::prelude::DiGraphMap;
 use petgraph::visit::EdgeRef;
 use serde::{Deserialize, Serialize};
 
-use crate::{Game, GameStatus, PlayThrough, agents::train::TrainableComponent};
+use indicatif::MultiProgress;
+use rayon::prelude::*;
+
+use crate::{
+    Game, GameStatus, PlayThrough, Symmetric,
+    agents::graph_file::{self, Compression, GraphFileError, GraphMapping, GraphNode, Layout},
+    agents::train::TrainableComponent,
+    common::defaults,
+    connect_four::ConnectFour,
+    rummy::{GinRummyMatch, Rummy},
+    ultimate_ttt::UltimateTTT,
+};
 
 use derive_aliases::derive;
 
@@ -51,7 +64,7 @@ pub enum State {
 /// assert_eq!(weight.draws(), 2);
 /// assert_eq!(weight.simulations(), 10);
 /// ```
-#[derive(..StdTraits, Serialize, Deserialize, Debug)]
+#[derive(..StdTraits, Serialize, Deserialize, Debug, Default)]
 pub struct EdgeWeight {
     wins: usize,
     losses: usize,
@@ -97,6 +110,23 @@ impl EdgeWeight {
     pub fn simulations(&self) -> usize {
         self.wins + self.losses + self.draws
     }
+
+    /// Returns the fraction of simulations that were wins, or 0 if there are none.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::EdgeWeight;
+    ///
+    /// let weight: EdgeWeight = (3, 1, 0).into();
+    /// assert_eq!(weight.win_rate(), 0.75);
+    /// ```
+    pub fn win_rate(&self) -> f64 {
+        if self.simulations() == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.simulations() as f64
+        }
+    }
 }
 
 impl Add<EdgeWeight> for EdgeWeight {
@@ -111,6 +141,18 @@ impl Add<EdgeWeight> for EdgeWeight {
     }
 }
 
+impl Sub<EdgeWeight> for EdgeWeight {
+    type Output = EdgeWeight;
+
+    fn sub(self, rhs: EdgeWeight) -> Self::Output {
+        EdgeWeight {
+            wins: self.wins - rhs.wins,
+            losses: self.losses - rhs.losses,
+            draws: self.draws - rhs.draws,
+        }
+    }
+}
+
 impl AddAssign<EdgeWeight> for EdgeWeight {
     fn add_assign(&mut self, rhs: EdgeWeight) {
         self.wins += rhs.wins;
@@ -143,11 +185,259 @@ impl From<EdgeWeight> for (usize, usize, usize) {
     }
 }
 
+/// Selects which edges a game result is credited to.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::monte_carlo_graph::{MonteCarloGraph, UpdateMode};
+/// use games_rs::GameStatus;
+///
+/// let mut graph: MonteCarloGraph<u32> =
+///     MonteCarloGraph::new().with_update_mode(UpdateMode::PathOnly);
+/// graph.back_propogate(vec![0, 1, 3], GameStatus::Win(0));
+/// graph.back_propogate(vec![0, 2, 3], GameStatus::Win(0));
+///
+/// // Each game only counts along the path it was played on
+/// assert_eq!(graph.edge_weight(0, 1).unwrap().simulations(), 1);
+/// assert!(graph.validate());
+/// ```
+#[derive(..StdTraits, Serialize, Deserialize, Debug, Default)]
+pub enum UpdateMode {
+    /// Every edge into a node carries the node's full statistics, so a result is
+    /// credited along every path from the root to the positions it passed through.
+    #[default]
+    AllAncestors,
+    /// A result is credited only to the edges of the game that produced it. New edges
+    /// start empty.
+    PathOnly,
+}
+
+/// Order in which positions are evicted from a graph that exceeds its node budget.
+///
+/// Only leaves are evicted, so the remaining graph stays connected; a position whose
+/// children have all been evicted becomes a leaf and a candidate itself.
+#[derive(..StdTraits, Debug)]
+pub enum EvictionPolicy {
+    /// Least visited positions first
+    FewestVisits,
+    /// Positions furthest from the root first; positions unreachable from the root go
+    /// before all others
+    Deepest,
+    /// Positions least recently reached by a training game first. Updates are only
+    /// tracked while a node budget is set; untracked positions count as the oldest.
+    LeastRecentlyUpdated,
+}
+
+/// Determines which graph node a game state is stored under.
+pub trait NodeMapping<G: Game> {
+    /// Returns the node representing `state`.
+    fn node(state: &G) -> G;
+}
+
+/// Stores every position as its own node.
+#[derive(..Copy, Debug, Default)]
+pub struct Exact;
+
+impl<G: Game> NodeMapping<G> for Exact {
+    #[inline]
+    fn node(state: &G) -> G {
+        *state
+    }
+}
+
+/// Merges symmetric positions into a single node keyed by their canonical form.
+///
+/// Statistics gathered from any position are shared with all of its symmetric
+/// counterparts, which shrinks the graph by up to the size of the symmetry group.
+#[derive(..Copy, Debug, Default)]
+pub struct Canonical;
+
+impl<G: Symmetric> NodeMapping<G> for Canonical {
+    #[inline]
+    fn node(state: &G) -> G {
+        state.canonicalize().0
+    }
+}
+
+/// What a graph needs to know about its nodes besides telling them apart.
+///
+/// Edge statistics are kept from the perspective of the player moving along the edge, so
+/// the graph has to know where games start and when the player to move changes. The
+/// provided methods suit games that always start from their default position and whose
+/// players take turns one move each, such as Connect Four, and integer nodes standing
+/// for such positions.
+pub trait GraphPosition: Default {
+    /// Returns the node the graph is rooted at, which every game is replayed from.
+    fn root() -> Self {
+        Self::default()
+    }
+
+    /// Returns the player to move, or `None` if the players alternate after every move.
+    fn mover(&self) -> Option<u8> {
+        None
+    }
+}
+
+impl GraphPosition for ConnectFour {}
+
+impl GraphPosition for UltimateTTT {}
+
+/// Each deal is a random position, so graphs are rooted before the deal. A turn is a
+/// draw followed by a discard, both by the same player.
+impl GraphPosition for Rummy {
+    fn root() -> Self {
+        Rummy::undealt()
+    }
+
+    fn mover(&self) -> Option<u8> {
+        Some(self.get_current_player().into())
+    }
+}
+
+impl GraphPosition for GinRummyMatch {
+    fn root() -> Self {
+        GinRummyMatch::undealt()
+    }
+
+    fn mover(&self) -> Option<u8> {
+        Some(self.get_current_player().into())
+    }
+}
+
+impl GraphPosition for u32 {}
+
+impl GraphPosition for u64 {}
+
+/// Converts statistics between the perspectives of the players to move at `parent` and
+/// at its child `child`.
+#[inline]
+fn seen_from<N: GraphPosition>(parent: &N, child: &N, weight: EdgeWeight) -> EdgeWeight {
+    match (parent.mover(), child.mover()) {
+        (Some(a), Some(b)) if a == b => weight,
+        _ => weight.flip(),
+    }
+}
+
+/// Statistics of an edge present in both graphs compared by [`MonteCarloGraph::diff`].
+#[derive(..Copy, Debug, PartialEq)]
+pub struct EdgeChange<N> {
+    pub from: N,
+    pub to: N,
+    pub before: EdgeWeight,
+    pub after: EdgeWeight,
+}
+
+impl<N> EdgeChange<N> {
+    /// Returns the change in win rate, positive if the move got better.
+    pub fn win_rate_shift(&self) -> f64 {
+        self.after.win_rate() - self.before.win_rate()
+    }
+}
+
+/// Differences between two graphs, as reported by [`MonteCarloGraph::diff`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphDiff<N> {
+    /// Nodes only present in the newer graph
+    pub new_nodes: Vec<N>,
+    /// Nodes only present in the older graph
+    pub removed_nodes: Vec<N>,
+    /// Edges only present in the newer graph, with their statistics
+    pub new_edges: Vec<(N, N, EdgeWeight)>,
+    /// Edges present in both graphs whose statistics differ
+    pub changed_edges: Vec<EdgeChange<N>>,
+    /// Changed edges whose win rate moved by more than the requested threshold, largest
+    /// shift first
+    pub large_shifts: Vec<EdgeChange<N>>,
+}
+
+impl<N> GraphDiff<N> {
+    /// Returns `true` if the graphs have the same nodes, edges and statistics.
+    pub fn is_empty(&self) -> bool {
+        self.new_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.new_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+}
+
+/// Returns the outcome of a finished game along `path` from the perspective of the player
+/// who made the last move.
+///
+/// A win goes to the last mover unless the node it moved from names another player,
+/// as when a knock in Rummy is undercut.
+fn final_outcome<N: GraphPosition>(path: &[N], state: GameStatus) -> EdgeWeight {
+    let mover = path
+        .len()
+        .checked_sub(2)
+        .and_then(|last_move| path[last_move].mover());
+    match state {
+        GameStatus::Win(winner) if mover.is_some_and(|mover| mover != winner) => (0, 1, 0).into(),
+        GameStatus::Win(_) => (1, 0, 0).into(),
+        GameStatus::Draw => (0, 0, 1).into(),
+        _ => panic!("Invalid board status"),
+    }
+}
+
+/// Layout of graph files written before the format had a header (schema version 0).
+///
+/// Nodes are stored in their own frozen layout, [`GraphNode::Legacy`].
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "L: for<'a> Deserialize<'a> + std::hash::Hash + Ord + Copy"))]
+struct LegacyGraph<L>
+where
+    L: std::hash::Hash + Ord + Copy,
+{
+    graph: DiGraphMap<L, EdgeWeight>,
+    root: L,
+}
+
+impl<L> LegacyGraph<L>
+where
+    L: std::hash::Hash + Ord + Copy,
+{
+    /// Converts every node to the current layout, or returns `None` if the node type has
+    /// no legacy layout.
+    fn migrate<N, M>(self) -> Option<MonteCarloGraph<N, M>>
+    where
+        N: GraphNode<Legacy = L>
+            + std::hash::Hash
+            + Eq
+            + Clone
+            + Copy
+            + Ord
+            + Default
+            + std::fmt::Debug,
+    {
+        let mut graph = DiGraphMap::with_capacity(self.graph.node_count(), self.graph.edge_count());
+        for node in self.graph.nodes() {
+            graph.add_node(N::from_legacy(node)?);
+        }
+        for (from, to, weight) in self.graph.all_edges() {
+            graph.add_edge(N::from_legacy(from)?, N::from_legacy(to)?, *weight);
+        }
+        Some(MonteCarloGraph {
+            graph,
+            root: N::from_legacy(self.root)?,
+            mode: UpdateMode::AllAncestors,
+            absorbed: HashMap::new(),
+            entered: HashMap::new(),
+            samples: 0,
+            budget: None,
+            last_update: HashMap::new(),
+            clock: 0,
+            _mapping: PhantomData,
+        })
+    }
+}
+
 /// Monte Carlo tree/graph search structure for game state exploration.
 ///
 /// Tracks game states (nodes) and transitions (edges) with win/simulation statistics.
 /// Edge weights represent (wins, losses, draws) from the parent node's perspective.
 ///
+/// The mapping `M` selects how trained game states become nodes; see [`Exact`] and
+/// [`Canonical`].
+///
 /// # Examples
 /// ```
 /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
@@ -165,7 +455,7 @@ impl From<EdgeWeight> for (usize, usize, usize) {
     bound(serialize = "N: Serialize"),
     bound(deserialize = "N: for<'a> Deserialize<'a>")
 )]
-pub struct MonteCarloGraph<N>
+pub struct MonteCarloGraph<N, M = Exact>
 where
     N: std::hash::Hash + Eq + Clone + Copy + Ord + Default + std::fmt::Debug,
 {
@@ -173,6 +463,29 @@ where
     graph: DiGraphMap<N, EdgeWeight>,
     /// Root node representing the initial game state
     root: N,
+    /// How results are credited to edges
+    #[serde(default)]
+    mode: UpdateMode,
+    /// Statistics of evicted children, folded into their parents
+    #[serde(default)]
+    absorbed: HashMap<N, EdgeWeight>,
+    /// Statistics of pruned edges from unreachable parents, which still count as having
+    /// entered the node. Only kept with [`UpdateMode::PathOnly`].
+    #[serde(default)]
+    entered: HashMap<N, EdgeWeight>,
+    /// Number of games the graph was trained on
+    #[serde(default)]
+    samples: u64,
+    /// Maximum number of nodes and the order in which they are evicted
+    #[serde(skip)]
+    budget: Option<(usize, EvictionPolicy)>,
+    /// Training clock value at which each node was last on a game's path
+    #[serde(skip)]
+    last_update: HashMap<N, u64>,
+    #[serde(skip)]
+    clock: u64,
+    #[serde(skip)]
+    _mapping: PhantomData<M>,
 }
 
 impl<N> MonteCarloGraph<N>
@@ -180,7 +493,8 @@ where
     N: std::hash::Hash + Eq + Clone + Copy + Ord + Default + std::fmt::Debug + Serialize,
     for<'a> N: Deserialize<'a>,
 {
-    /// Creates a new graph with the default node as root.
+    /// Creates a new graph rooted at [`GraphPosition::root`], the default node for most
+    /// games.
     ///
     /// # Examples
     /// ```
@@ -189,17 +503,113 @@ where
     /// let graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
     /// assert!(graph.contains_node(&0));
     /// ```
-    pub fn new() -> Self {
+    pub fn new() -> Self
+    where
+        N: GraphPosition,
+    {
         let mut graph = DiGraphMap::new();
-        graph.add_node(N::default());
+        graph.add_node(N::root());
         MonteCarloGraph {
             graph,
-            root: N::default(),
+            root: N::root(),
+            mode: UpdateMode::default(),
+            absorbed: HashMap::new(),
+            entered: HashMap::new(),
+            samples: 0,
+            budget: None,
+            last_update: HashMap::new(),
+            clock: 0,
+            _mapping: PhantomData,
+        }
+    }
+
+    /// Converts the graph to merge symmetric positions during training and lookup.
+    ///
+    /// Use this on freshly created graphs. Graphs saved with symmetries merged are
+    /// loaded directly as `MonteCarloGraph<N, Canonical>` by
+    /// [`MonteCarloGraph::from_file`].
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::{Canonical, MonteCarloGraph};
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// let graph: MonteCarloGraph<ConnectFour, Canonical> =
+    ///     MonteCarloGraph::new().with_symmetries();
+    /// ```
+    pub fn with_symmetries(self) -> MonteCarloGraph<N, Canonical>
+    where
+        N: Symmetric,
+    {
+        MonteCarloGraph {
+            graph: self.graph,
+            root: self.root,
+            mode: self.mode,
+            absorbed: self.absorbed,
+            entered: self.entered,
+            samples: self.samples,
+            budget: self.budget,
+            last_update: self.last_update,
+            clock: self.clock,
+            _mapping: PhantomData,
         }
     }
+}
+
+impl<N, M> MonteCarloGraph<N, M>
+where
+    N: std::hash::Hash + Eq + Clone + Copy + Ord + Default + std::fmt::Debug + Serialize,
+    for<'a> N: Deserialize<'a>,
+{
+    /// Sets how results are credited to edges.
+    ///
+    /// Statistics gathered under one mode are not meaningful under the other, so the
+    /// mode should be chosen before training.
+    pub fn with_update_mode(mut self, mode: UpdateMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns how results are credited to edges.
+    pub fn update_mode(&self) -> UpdateMode {
+        self.mode
+    }
+
+    /// Limits the graph to `max_nodes` nodes.
+    ///
+    /// Whenever training or merging leaves the graph over budget, positions are evicted
+    /// in the order given by `policy` until a tenth of the budget is free again, so that
+    /// eviction does not run after every game. The statistics of evicted positions stay
+    /// in their parents' aggregates. The budget is not saved with the graph.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::{EvictionPolicy, MonteCarloGraph};
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> =
+    ///     MonteCarloGraph::new().with_node_budget(10, EvictionPolicy::FewestVisits);
+    /// for i in 1..20 {
+    ///     graph.back_propogate(vec![0, i], GameStatus::Win(0));
+    /// }
+    ///
+    /// assert!(graph.node_count() <= 10);
+    /// assert_eq!(graph.get_aggregate_outcomes(&0).simulations(), 19);
+    /// ```
+    pub fn with_node_budget(mut self, max_nodes: usize, policy: EvictionPolicy) -> Self {
+        self.budget = Some((max_nodes.max(1), policy));
+        self
+    }
+
+    /// Returns the node budget and eviction policy, if any.
+    pub fn node_budget(&self) -> Option<(usize, EvictionPolicy)> {
+        self.budget
+    }
 
     /// Aggregates outcomes from all outgoing edges.
     ///
+    /// Outcomes of children that were evicted or pruned are included.
+    ///
     /// # Examples
     /// ```
     /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
@@ -214,11 +624,75 @@ where
     /// ```
     #[inline]
     pub fn get_aggregate_outcomes(&self, node: &N) -> EdgeWeight {
+        let absorbed = self.absorbed.get(node).copied().unwrap_or_default();
         self.edges_from(node)
             .iter()
-            .fold((0usize, 0usize, 0usize).into(), |weight, edge| {
-                weight + edge.1
-            })
+            .fold(absorbed, |weight, edge| weight + edge.1)
+    }
+
+    /// Returns the number of games that passed through a node.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate(vec![0, 1, 2], GameStatus::Win(0));
+    /// graph.back_propogate(vec![0, 1, 3], GameStatus::Draw);
+    ///
+    /// assert_eq!(graph.visits(&0), 2);
+    /// assert_eq!(graph.visits(&1), 2);
+    /// assert_eq!(graph.visits(&3), 1);
+    /// ```
+    pub fn visits(&self, n: &N) -> usize {
+        let incoming = self
+            .graph
+            .edges_directed(*n, Direction::Incoming)
+            .map(|e| e.weight().simulations());
+        let incoming = match self.mode {
+            UpdateMode::AllAncestors => incoming.max().unwrap_or(0),
+            UpdateMode::PathOnly => {
+                incoming.sum::<usize>()
+                    + self.entered.get(n).map_or(0, |weight| weight.simulations())
+            }
+        };
+        incoming.max(self.get_aggregate_outcomes(n).simulations())
+    }
+
+    /// Returns the node representing the initial game state.
+    #[inline]
+    pub fn root(&self) -> N {
+        self.root
+    }
+
+    /// Returns the number of nodes in the graph.
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// Returns the number of edges in the graph.
+    #[inline]
+    pub fn edge_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    /// Estimates the heap memory used by the graph in bytes.
+    ///
+    /// Each node is stored with its adjacency list and an index entry, and each edge
+    /// appears in the edge map and in the adjacency lists of both endpoints.
+    pub fn memory_usage(&self) -> usize {
+        let node = std::mem::size_of::<N>();
+        let per_node =
+            node + std::mem::size_of::<Vec<(N, bool)>>() + 2 * std::mem::size_of::<usize>();
+        let per_edge = 2 * std::mem::size_of::<(N, bool)>()
+            + 2 * node
+            + std::mem::size_of::<EdgeWeight>()
+            + 2 * std::mem::size_of::<usize>();
+        let absorbed = (self.absorbed.capacity() + self.entered.capacity())
+            * (node + std::mem::size_of::<EdgeWeight>());
+        self.graph.node_count() * per_node + self.graph.edge_count() * per_edge + absorbed
     }
 
     /// Returns all nodes in the graph.
@@ -276,19 +750,217 @@ where
         self.graph.edge_weight(from, to)
     }
 
-    /// Propagates weight updates up the tree recursively.
-    fn propogate_edge(&mut self, n: &N, weight_update: EdgeWeight) {
-        for (src, _) in self.edges_to(n) {
-            if let Some(weight) = self.graph.edge_weight_mut(src, *n) {
-                *weight += weight_update.flip();
-                self.propogate_edge(&src, weight_update.flip());
+    /// Propagates increases of node aggregates to every ancestor.
+    ///
+    /// `deltas` maps nodes to the amount their aggregate outcomes grew by. Each incoming
+    /// edge of a node grows by the delta as seen by the parent's player to move, which
+    /// in turn grows the parent's aggregate. Nodes are visited in reverse topological
+    /// order of the ancestor set, so every edge is updated once with the sum of all
+    /// deltas reaching it, no matter how many paths lead there.
+    ///
+    /// Graphs of games that can repeat positions may contain cycles. Deltas cannot
+    /// settle on a cycle; each node on one is released once, smallest first, and
+    /// deltas that reach it afterwards are dropped.
+    fn propagate(&mut self, mut deltas: HashMap<N, EdgeWeight>)
+    where
+        N: GraphPosition,
+    {
+        let mut ancestors: HashSet<N> = deltas.keys().copied().collect();
+        let mut stack: Vec<N> = ancestors.iter().copied().collect();
+        while let Some(n) = stack.pop() {
+            for parent in self.graph.neighbors_directed(n, Direction::Incoming) {
+                if ancestors.insert(parent) {
+                    stack.push(parent);
+                }
+            }
+        }
+
+        // Number of children in the ancestor set that each node still waits on
+        let mut pending: HashMap<N, usize> = HashMap::new();
+        let mut ready = VecDeque::new();
+        for &n in &ancestors {
+            let children = self
+                .graph
+                .neighbors_directed(n, Direction::Outgoing)
+                .filter(|child| ancestors.contains(child))
+                .count();
+            if children == 0 {
+                ready.push_back(n);
+            } else {
+                pending.insert(n, children);
+            }
+        }
+
+        loop {
+            while let Some(n) = ready.pop_front() {
+                let delta = deltas.remove(&n).unwrap_or_default();
+                let parents: Vec<N> = self
+                    .graph
+                    .neighbors_directed(n, Direction::Incoming)
+                    .collect();
+
+                for parent in parents {
+                    if delta.simulations() > 0 {
+                        let delta = seen_from(&parent, &n, delta);
+                        *self.graph.edge_weight_mut(parent, n).unwrap() += delta;
+                        *deltas.entry(parent).or_default() += delta;
+                    }
+                    if let Some(count) = pending.get_mut(&parent) {
+                        *count -= 1;
+                        if *count == 0 {
+                            pending.remove(&parent);
+                            ready.push_back(parent);
+                        }
+                    }
+                }
+            }
+
+            // Only nodes on cycles are left
+            match pending.keys().min().copied() {
+                Some(n) => {
+                    pending.remove(&n);
+                    ready.push_back(n);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Adds a finished game to the graph without propagating to ancestors.
+    ///
+    /// Missing nodes and edges of `path` are created and the result is credited to the
+    /// edges into the final position that exist at this point. Increases of node
+    /// aggregates that still have to reach the ancestors are accumulated in `deltas`.
+    fn insert_game(&mut self, path: &[N], state: GameStatus, deltas: &mut HashMap<N, EdgeWeight>)
+    where
+        N: GraphPosition,
+    {
+        let outcome = final_outcome(path, state);
+
+        if self.budget.is_some() {
+            self.clock += 1;
+            for n in path {
+                self.last_update.insert(*n, self.clock);
+            }
+        }
+
+        for i in 1..path.len() {
+            let from = path[i - 1];
+            let to = path[i];
+
+            if !self.graph.contains_node(to) {
+                self.graph.add_node(to);
+            }
+
+            if !self.contains_edge(&from, &to) {
+                let weight = match self.mode {
+                    // Pending deltas of `to` will reach the new edge during propagation
+                    UpdateMode::AllAncestors => {
+                        let pending = deltas.get(&to).copied().unwrap_or_default();
+                        seen_from(&from, &to, self.get_aggregate_outcomes(&to) - pending)
+                    }
+                    UpdateMode::PathOnly => EdgeWeight::default(),
+                };
+                self.graph.add_edge(from, to, weight);
+                *deltas.entry(from).or_default() += weight;
+            }
+        }
+
+        let last = path[path.len() - 1];
+        match self.mode {
+            UpdateMode::AllAncestors => {
+                let parents: Vec<N> = self
+                    .graph
+                    .neighbors_directed(last, Direction::Incoming)
+                    .collect();
+                for parent in parents {
+                    *self.graph.edge_weight_mut(parent, last).unwrap() += outcome;
+                    *deltas.entry(parent).or_default() += outcome;
+                }
+            }
+            UpdateMode::PathOnly => {
+                let mut weight = outcome;
+                for i in (1..path.len()).rev() {
+                    *self.graph.edge_weight_mut(path[i - 1], path[i]).unwrap() += weight;
+                    if i > 1 {
+                        weight = seen_from(&path[i - 2], &path[i - 1], weight);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds the games a shard was trained on, as if they had been inserted here.
+    ///
+    /// `shard` is a graph with the same root and update mode that started out empty, and
+    /// `ends` holds the summed outcomes of its games by final position. Merging the
+    /// shards of consecutive games in order gives the same graph as inserting the games
+    /// themselves: edges that already lead to a final position are credited with every
+    /// game of the shard ending there, new edges into final positions keep the shard's
+    /// statistics, and new edges into other positions start from the position's
+    /// aggregate. Increases of node aggregates are accumulated in `deltas`, as by
+    /// `insert_game`.
+    fn insert_shard(
+        &mut self,
+        shard: &Self,
+        ends: &HashMap<N, EdgeWeight>,
+        deltas: &mut HashMap<N, EdgeWeight>,
+    ) where
+        N: GraphPosition,
+    {
+        self.samples += shard.samples;
+
+        if self.mode == UpdateMode::AllAncestors {
+            for (end, outcome) in ends {
+                if !self.graph.contains_node(*end) {
+                    continue;
+                }
+                let parents: Vec<N> = self
+                    .graph
+                    .neighbors_directed(*end, Direction::Incoming)
+                    .collect();
+                for parent in parents {
+                    *self.graph.edge_weight_mut(parent, *end).unwrap() += *outcome;
+                    *deltas.entry(parent).or_default() += *outcome;
+                }
             }
         }
+
+        for n in shard.graph.nodes() {
+            if !self.graph.contains_node(n) {
+                self.graph.add_node(n);
+            }
+        }
+
+        for (from, to, weight) in shard.graph.all_edges() {
+            if let Some(existing) = self.graph.edge_weight_mut(from, to) {
+                if self.mode == UpdateMode::PathOnly {
+                    *existing += *weight;
+                }
+                continue;
+            }
+
+            let leaf = shard
+                .graph
+                .neighbors_directed(to, Direction::Outgoing)
+                .next()
+                .is_none();
+            let weight = match self.mode {
+                UpdateMode::AllAncestors if !leaf => {
+                    let pending = deltas.get(&to).copied().unwrap_or_default();
+                    seen_from(&from, &to, self.get_aggregate_outcomes(&to) - pending)
+                }
+                _ => *weight,
+            };
+            self.graph.add_edge(from, to, weight);
+            *deltas.entry(from).or_default() += weight;
+        }
     }
 
     /// Updates the graph with simulation results from a game path.
     ///
-    /// Creates nodes/edges as needed and propagates outcome statistics upward.
+    /// Creates nodes/edges as needed and propagates outcome statistics upward, either to
+    /// all ancestors or along the path only, depending on the [`UpdateMode`].
     ///
     /// # Examples
     /// ```
@@ -302,36 +974,56 @@ where
     /// assert!(graph.contains_edge(&1, &2));
     /// assert!(graph.validate());
     /// ```
-    pub fn back_propogate(&mut self, path: Vec<N>, state: GameStatus) {
-        for i in (1..path.len()) {
-            let from = path[i - 1];
-            let to = path[i];
-
-            if !self.graph.contains_node(to) {
-                self.graph.add_node(to);
-            }
+    pub fn back_propogate(&mut self, path: Vec<N>, state: GameStatus)
+    where
+        N: GraphPosition,
+    {
+        self.back_propogate_batch(vec![(path, state)]);
+    }
 
-            if !self.contains_edge(&from, &to) {
-                let weight = self.get_aggregate_outcomes(&to);
-                self.graph.add_edge(from, to, weight.flip());
-                self.propogate_edge(&from, weight.flip());
-            }
+    /// Updates the graph with the results of several games at once.
+    ///
+    /// Games are inserted in order and their statistics are propagated to the ancestors
+    /// in a single pass, so shared ancestors are visited once per batch instead of once
+    /// per game. For acyclic graphs the result is identical to calling
+    /// [`MonteCarloGraph::back_propogate`] on each game in turn.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate_batch(vec![
+    ///     (vec![0, 1, 2], GameStatus::Win(0)),
+    ///     (vec![0, 3, 2], GameStatus::Win(0)),
+    /// ]);
+    ///
+    /// assert_eq!(graph.get_aggregate_outcomes(&0).simulations(), 3);
+    /// assert!(graph.validate());
+    /// ```
+    pub fn back_propogate_batch(&mut self, games: Vec<(Vec<N>, GameStatus)>)
+    where
+        N: GraphPosition,
+    {
+        self.samples += games.len() as u64;
+        let mut deltas = HashMap::new();
+        for (path, state) in games {
+            self.insert_game(&path, state, &mut deltas);
         }
 
-        if state != GameStatus::InProgress || self.contains_node(&path[path.len() - 1]) {
-            let weight: EdgeWeight = match state {
-                GameStatus::Win(_) => (1, 0, 0).into(),
-                GameStatus::Draw => (0, 0, 1).into(),
-                _ => panic!("Invalid board status"),
-            };
-
-            self.propogate_edge(&path[path.len() - 1], weight.flip());
+        if self.mode == UpdateMode::AllAncestors {
+            self.propagate(deltas);
         }
+        self.enforce_budget();
     }
 
     /// Validates graph integrity.
     ///
-    /// Checks that each non-leaf node's incoming edge weights match its aggregated outgoing edges.
+    /// With [`UpdateMode::AllAncestors`], checks that each non-leaf node's incoming edge
+    /// weights match its aggregated outgoing edges. With [`UpdateMode::PathOnly`], checks
+    /// that every game entering a non-leaf node also left it: the incoming edge weights
+    /// sum to the aggregated outgoing edges.
     ///
     /// # Examples
     /// ```
@@ -342,65 +1034,727 @@ where
     /// graph.back_propogate(vec![0, 1, 2], GameStatus::Win(0));
     /// assert!(graph.validate());
     /// ```
-    pub fn validate(&self) -> bool {
-        let mut valid = true;
+    pub fn validate(&self) -> bool
+    where
+        N: GraphPosition,
+    {
         self.graph
             .nodes()
             .filter(|n| self.edges_from(n).iter().count() > 0)
-            .for_each(|n| {
-                let exp_weight = self.get_aggregate_outcomes(&n).flip();
-                valid &= self
-                    .edges_to(&n)
-                    .iter()
-                    .all(|(_, weight)| *weight == exp_weight)
-            });
-        valid
+            .all(|n| {
+                let aggregate = self.get_aggregate_outcomes(&n);
+                let incoming = self.edges_to(&n);
+                match self.mode {
+                    UpdateMode::AllAncestors => incoming
+                        .iter()
+                        .all(|(parent, weight)| *weight == seen_from(parent, &n, aggregate)),
+                    UpdateMode::PathOnly => {
+                        let entered = self.entered.get(&n).copied();
+                        (incoming.is_empty() && entered.is_none())
+                            || incoming.iter().fold(
+                                entered.unwrap_or_default().flip(),
+                                |sum, (parent, weight)| sum + seen_from(parent, &n, *weight),
+                            ) == aggregate
+                    }
+                }
+            })
     }
 
-    /// Serializes the graph to a file using bitcode.
-    ///
-    /// # Examples
-    /// ```no_run
-    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// Recomputes every edge into a non-leaf node from the node's aggregate outcomes.
     ///
-    /// let graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
-    /// graph.to_file("graph.bin").unwrap();
-    /// ```
-    pub fn to_file(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let serialized = bitcode::serialize(self)?;
-        std::fs::write(path, serialized)?;
-        Ok(())
+    /// Edges into leaves keep their statistics; everything above them is rebuilt in
+    /// reverse topological order. Edges on cycles are left unchanged.
+    fn recompute_from_leaves(&mut self)
+    where
+        N: GraphPosition,
+    {
+        let mut pending: HashMap<N, usize> = HashMap::new();
+        let mut ready = VecDeque::new();
+        for n in self.graph.nodes() {
+            let children = self
+                .graph
+                .neighbors_directed(n, Direction::Outgoing)
+                .count();
+            if children == 0 {
+                ready.push_back(n);
+            } else {
+                pending.insert(n, children);
+            }
+        }
+
+        while let Some(n) = ready.pop_front() {
+            let leaf = self
+                .graph
+                .neighbors_directed(n, Direction::Outgoing)
+                .next()
+                .is_none();
+            let aggregate = self.get_aggregate_outcomes(&n);
+            let parents: Vec<N> = self
+                .graph
+                .neighbors_directed(n, Direction::Incoming)
+                .collect();
+
+            for parent in parents {
+                if !leaf {
+                    *self.graph.edge_weight_mut(parent, n).unwrap() =
+                        seen_from(&parent, &n, aggregate);
+                }
+                if let Some(count) = pending.get_mut(&parent) {
+                    *count -= 1;
+                    if *count == 0 {
+                        pending.remove(&parent);
+                        ready.push_back(parent);
+                    }
+                }
+            }
+        }
     }
 
-    /// Deserializes the graph from a bitcode file.
+    /// Merges the statistics of another graph into this one.
+    ///
+    /// Nodes and edges are unioned and edge weights summed. With
+    /// [`UpdateMode::AllAncestors`], edges into non-leaf nodes are then recomputed from
+    /// the merged leaf statistics, so results that reach a position through the other
+    /// graph's paths also count along this graph's paths and [`MonteCarloGraph::validate`]
+    /// still holds.
+    ///
+    /// # Errors
+    /// Returns an error if the graphs have different roots or update modes.
     ///
     /// # Examples
-    /// ```no_run
+    /// ```
     /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
     ///
-    /// let graph: MonteCarloGraph<u32> = MonteCarloGraph::from_file("graph.bin").unwrap();
+    /// let mut a: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// a.back_propogate(vec![0, 1, 2, 3], GameStatus::Win(0));
+    /// let mut b: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// b.back_propogate(vec![0, 4, 2, 5], GameStatus::Draw);
+    ///
+    /// a.merge(&b).unwrap();
+    /// assert_eq!(a.edge_weight(0, 1).unwrap().simulations(), 2);
+    /// assert_eq!(a.edge_weight(0, 4).unwrap().simulations(), 2);
+    /// assert!(a.validate());
     /// ```
-    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let data = std::fs::read(path)?;
-        let deserialized: Self = bitcode::deserialize(&data)?;
-        Ok(deserialized)
-    }
-}
+    pub fn merge(&mut self, other: &MonteCarloGraph<N, M>) -> Result<(), String>
+    where
+        N: GraphPosition,
+    {
+        if self.root != other.root {
+            return Err(format!(
+                "Cannot merge graphs with different roots: {:?} and {:?}",
+                self.root, other.root
+            ));
+        }
+        if self.mode != other.mode {
+            return Err(format!(
+                "Cannot merge graphs with different update modes: {:?} and {:?}",
+                self.mode, other.mode
+            ));
+        }
 
-impl<G: Game> TrainableComponent<G> for MonteCarloGraph<G> {
-    const name: &'static str = "MonteCarloGraph";
+        for n in other.graph.nodes() {
+            if !self.graph.contains_node(n) {
+                self.graph.add_node(n);
+            }
+        }
 
-    fn train(&mut self, sample: &PlayThrough<G>, _verbose: bool) -> () {
-        let mut path = Vec::new();
-        path.push(self.root);
-        let mut game = G::default();
+        for (from, to, weight) in other.graph.all_edges() {
+            match self.graph.edge_weight_mut(from, to) {
+                Some(existing) => *existing += *weight,
+                None => {
+                    self.graph.add_edge(from, to, *weight);
+                }
+            }
+        }
+
+        for (n, weight) in &other.absorbed {
+            *self.absorbed.entry(*n).or_default() += *weight;
+        }
+        for (n, weight) in &other.entered {
+            *self.entered.entry(*n).or_default() += *weight;
+        }
+        self.samples += other.samples;
+
+        if self.mode == UpdateMode::AllAncestors {
+            self.recompute_from_leaves();
+        }
+        self.enforce_budget();
+
+        Ok(())
+    }
+
+    /// Compares this graph with a newer version of it.
+    ///
+    /// Reports nodes and edges that appeared or disappeared, edges whose statistics
+    /// changed, and the changed edges whose win rate moved by more than
+    /// `shift_threshold`. Lists are sorted so reports are reproducible.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut old: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// old.back_propogate(vec![0, 1], GameStatus::Win(0));
+    /// let mut new = old.clone();
+    /// new.back_propogate(vec![0, 1, 2], GameStatus::Win(0));
+    ///
+    /// let diff = old.diff(&new, 0.25);
+    /// assert_eq!(diff.new_nodes, vec![2]);
+    /// assert_eq!(diff.changed_edges.len(), 1);
+    /// assert_eq!(diff.large_shifts[0].win_rate_shift(), -0.5);
+    /// ```
+    pub fn diff(&self, other: &MonteCarloGraph<N, M>, shift_threshold: f64) -> GraphDiff<N> {
+        let mut new_nodes: Vec<N> = other
+            .graph
+            .nodes()
+            .filter(|n| !self.graph.contains_node(*n))
+            .collect();
+        let mut removed_nodes: Vec<N> = self
+            .graph
+            .nodes()
+            .filter(|n| !other.graph.contains_node(*n))
+            .collect();
+        new_nodes.sort();
+        removed_nodes.sort();
+
+        let mut new_edges = Vec::new();
+        let mut changed_edges = Vec::new();
+        for (from, to, after) in other.graph.all_edges() {
+            match self.graph.edge_weight(from, to) {
+                None => new_edges.push((from, to, *after)),
+                Some(before) if before != after => changed_edges.push(EdgeChange {
+                    from,
+                    to,
+                    before: *before,
+                    after: *after,
+                }),
+                Some(_) => {}
+            }
+        }
+        new_edges.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        changed_edges.sort_by(|a, b| (a.from, a.to).cmp(&(b.from, b.to)));
+
+        let mut large_shifts: Vec<EdgeChange<N>> = changed_edges
+            .iter()
+            .filter(|change| change.win_rate_shift().abs() > shift_threshold)
+            .copied()
+            .collect();
+        large_shifts.sort_by(|a, b| {
+            b.win_rate_shift()
+                .abs()
+                .total_cmp(&a.win_rate_shift().abs())
+        });
+
+        GraphDiff {
+            new_nodes,
+            removed_nodes,
+            new_edges,
+            changed_edges,
+            large_shifts,
+        }
+    }
+
+    /// Returns the distance of every node reachable from the root.
+    fn depths(&self) -> HashMap<N, u64> {
+        let mut depths = HashMap::from([(self.root, 0)]);
+        let mut queue = VecDeque::from([self.root]);
+        while let Some(n) = queue.pop_front() {
+            let depth = depths[&n];
+            for child in self.graph.neighbors_directed(n, Direction::Outgoing) {
+                depths.entry(child).or_insert_with(|| {
+                    queue.push_back(child);
+                    depth + 1
+                });
+            }
+        }
+        depths
+    }
+
+    /// Evicts leaves in increasing order of `key` until at most `target` nodes remain.
+    ///
+    /// Only leaves whose key is below `limit` are evicted. The weights of the edges into
+    /// an evicted leaf are absorbed by the parents, so aggregates, and with them the
+    /// statistics of every remaining edge, are unchanged. Returns the number of nodes
+    /// evicted.
+    fn evict_leaves(&mut self, key: impl Fn(&Self, &N) -> u64, limit: u64, target: usize) -> usize {
+        let is_candidate = |graph: &Self, n: N| {
+            n != graph.root
+                && graph
+                    .graph
+                    .neighbors_directed(n, Direction::Outgoing)
+                    .next()
+                    .is_none()
+        };
+
+        let mut heap = BinaryHeap::new();
+        for n in self.graph.nodes() {
+            if is_candidate(self, n) {
+                let k = key(self, &n);
+                if k < limit {
+                    heap.push(Reverse((k, n)));
+                }
+            }
+        }
+
+        let mut evicted = 0;
+        while self.graph.node_count() > target {
+            let Some(Reverse((_, n))) = heap.pop() else {
+                break;
+            };
+
+            let parents = self.edges_to(&n);
+            self.graph.remove_node(n);
+            self.absorbed.remove(&n);
+            self.entered.remove(&n);
+            self.last_update.remove(&n);
+            evicted += 1;
+
+            for (parent, weight) in parents {
+                *self.absorbed.entry(parent).or_default() += weight;
+                if is_candidate(self, parent) {
+                    let k = key(self, &parent);
+                    if k < limit {
+                        heap.push(Reverse((k, parent)));
+                    }
+                }
+            }
+        }
+        evicted
+    }
+
+    /// Evicts positions in the order given by `policy` until at most `max_nodes` remain.
+    ///
+    /// Returns the number of nodes evicted. Fewer nodes than requested are evicted if
+    /// only the root and positions on cycles are left.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::{EvictionPolicy, MonteCarloGraph};
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate(vec![0, 1, 2, 3], GameStatus::Win(0));
+    /// graph.back_propogate(vec![0, 4], GameStatus::Draw);
+    ///
+    /// assert_eq!(graph.evict(3, EvictionPolicy::Deepest), 2);
+    /// assert_eq!(graph.nodes(), vec![0, 1, 4]);
+    /// assert!(graph.validate());
+    /// ```
+    pub fn evict(&mut self, max_nodes: usize, policy: EvictionPolicy) -> usize {
+        if self.graph.node_count() <= max_nodes {
+            return 0;
+        }
+
+        match policy {
+            EvictionPolicy::FewestVisits => {
+                self.evict_leaves(|graph, n| graph.visits(n) as u64, u64::MAX, max_nodes)
+            }
+            EvictionPolicy::Deepest => {
+                let depths = self.depths();
+                self.evict_leaves(
+                    |_, n| depths.get(n).map_or(0, |depth| u64::MAX - 1 - depth),
+                    u64::MAX,
+                    max_nodes,
+                )
+            }
+            EvictionPolicy::LeastRecentlyUpdated => {
+                let last_update = std::mem::take(&mut self.last_update);
+                let evicted = self.evict_leaves(
+                    |_, n| last_update.get(n).copied().unwrap_or(0),
+                    u64::MAX,
+                    max_nodes,
+                );
+                self.last_update = last_update;
+                self.last_update.retain(|n, _| self.graph.contains_node(*n));
+                evicted
+            }
+        }
+    }
+
+    /// Returns an empty graph with the same root and update mode.
+    fn empty_shard(&self) -> Self {
+        let mut graph = DiGraphMap::new();
+        graph.add_node(self.root);
+        MonteCarloGraph {
+            graph,
+            root: self.root,
+            mode: self.mode,
+            absorbed: HashMap::new(),
+            entered: HashMap::new(),
+            samples: 0,
+            budget: None,
+            last_update: HashMap::new(),
+            clock: 0,
+            _mapping: PhantomData,
+        }
+    }
+
+    /// Evicts down to 90% of the node budget once the graph exceeds it.
+    fn enforce_budget(&mut self) {
+        if let Some((max_nodes, policy)) = self.budget {
+            if self.graph.node_count() > max_nodes {
+                self.evict(max_nodes - max_nodes / 10, policy);
+            }
+        }
+    }
+
+    /// Removes positions visited fewer than `min_visits` times, leaves first.
+    ///
+    /// Positions with enough visits are kept along with everything above them. Returns
+    /// the number of nodes removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate(vec![0, 1, 2], GameStatus::Win(0));
+    /// graph.back_propogate(vec![0, 1, 3], GameStatus::Draw);
+    ///
+    /// assert_eq!(graph.prune_min_visits(2), 2);
+    /// assert_eq!(graph.visits(&1), 2);
+    /// assert!(graph.validate());
+    /// ```
+    pub fn prune_min_visits(&mut self, min_visits: usize) -> usize {
+        self.evict_leaves(|graph, n| graph.visits(n) as u64, min_visits as u64, 0)
+    }
+
+    /// Removes positions more than `max_depth` moves away from the root, as well as
+    /// positions not reachable from it. Returns the number of nodes removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate(vec![0, 1, 2, 3], GameStatus::Win(0));
+    ///
+    /// assert_eq!(graph.prune_depth(1), 2);
+    /// assert_eq!(graph.get_aggregate_outcomes(&1).simulations(), 1);
+    /// ```
+    pub fn prune_depth(&mut self, max_depth: usize) -> usize {
+        let depths = self.depths();
+        let limit = u64::MAX - 1 - max_depth as u64;
+        self.evict_leaves(
+            |_, n| depths.get(n).map_or(0, |depth| u64::MAX - 1 - depth),
+            limit,
+            0,
+        )
+    }
+
+    /// Removes every node that cannot be reached from the root, together with its edges.
+    ///
+    /// Unlike eviction, the statistics of the removed nodes are dropped rather than
+    /// absorbed by their parents, since they never contributed to positions reachable
+    /// from the root. Games that went on from a removed node into a remaining one still
+    /// entered it: with [`UpdateMode::PathOnly`] the weights of those edges are kept by
+    /// their targets, so visits and [`MonteCarloGraph::validate`] are unchanged. Returns
+    /// the number of nodes removed.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::GameStatus;
+    ///
+    /// let mut graph: MonteCarloGraph<u32> = MonteCarloGraph::new();
+    /// graph.back_propogate(vec![0, 1, 2], GameStatus::Win(0));
+    /// graph.back_propogate(vec![5, 6, 2], GameStatus::Draw);
+    ///
+    /// assert_eq!(graph.prune_unreachable(), 2);
+    /// assert_eq!(graph.nodes(), vec![0, 1, 2]);
+    /// ```
+    pub fn prune_unreachable(&mut self) -> usize
+    where
+        N: GraphPosition,
+    {
+        let depths = self.depths();
+        let unreachable: Vec<N> = self
+            .graph
+            .nodes()
+            .filter(|n| !depths.contains_key(n))
+            .collect();
+
+        if self.mode == UpdateMode::PathOnly {
+            for n in &unreachable {
+                for (child, weight) in self.edges_from(n) {
+                    if depths.contains_key(&child) {
+                        // Kept as if the pruned parent had the opponent of `child` to move
+                        *self.entered.entry(child).or_default() +=
+                            seen_from(n, &child, weight).flip();
+                    }
+                }
+            }
+        }
+
+        for n in &unreachable {
+            self.graph.remove_node(*n);
+            self.absorbed.remove(n);
+            self.entered.remove(n);
+            self.last_update.remove(n);
+        }
+        unreachable.len()
+    }
+
+    /// Returns the number of games the graph was trained on.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Loads a graph written by [`MonteCarloGraph::to_file`].
+    ///
+    /// The graph must be loaded with the node mapping it was trained with; graphs trained
+    /// with symmetries merged are loaded as `MonteCarloGraph<N, Canonical>`.
+    ///
+    /// Files written before the format had a header are migrated on load, decoding each
+    /// node with its frozen [`GraphNode::Legacy`] layout. They carry no game name, so the
+    /// caller is trusted to load them as the right game.
+    ///
+    /// # Errors
+    /// Returns a [`GraphFileError`] if the file cannot be read, is corrupted, comes from
+    /// an unsupported version, was trained on a different game or node mapping, or has
+    /// no header and holds a game that had no graph files then.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// let graph = MonteCarloGraph::<ConnectFour>::from_file("graph.bin").unwrap();
+    /// ```
+    pub fn from_file(path: &str) -> Result<Self, GraphFileError>
+    where
+        N: GraphNode,
+        M: GraphMapping,
+    {
+        let (header, payload) = graph_file::read(path, N::NAME, Layout::Graph, M::MAPPING)?;
+
+        let decode_error = |err: bitcode::Error| GraphFileError::Decode(err.to_string());
+        match header.version {
+            0 => {
+                let legacy: LegacyGraph<N::Legacy> =
+                    bitcode::deserialize(&payload).map_err(decode_error)?;
+                legacy
+                    .migrate()
+                    .ok_or_else(|| GraphFileError::NoLegacyLayout {
+                        game: N::NAME.to_string(),
+                    })
+            }
+            // Headers from before graph_file::OLDEST_VERSION are rejected on reading
+            _ => bitcode::deserialize(&payload).map_err(decode_error),
+        }
+    }
+
+    /// Writes the graph to a compressed file; see [`graph_file`] for the format.
+    ///
+    /// The header records the node mapping, which [`MonteCarloGraph::from_file`] checks.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// let graph = MonteCarloGraph::<ConnectFour>::new();
+    /// graph.to_file("graph.bin").unwrap();
+    /// ```
+    pub fn to_file(&self, path: &str) -> Result<(), GraphFileError>
+    where
+        N: GraphNode,
+        M: GraphMapping,
+    {
+        self.to_file_with_compression(path, Compression::default())
+    }
+
+    /// Writes the graph to a file, storing the payload as requested.
+    pub fn to_file_with_compression(
+        &self,
+        path: &str,
+        compression: Compression,
+    ) -> Result<(), GraphFileError>
+    where
+        N: GraphNode,
+        M: GraphMapping,
+    {
+        let payload =
+            bitcode::serialize(self).map_err(|err| GraphFileError::Encode(err.to_string()))?;
+        graph_file::write(
+            path,
+            N::NAME,
+            self.samples,
+            Layout::Graph,
+            M::MAPPING,
+            &payload,
+            compression,
+        )
+    }
+}
+
+impl<G: Game + GraphPosition, M: NodeMapping<G>> MonteCarloGraph<G, M> {
+    /// Replays a sample from the root, returning the visited nodes and the result.
+    ///
+    /// Samples starting elsewhere than the root, such as Rummy deals, are entered through
+    /// an edge from the root to their start.
+    fn replay(&self, sample: &PlayThrough<G>) -> (Vec<G>, GameStatus) {
+        let mut path = Vec::with_capacity(sample.moves.len() + 2);
+        path.push(self.root);
+        let mut game = *sample.get_start();
+        if M::node(&game) != self.root {
+            path.push(M::node(&game));
+        }
 
         for (player, mv) in &sample.moves {
             game.play(*mv, *player).unwrap();
-            path.push(game.clone());
+            path.push(M::node(&game));
         }
 
-        self.back_propogate(path, *sample.get_result());
+        (path, *sample.get_result())
+    }
+
+    /// Trains on a batch of samples on all available cores.
+    ///
+    /// The batch is split into one run of consecutive samples per thread. Each thread
+    /// replays its samples and trains them into a graph of its own, and the shard graphs
+    /// are then merged into this one in sample order, so the result is deterministic
+    /// and, for acyclic graphs, identical to training on each sample sequentially.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::agents::monte_carlo_graph::MonteCarloGraph;
+    /// use games_rs::agents::train::play_batch_parallel;
+    /// use games_rs::agents::RandomAgent;
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// let samples = play_batch_parallel::<ConnectFour, _, _>(
+    ///     || Box::new(RandomAgent::new()),
+    ///     || Box::new(RandomAgent::new()),
+    ///     100,
+    ///     None,
+    /// );
+    ///
+    /// let mut graph = MonteCarloGraph::<ConnectFour>::new();
+    /// graph.train_batch_parallel(&samples, None);
+    /// assert!(graph.validate());
+    /// ```
+    pub fn train_batch_parallel(
+        &mut self,
+        samples_batch: &[PlayThrough<G>],
+        mpb: Option<&MultiProgress>,
+    ) where
+        G: Send + Sync,
+        M: Sync + Send,
+    {
+        let pb = mpb.map(|mpb| {
+            let pb = mpb
+                .add(indicatif::ProgressBar::new(samples_batch.len() as u64))
+                .with_style(defaults::PB_STYLE.clone())
+                .with_prefix(format!(
+                    "{}/{}",
+                    G::name,
+                    <Self as TrainableComponent<G>>::name
+                ));
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb
+        });
+
+        let chunk_size = samples_batch
+            .len()
+            .div_ceil(rayon::current_num_threads())
+            .max(1);
+        let track_updates = self.budget.is_some();
+        let shards = samples_batch
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                let mut shard = self.empty_shard();
+                let mut ends: HashMap<G, EdgeWeight> = HashMap::new();
+                // Index within the chunk, counted from 1, of the last game through each node
+                let mut last_update = HashMap::new();
+
+                let games = chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sample)| {
+                        if let Some(pb) = &pb {
+                            pb.inc(1);
+                        }
+                        let (path, result) = self.replay(sample);
+                        *ends.entry(path[path.len() - 1]).or_default() +=
+                            final_outcome(&path, result);
+                        if track_updates {
+                            for n in &path {
+                                last_update.insert(*n, i as u64 + 1);
+                            }
+                        }
+                        (path, result)
+                    })
+                    .collect::<Vec<_>>();
+                shard.back_propogate_batch(games);
+                (shard, ends, last_update)
+            })
+            .collect::<Vec<_>>();
+
+        let mut deltas = HashMap::new();
+        for (i, (shard, ends, last_update)) in shards.iter().enumerate() {
+            self.insert_shard(shard, ends, &mut deltas);
+            let offset = self.clock + (i * chunk_size) as u64;
+            for (n, update) in last_update {
+                self.last_update.insert(*n, offset + update);
+            }
+        }
+        if track_updates {
+            self.clock += samples_batch.len() as u64;
+        }
+
+        if self.mode == UpdateMode::AllAncestors {
+            self.propagate(deltas);
+        }
+        self.enforce_budget();
+
+        if let Some(pb) = &pb {
+            pb.finish();
+        }
+    }
+}
+
+impl<G: Game + GraphPosition, M: NodeMapping<G>> TrainableComponent<G> for MonteCarloGraph<G, M> {
+    const name: &'static str = "MonteCarloGraph";
+
+    fn train(&mut self, sample: &PlayThrough<G>, _verbose: bool) -> () {
+        let (path, result) = self.replay(sample);
+        self.back_propogate(path, result);
+    }
+
+    /// Trains on all samples with a single propagation pass.
+    fn train_batch(
+        &mut self,
+        samples_batch: &Vec<PlayThrough<G>>,
+        mpb: Option<&MultiProgress>,
+    ) -> () {
+        let pb = mpb.map(|mpb| {
+            let pb = mpb
+                .add(indicatif::ProgressBar::new(samples_batch.len() as u64))
+                .with_style(defaults::PB_STYLE.clone())
+                .with_prefix(format!("{}/{}", G::name, Self::name));
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb
+        });
+
+        let games = samples_batch
+            .iter()
+            .map(|sample| {
+                if let Some(pb) = &pb {
+                    pb.inc(1);
+                }
+                self.replay(sample)
+            })
+            .collect::<Vec<_>>();
+        self.back_propogate_batch(games);
+
+        if let Some(pb) = &pb {
+            pb.finish();
+        }
     }
 }
 
@@ -427,7 +1781,7 @@ mod test {
         let path = "test_mcg.bin";
         mcg.to_file(path).unwrap();
 
-        let loaded_mcg = MonteCarloGraph::from_file(path).unwrap();
+        let loaded_mcg = MonteCarloGraph::<u32>::from_file(path).unwrap();
 
         assert!(loaded_mcg.contains_node(&0));
         assert!(loaded_mcg.contains_node(&1));
@@ -487,4 +1841,478 @@ mod test {
 
         assert!(mcg.validate());
     }
+
+    #[test]
+    fn test_train_merges_symmetric_positions() {
+        use super::MonteCarloGraph;
+        use crate::agents::train::TrainableComponent;
+        use crate::connect_four::{ConnectFour, Token};
+        use crate::{GameStatus, PlayThrough};
+
+        let game = |moves: &[usize]| -> PlayThrough<ConnectFour> {
+            let players = [Token::Red, Token::Yellow];
+            let moves = moves
+                .iter()
+                .enumerate()
+                .map(|(i, mv)| (players[i % 2], *mv))
+                .collect();
+            PlayThrough::new(GameStatus::Win(1), moves)
+        };
+        let samples = vec![game(&[0, 1, 0, 1, 0, 1, 0]), game(&[6, 5, 6, 5, 6, 5, 6])];
+
+        let mut exact = MonteCarloGraph::<ConnectFour>::new();
+        let mut canonical = MonteCarloGraph::<ConnectFour>::new().with_symmetries();
+        for sample in &samples {
+            exact.train(sample, false);
+            canonical.train(sample, false);
+        }
+
+        assert_eq!(exact.nodes().len(), 15);
+        assert_eq!(canonical.nodes().len(), 8);
+        assert_eq!(
+            canonical
+                .get_aggregate_outcomes(&ConnectFour::new())
+                .simulations(),
+            2
+        );
+        assert!(canonical.validate());
+    }
+
+    #[test]
+    fn test_back_propogate_deep_and_cyclic() {
+        use super::{MonteCarloGraph, UpdateMode};
+        use crate::GameStatus;
+
+        // Long paths must not overflow the stack
+        let path = (0..200_000u32).collect::<Vec<_>>();
+        for mode in [UpdateMode::AllAncestors, UpdateMode::PathOnly] {
+            let mut mcg: MonteCarloGraph<u32> = MonteCarloGraph::new().with_update_mode(mode);
+            mcg.back_propogate(path.clone(), GameStatus::Draw);
+            assert_eq!(mcg.edge_weight(0, 1), Some(&(0, 0, 1).into()));
+            assert!(mcg.validate());
+        }
+
+        // Paths that revisit a position terminate
+        let mut mcg: MonteCarloGraph<u32> = MonteCarloGraph::new();
+        mcg.back_propogate(vec![0, 1, 2, 1, 3], GameStatus::Win(0));
+        mcg.back_propogate(vec![0, 1, 3], GameStatus::Win(0));
+        assert!(mcg.edge_weight(0, 1).unwrap().simulations() > 0);
+    }
+
+    #[test]
+    fn test_update_modes_on_transpositions() {
+        use super::{MonteCarloGraph, UpdateMode};
+        use crate::agents::train::TrainableComponent;
+        use crate::agents::{Agent, RandomAgent};
+        use crate::connect_four::ConnectFour;
+        use crate::{GameStatus, play_game};
+
+        let mut all = MonteCarloGraph::<ConnectFour>::new();
+        let mut path_only =
+            MonteCarloGraph::<ConnectFour>::new().with_update_mode(UpdateMode::PathOnly);
+        let agent = RandomAgent::<ConnectFour>::new();
+        let samples = (0..200)
+            .map(|_| play_game(&agent as &dyn Agent<_>, &agent as &dyn Agent<_>))
+            .collect::<Vec<_>>();
+        for sample in &samples {
+            all.train(sample, false);
+            path_only.train(sample, false);
+        }
+
+        assert!(all.validate());
+        assert!(path_only.validate());
+
+        // Each game passes through the root exactly once
+        let root = ConnectFour::new();
+        assert_eq!(path_only.get_aggregate_outcomes(&root).simulations(), 200);
+        assert!(all.get_aggregate_outcomes(&root).simulations() >= 200);
+
+        // Diamond: both paths reach node 3, whose statistics flow back along both
+        let mut mcg: MonteCarloGraph<u32> = MonteCarloGraph::new();
+        mcg.back_propogate(vec![0, 1, 3, 4], GameStatus::Win(0));
+        mcg.back_propogate(vec![0, 2, 3, 5], GameStatus::Win(0));
+        assert_eq!(mcg.edge_weight(1, 3), Some(&(0, 2, 0).into()));
+        assert_eq!(mcg.edge_weight(2, 3), Some(&(0, 2, 0).into()));
+        assert_eq!(mcg.edge_weight(0, 1), Some(&(2, 0, 0).into()));
+        assert_eq!(mcg.get_aggregate_outcomes(&0).simulations(), 4);
+        assert!(mcg.validate());
+    }
+
+    #[test]
+    fn test_parallel_training_matches_sequential() {
+        use super::{MonteCarloGraph, NodeMapping, UpdateMode};
+        use crate::agents::RandomAgent;
+        use crate::agents::train::{TrainableComponent, play_batch_parallel};
+        use crate::connect_four::ConnectFour;
+
+        fn assert_same<M>(a: &MonteCarloGraph<ConnectFour, M>, b: &MonteCarloGraph<ConnectFour, M>)
+        where
+            M: NodeMapping<ConnectFour>,
+        {
+            let mut nodes = a.nodes();
+            nodes.sort();
+            let mut other = b.nodes();
+            other.sort();
+            assert_eq!(nodes, other);
+            for node in &nodes {
+                let mut edges = a.edges_from(node);
+                edges.sort();
+                let mut other = b.edges_from(node);
+                other.sort();
+                assert_eq!(edges, other);
+            }
+        }
+
+        let samples = play_batch_parallel::<ConnectFour, _, _>(
+            || Box::new(RandomAgent::new()),
+            || Box::new(RandomAgent::new()),
+            300,
+            None,
+        );
+
+        // Shard the batches even on machines with a single core
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        for mode in [UpdateMode::AllAncestors, UpdateMode::PathOnly] {
+            let mut sequential = MonteCarloGraph::<ConnectFour>::new().with_update_mode(mode);
+            for sample in &samples {
+                sequential.train(sample, false);
+            }
+
+            let mut parallel = MonteCarloGraph::<ConnectFour>::new().with_update_mode(mode);
+            pool.install(|| {
+                parallel.train_batch_parallel(&samples[..150], None);
+                parallel.train_batch_parallel(&samples[150..], None);
+            });
+            assert_same(&sequential, &parallel);
+            assert_eq!(parallel.samples(), sequential.samples());
+            assert!(parallel.validate());
+
+            let mut sequential = MonteCarloGraph::<ConnectFour>::new()
+                .with_symmetries()
+                .with_update_mode(mode);
+            let mut batched = sequential.clone();
+            let mut parallel = sequential.clone();
+            for sample in &samples {
+                sequential.train(sample, false);
+            }
+            batched.train_batch(&samples, None);
+            assert_same(&sequential, &batched);
+            pool.install(|| parallel.train_batch_parallel(&samples, None));
+            assert_same(&sequential, &parallel);
+        }
+    }
+
+    #[test]
+    fn test_trains_on_random_deals() {
+        use super::{GraphPosition, MonteCarloGraph, UpdateMode};
+        use crate::GameStatus;
+        use crate::agents::RandomAgent;
+        use crate::agents::train::{TrainableComponent, play_batch_parallel};
+        use crate::rummy::{GinRummyMatch, Rummy};
+
+        let samples = play_batch_parallel::<Rummy, _, _>(
+            || Box::new(RandomAgent::new()),
+            || Box::new(RandomAgent::new()),
+            100,
+            None,
+        );
+        assert!(
+            samples
+                .iter()
+                .any(|sample| matches!(sample.get_result(), GameStatus::Win(_)))
+        );
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        for mode in [UpdateMode::AllAncestors, UpdateMode::PathOnly] {
+            let mut sequential = MonteCarloGraph::<Rummy>::new().with_update_mode(mode);
+            for sample in &samples {
+                sequential.train(sample, false);
+            }
+            let mut parallel = MonteCarloGraph::<Rummy>::new().with_update_mode(mode);
+            pool.install(|| parallel.train_batch_parallel(&samples, None));
+
+            // Every deal hangs off the same root, whichever run trained the graph
+            assert_eq!(sequential.root(), Rummy::undealt());
+            assert_eq!(parallel.root(), sequential.root());
+            assert_eq!(parallel.nodes().len(), sequential.nodes().len());
+
+            // The last move is credited with a loss if the knock was undercut
+            for sample in &samples {
+                let (path, result) = sequential.replay(sample);
+                let (from, to) = (path[path.len() - 2], path[path.len() - 1]);
+                let last = sequential.edge_weight(from, to).unwrap();
+                match result {
+                    GameStatus::Win(winner) if from.mover() == Some(winner) => {
+                        assert!(last.wins() > 0)
+                    }
+                    GameStatus::Win(_) => assert!(last.losses() > 0),
+                    _ => assert!(last.draws() > 0),
+                }
+            }
+        }
+
+        // Positions can repeat, which only crediting along the path handles exactly. A
+        // draw and the discard after it are credited to the same player.
+        let mut graph = MonteCarloGraph::<Rummy>::new().with_update_mode(UpdateMode::PathOnly);
+        graph.train_batch(&samples, None);
+        assert_eq!(graph.visits(&Rummy::undealt()), 100);
+        assert!(graph.validate());
+
+        // Matches deal again after every hand, and replay the same deals
+        let samples = play_batch_parallel::<GinRummyMatch, _, _>(
+            || Box::new(RandomAgent::new()),
+            || Box::new(RandomAgent::new()),
+            2,
+            None,
+        );
+        let mut graph =
+            MonteCarloGraph::<GinRummyMatch>::new().with_update_mode(UpdateMode::PathOnly);
+        graph.train_batch(&samples, None);
+        assert_eq!(graph.visits(&GinRummyMatch::undealt()), 2);
+        assert!(graph.validate());
+    }
+
+    #[test]
+    fn test_merge_matches_combined_training() {
+        use super::{MonteCarloGraph, UpdateMode};
+        use crate::agents::RandomAgent;
+        use crate::agents::train::{TrainableComponent, play_batch_parallel};
+        use crate::connect_four::ConnectFour;
+
+        let samples = play_batch_parallel::<ConnectFour, _, _>(
+            || Box::new(RandomAgent::new()),
+            || Box::new(RandomAgent::new()),
+            200,
+            None,
+        );
+
+        for mode in [UpdateMode::AllAncestors, UpdateMode::PathOnly] {
+            let mut a = MonteCarloGraph::<ConnectFour>::new().with_update_mode(mode);
+            let mut b = MonteCarloGraph::<ConnectFour>::new().with_update_mode(mode);
+            let mut combined = MonteCarloGraph::<ConnectFour>::new().with_update_mode(mode);
+            a.train_batch_parallel(&samples[..100], None);
+            b.train_batch_parallel(&samples[100..], None);
+            combined.train_batch(&samples, None);
+
+            a.merge(&b).unwrap();
+            assert!(a.validate());
+            let diff = combined.diff(&a, 0.0);
+            assert!(diff.new_nodes.is_empty() && diff.removed_nodes.is_empty());
+            assert!(diff.new_edges.is_empty());
+            if mode == UpdateMode::PathOnly {
+                assert!(diff.is_empty());
+            }
+        }
+
+        let a = MonteCarloGraph::<ConnectFour>::new();
+        let b = MonteCarloGraph::<ConnectFour>::new().with_update_mode(UpdateMode::PathOnly);
+        assert!(a.clone().merge(&b).is_err());
+    }
+
+    #[test]
+    fn test_node_budget_bounds_graph() {
+        use super::{EvictionPolicy, MonteCarloGraph, UpdateMode};
+        use crate::agents::RandomAgent;
+        use crate::agents::train::{TrainableComponent, play_batch_parallel};
+        use crate::connect_four::ConnectFour;
+
+        let samples = play_batch_parallel::<ConnectFour, _, _>(
+            || Box::new(RandomAgent::new()),
+            || Box::new(RandomAgent::new()),
+            300,
+            None,
+        );
+
+        for mode in [UpdateMode::AllAncestors, UpdateMode::PathOnly] {
+            for policy in [
+                EvictionPolicy::FewestVisits,
+                EvictionPolicy::Deepest,
+                EvictionPolicy::LeastRecentlyUpdated,
+            ] {
+                let mut graph = MonteCarloGraph::<ConnectFour>::new()
+                    .with_update_mode(mode)
+                    .with_node_budget(500, policy);
+                for batch in samples.chunks(10) {
+                    graph.train_batch_parallel(batch, None);
+                    assert!(graph.node_count() <= 500);
+                }
+
+                // Evicted statistics are kept by the parents. Transpositions count
+                // once per path into them when crediting all ancestors.
+                assert!(graph.validate());
+                let visits = graph.visits(&ConnectFour::default());
+                match mode {
+                    UpdateMode::AllAncestors => assert!(visits >= samples.len()),
+                    UpdateMode::PathOnly => assert_eq!(visits, samples.len()),
+                }
+
+                if policy == EvictionPolicy::LeastRecentlyUpdated {
+                    let (path, _) = graph.replay(samples.last().unwrap());
+                    assert!(path.iter().all(|n| graph.contains_node(n)));
+                }
+            }
+        }
+
+        // Pruning removes whole subtrees below the threshold but keeps the totals
+        let mut graph =
+            MonteCarloGraph::<ConnectFour>::new().with_update_mode(UpdateMode::PathOnly);
+        graph.train_batch(&samples, None);
+        let before = graph.node_count();
+        assert!(graph.prune_min_visits(2) > 0);
+        assert!(graph.node_count() < before);
+        assert!(
+            graph
+                .nodes()
+                .iter()
+                .filter(|n| graph.edges_from(n).is_empty())
+                .all(|n| graph.visits(n) >= 2)
+        );
+        assert_eq!(graph.visits(&ConnectFour::default()), samples.len());
+        assert!(graph.validate());
+
+        graph.prune_depth(3);
+        assert!(graph.node_count() <= 1 + 7 + 49 + 343);
+        assert_eq!(graph.prune_unreachable(), 0);
+    }
+
+    #[test]
+    fn test_prune_unreachable_keeps_games_entering_reachable_nodes() {
+        use super::{MonteCarloGraph, UpdateMode};
+        use crate::GameStatus;
+
+        // 5 and 6 are unreachable, but the game through them went on through 2
+        let mut graph: MonteCarloGraph<u32> =
+            MonteCarloGraph::new().with_update_mode(UpdateMode::PathOnly);
+        graph.back_propogate(vec![0, 1, 2, 3], GameStatus::Win(0));
+        graph.back_propogate(vec![5, 6, 2, 4], GameStatus::Draw);
+        assert!(graph.validate());
+
+        assert_eq!(graph.prune_unreachable(), 2);
+        assert_eq!(graph.nodes(), vec![0, 1, 2, 3, 4]);
+        assert_eq!(graph.visits(&2), 2);
+        assert_eq!(graph.visits(&1), 1);
+        assert!(graph.validate());
+
+        // The kept weight is merged with the rest of the graph and dropped on eviction
+        let mut other = graph.clone();
+        other.merge(&graph).unwrap();
+        assert_eq!(other.visits(&2), 4);
+        assert!(other.validate());
+        other.prune_min_visits(5);
+        assert_eq!(other.nodes(), vec![0]);
+        assert!(other.validate());
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        use super::{Canonical, MonteCarloGraph};
+        use crate::agents::graph_file::{GraphFileError, Mapping, read_header};
+        use crate::agents::train::TrainableComponent;
+        use crate::connect_four::ConnectFour;
+        use crate::ultimate_ttt::UltimateTTT;
+        use crate::{Game, GameStatus, PlayThrough};
+
+        let path = std::env::temp_dir().join(format!("mcg_test_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut sample = PlayThrough::<ConnectFour>::new(GameStatus::InProgress, Vec::new());
+        let mut game = ConnectFour::default();
+        for mv in [3, 3, 4, 4, 5, 5, 6] {
+            let player = game.get_current_player();
+            game.play(mv, player).unwrap();
+            sample.add_move(player, mv);
+        }
+        sample.set_result(game.get_status());
+
+        let mut graph = MonteCarloGraph::<ConnectFour>::new();
+        graph.train(&sample, false);
+        graph.to_file(path).unwrap();
+
+        let header = read_header(path).unwrap();
+        assert_eq!(header.game, ConnectFour::name);
+        assert_eq!(header.samples, 1);
+        let loaded = MonteCarloGraph::<ConnectFour>::from_file(path).unwrap();
+        assert!(graph.diff(&loaded, 0.0).is_empty());
+        assert_eq!(loaded.samples(), 1);
+
+        assert!(matches!(
+            MonteCarloGraph::<UltimateTTT>::from_file(path),
+            Err(GraphFileError::WrongGame { .. })
+        ));
+
+        // Graphs trained with symmetries merged are only loaded as such
+        let mut canonical = MonteCarloGraph::<ConnectFour>::new().with_symmetries();
+        canonical.train(&sample, false);
+        canonical.to_file(path).unwrap();
+        assert_eq!(read_header(path).unwrap().mapping, Mapping::Canonical);
+        let loaded = MonteCarloGraph::<ConnectFour, Canonical>::from_file(path).unwrap();
+        assert!(canonical.diff(&loaded, 0.0).is_empty());
+        assert!(matches!(
+            MonteCarloGraph::<ConnectFour>::from_file(path),
+            Err(GraphFileError::WrongMapping {
+                expected: Mapping::Exact,
+                found: Mapping::Canonical
+            })
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_migrates_files_without_header() {
+        use super::{GraphPosition, MonteCarloGraph};
+        use crate::agents::graph_file::{GraphNode, read_header};
+        use crate::connect_four::ConnectFour;
+        use crate::rummy::GinRummyMatch;
+        use crate::ultimate_ttt::UltimateTTT;
+        use crate::{Game, GameStatus};
+
+        // The fixtures were written by `to_file` before graph files had a header, from
+        // two games that always play the first and the last available move
+        fn expected<G: Game + GraphPosition>() -> MonteCarloGraph<G> {
+            let picks: [fn(usize) -> usize; 2] = [|_| 0, |n| n - 1];
+            let mut graph = MonteCarloGraph::new();
+            for pick in picks {
+                let mut game = G::default();
+                let mut path = vec![game];
+                while game.get_status() == GameStatus::InProgress {
+                    let moves = game.get_available_moves();
+                    game.play(moves[pick(moves.len())], game.get_current_player())
+                        .unwrap();
+                    path.push(game);
+                }
+                graph.back_propogate(path, game.get_status());
+            }
+            graph
+        }
+
+        fn check<G: Game + GraphNode + GraphPosition>(fixture: &str) {
+            let path = format!("{}/assets/fixtures/{}", env!("CARGO_MANIFEST_DIR"), fixture);
+            assert_eq!(read_header(&path).unwrap().version, 0);
+
+            let migrated = MonteCarloGraph::<G>::from_file(&path).unwrap();
+            let expected = expected::<G>();
+            assert!(expected.diff(&migrated, 0.0).is_empty());
+            assert!(migrated.diff(&expected, 0.0).is_empty());
+            assert_eq!(migrated.root, G::default());
+            assert!(migrated.validate());
+        }
+
+        check::<ConnectFour>("connect_four_v0.bin");
+        check::<UltimateTTT>("ultimate_ttt_v0.bin");
+
+        let path = format!(
+            "{}/assets/fixtures/connect_four_v0.bin",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        assert!(MonteCarloGraph::<GinRummyMatch>::from_file(&path).is_err());
+    }
 }
@@ -0,0 +1,221 @@
+//! Move selection policies for Monte Carlo search agents.
+//!
+//! A [`SelectionPolicy`] turns per-move [`EdgeWeight`] statistics into a value for each
+//! candidate move. It is shared by [`MonteCarloGraphSearch`](crate::agents::MonteCarloGraphSearch)
+//! and [`MonteCarloTreeSearch`](crate::agents::mcts::MonteCarloTreeSearch), so exploration
+//! can be tuned per game in one place.
+
+use crate::agents::monte_carlo_graph::EdgeWeight;
+
+use derive_aliases::derive;
+
+/// The formula used to value a move.
+#[derive(..Copy, Debug, PartialEq)]
+pub enum Formula {
+    /// `q + c * sqrt(ln N / n)`
+    Ucb1,
+    /// `q + c * sqrt(ln N / n * min(1/4, V))`, where `V` is an upper confidence bound on
+    /// the variance of the move's rewards
+    Ucb1Tuned,
+    /// `q + c * P * sqrt(N) / (1 + n)` with a uniform prior `P` over the candidate moves
+    Puct,
+    /// Mean reward only, without exploration
+    Greedy,
+    /// Number of visits only, without exploration
+    Robust,
+}
+
+/// Configurable rule for valuing candidate moves from their statistics.
+///
+/// Rewards are 1 for a win, `draw_value` for a draw and 0 for a loss, from the
+/// perspective of the player making the move. `N` is the total number of visits over
+/// all candidates and `n` the visits of the move being valued.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::monte_carlo_graph::EdgeWeight;
+/// use games_rs::agents::selection::SelectionPolicy;
+///
+/// let stats: [Option<EdgeWeight>; 3] = [Some((6, 4, 0).into()), Some((4, 1, 5).into()), None];
+///
+/// // Unvisited moves are tried first by default
+/// assert_eq!(SelectionPolicy::default().best_moves(&stats), vec![2]);
+///
+/// // Counting draws as half a win prefers the drawish move
+/// assert_eq!(SelectionPolicy::greedy().best_moves(&stats), vec![1]);
+/// assert_eq!(SelectionPolicy::greedy().with_draw_value(0.0).best_moves(&stats), vec![0]);
+/// ```
+#[derive(..Copy, Debug, PartialEq)]
+pub struct SelectionPolicy {
+    /// The formula used to value moves
+    pub formula: Formula,
+    /// Weight of the exploration term
+    pub exploration: f64,
+    /// Value of moves that have never been visited; for [`Formula::Puct`] this is the
+    /// estimated mean reward, to which the exploration term is still added
+    pub first_play_urgency: f64,
+    /// Reward credited for a draw
+    pub draw_value: f64,
+}
+
+impl Default for SelectionPolicy {
+    /// UCB1 with `c = sqrt(2)`, unvisited moves first and draws worth half a win.
+    fn default() -> Self {
+        SelectionPolicy::ucb1(std::f64::consts::SQRT_2)
+    }
+}
+
+impl SelectionPolicy {
+    /// Tolerance when comparing move values for ties.
+    const TIE_TOLERANCE: f64 = 1e-9;
+
+    /// UCB1 with the given exploration constant.
+    pub fn ucb1(exploration: f64) -> Self {
+        SelectionPolicy {
+            formula: Formula::Ucb1,
+            exploration,
+            first_play_urgency: f64::INFINITY,
+            draw_value: 0.5,
+        }
+    }
+
+    /// UCB1-Tuned, which scales exploration by the observed reward variance.
+    pub fn ucb1_tuned() -> Self {
+        SelectionPolicy {
+            formula: Formula::Ucb1Tuned,
+            ..Self::ucb1(1.0)
+        }
+    }
+
+    /// PUCT with the given exploration constant and unvisited moves valued as draws.
+    pub fn puct(exploration: f64) -> Self {
+        SelectionPolicy {
+            formula: Formula::Puct,
+            exploration,
+            first_play_urgency: 0.5,
+            draw_value: 0.5,
+        }
+    }
+
+    /// Picks the move with the best mean reward, for competitive play.
+    pub fn greedy() -> Self {
+        SelectionPolicy {
+            formula: Formula::Greedy,
+            exploration: 0.0,
+            first_play_urgency: 0.0,
+            draw_value: 0.5,
+        }
+    }
+
+    /// Picks the most visited move, for competitive play.
+    pub fn robust() -> Self {
+        SelectionPolicy {
+            formula: Formula::Robust,
+            exploration: 0.0,
+            first_play_urgency: 0.0,
+            draw_value: 0.5,
+        }
+    }
+
+    /// Sets the exploration constant.
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Sets the value of unvisited moves.
+    pub fn with_first_play_urgency(mut self, first_play_urgency: f64) -> Self {
+        self.first_play_urgency = first_play_urgency;
+        self
+    }
+
+    /// Sets the reward credited for a draw.
+    pub fn with_draw_value(mut self, draw_value: f64) -> Self {
+        self.draw_value = draw_value;
+        self
+    }
+
+    /// Returns the mean reward of a move with the given statistics.
+    pub fn mean(&self, stats: &EdgeWeight) -> f64 {
+        let n = stats.simulations() as f64;
+        (stats.wins() as f64 + self.draw_value * stats.draws() as f64) / n
+    }
+
+    /// Values a move given its statistics, the total visits over all candidates and
+    /// its prior probability.
+    pub fn value(&self, stats: Option<&EdgeWeight>, total_visits: usize, prior: f64) -> f64 {
+        let stats = stats.filter(|stats| stats.simulations() > 0);
+        let total = total_visits.max(1) as f64;
+
+        if self.formula == Formula::Puct {
+            let (q, n) = match stats {
+                Some(stats) => (self.mean(stats), stats.simulations() as f64),
+                None => (self.first_play_urgency, 0.0),
+            };
+            return q + self.exploration * prior * total.sqrt() / (1.0 + n);
+        }
+
+        let Some(stats) = stats else {
+            return self.first_play_urgency;
+        };
+        let n = stats.simulations() as f64;
+        let q = self.mean(stats);
+
+        match self.formula {
+            Formula::Ucb1 => q + self.exploration * (total.ln() / n).sqrt(),
+            Formula::Ucb1Tuned => {
+                let squares = stats.wins() as f64 + self.draw_value.powi(2) * stats.draws() as f64;
+                let variance = squares / n - q * q + (2.0 * total.ln() / n).sqrt();
+                q + self.exploration * (total.ln() / n * variance.min(0.25)).sqrt()
+            }
+            Formula::Greedy => q,
+            Formula::Robust => n,
+            Formula::Puct => unreachable!(),
+        }
+    }
+
+    /// Returns the indices of the highest-valued candidates.
+    ///
+    /// Candidates without statistics are unvisited. Ties are all returned, so callers
+    /// can break them as they see fit.
+    pub fn best_moves(&self, stats: &[Option<EdgeWeight>]) -> Vec<usize> {
+        let total = stats.iter().flatten().map(|s| s.simulations()).sum();
+        let prior = 1.0 / stats.len().max(1) as f64;
+        let values = stats
+            .iter()
+            .map(|s| self.value(s.as_ref(), total, prior))
+            .collect::<Vec<_>>();
+
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (0..values.len())
+            .filter(|&i| values[i] >= max - Self::TIE_TOLERANCE)
+            .collect()
+    }
+}
+
+mod test {
+    #[test]
+    fn test_formulas_prefer_expected_moves() {
+        use super::SelectionPolicy;
+        use crate::agents::monte_carlo_graph::EdgeWeight;
+
+        let stats: [Option<EdgeWeight>; 2] = [Some((61, 39, 0).into()), Some((3, 2, 0).into())];
+
+        // Exploration favours the rarely visited move, exploitation the stronger one
+        assert_eq!(SelectionPolicy::ucb1(2.0).best_moves(&stats), vec![1]);
+        assert_eq!(SelectionPolicy::ucb1(0.0).best_moves(&stats), vec![0]);
+        assert_eq!(SelectionPolicy::puct(5.0).best_moves(&stats), vec![1]);
+        assert_eq!(SelectionPolicy::robust().best_moves(&stats), vec![0]);
+
+        // The variance term is capped at 1/4, so UCB1-Tuned never explores more than UCB1
+        let stats: [Option<EdgeWeight>; 2] = [Some((0, 0, 10).into()), Some((50, 50, 0).into())];
+        let tuned = SelectionPolicy::ucb1_tuned();
+        let ucb1 = SelectionPolicy::ucb1(1.0);
+        assert!(tuned.value(stats[0].as_ref(), 110, 0.5) < ucb1.value(stats[0].as_ref(), 110, 0.5));
+
+        // First-play urgency below the best mean stops unvisited moves from being tried
+        let stats: [Option<EdgeWeight>; 2] = [Some((9, 1, 0).into()), None];
+        let policy = SelectionPolicy::ucb1(0.1).with_first_play_urgency(0.5);
+        assert_eq!(policy.best_moves(&stats), vec![0]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Fixed-size transposition table for game-tree searches.
+//!
+//! Entries are indexed by [`Game::hash_key`](crate::Game::hash_key) and record the
+//! search depth, the kind of bound the stored value represents and the best move found,
+//! so that positions reached through different move orders are only searched once.
+
+use derive_aliases::derive;
+
+/// How a stored value relates to the true value of a position.
+#[derive(..StdTraits, Debug)]
+pub enum Bound {
+    /// The value is exact
+    Exact,
+    /// The search failed high: the true value is at least the stored value
+    Lower,
+    /// The search failed low: the true value is at most the stored value
+    Upper,
+}
+
+/// Decides whether a new entry overwrites the one already occupying its slot.
+#[derive(..StdTraits, Debug)]
+pub enum ReplacementPolicy {
+    /// Every store overwrites the slot.
+    AlwaysReplace,
+    /// Keeps entries searched to a greater depth, unless they are left over from an
+    /// earlier search or describe the same position.
+    DepthPreferred,
+}
+
+/// A cached search result.
+#[derive(..Copy, Debug)]
+pub struct Entry<M> {
+    /// Full hash key of the position, used to detect index collisions
+    pub key: u64,
+    /// Remaining depth the position was searched to
+    pub depth: u8,
+    /// Search value from the perspective of the player to move
+    pub value: f32,
+    /// How `value` bounds the true value
+    pub bound: Bound,
+    /// Best move found, if any move was searched
+    pub best_move: Option<M>,
+    generation: u8,
+}
+
+impl<M> Entry<M> {
+    /// Creates an entry for the position with the given key.
+    pub fn new(key: u64, depth: u8, value: f32, bound: Bound, best_move: Option<M>) -> Self {
+        Entry {
+            key,
+            depth,
+            value,
+            bound,
+            best_move,
+            generation: 0,
+        }
+    }
+
+    /// Returns the value if it settles a search with window `(alpha, beta)`.
+    pub fn cutoff(&self, alpha: f32, beta: f32) -> Option<f32> {
+        match self.bound {
+            Bound::Exact => Some(self.value),
+            Bound::Lower if self.value >= beta => Some(self.value),
+            Bound::Upper if self.value <= alpha => Some(self.value),
+            _ => None,
+        }
+    }
+}
+
+/// Counters describing how effective the table has been.
+#[derive(..Copy, Debug, Default)]
+pub struct TableStats {
+    /// Number of lookups
+    pub probes: u64,
+    /// Number of lookups that found an entry for the same position
+    pub hits: u64,
+    /// Number of entries written
+    pub stores: u64,
+    /// Number of stores that evicted an entry for a different position
+    pub overwrites: u64,
+    /// Number of stores rejected by the replacement policy
+    pub rejected: u64,
+}
+
+impl TableStats {
+    /// Returns the fraction of probes that were hits.
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.probes as f64
+        }
+    }
+}
+
+/// A fixed-size, direct-mapped transposition table.
+///
+/// # Examples
+/// ```
+/// use games_rs::agents::transposition::{Bound, Entry, ReplacementPolicy, TranspositionTable};
+///
+/// let mut table = TranspositionTable::<usize>::with_capacity(1024, ReplacementPolicy::DepthPreferred);
+/// table.store(Entry::new(42, 3, 0.5, Bound::Exact, Some(2)));
+///
+/// assert_eq!(table.probe(42).unwrap().best_move, Some(2));
+/// assert!(table.probe(43).is_none());
+/// assert_eq!(table.stats().hit_rate(), 0.5);
+/// ```
+pub struct TranspositionTable<M> {
+    entries: Vec<Option<Entry<M>>>,
+    policy: ReplacementPolicy,
+    generation: u8,
+    stats: TableStats,
+}
+
+impl<M: Copy> TranspositionTable<M> {
+    /// Creates a table with room for `entries` positions.
+    pub fn with_capacity(entries: usize, policy: ReplacementPolicy) -> Self {
+        TranspositionTable {
+            entries: vec![None; entries.max(1)],
+            policy,
+            generation: 0,
+            stats: TableStats::default(),
+        }
+    }
+
+    /// Creates the largest table that fits in `bytes` bytes.
+    pub fn with_memory(bytes: usize, policy: ReplacementPolicy) -> Self {
+        Self::with_capacity(bytes / std::mem::size_of::<Option<Entry<M>>>(), policy)
+    }
+
+    /// Returns the number of slots in the table.
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of occupied slots.
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.is_some()).count()
+    }
+
+    /// Returns the replacement policy.
+    pub fn policy(&self) -> ReplacementPolicy {
+        self.policy
+    }
+
+    /// Returns the statistics gathered since the table was created or last reset.
+    pub fn stats(&self) -> TableStats {
+        self.stats
+    }
+
+    /// Resets the statistics counters.
+    pub fn reset_stats(&mut self) {
+        self.stats = TableStats::default();
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// Marks the start of a new search, so that entries from earlier searches are
+    /// replaced first under [`ReplacementPolicy::DepthPreferred`].
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    #[inline]
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    /// Looks up the entry for the position with the given key.
+    pub fn probe(&mut self, key: u64) -> Option<&Entry<M>> {
+        self.stats.probes += 1;
+        let index = self.index(key);
+        match &self.entries[index] {
+            Some(entry) if entry.key == key => {
+                self.stats.hits += 1;
+                self.entries[index].as_ref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Stores an entry, subject to the replacement policy.
+    pub fn store(&mut self, mut entry: Entry<M>) {
+        entry.generation = self.generation;
+        let index = self.index(entry.key);
+        let slot = &mut self.entries[index];
+
+        if let Some(existing) = slot {
+            let replace = match self.policy {
+                ReplacementPolicy::AlwaysReplace => true,
+                ReplacementPolicy::DepthPreferred => {
+                    existing.key == entry.key
+                        || existing.generation != entry.generation
+                        || entry.depth >= existing.depth
+                }
+            };
+            if !replace {
+                self.stats.rejected += 1;
+                return;
+            }
+            if existing.key != entry.key {
+                self.stats.overwrites += 1;
+            }
+        }
+
+        *slot = Some(entry);
+        self.stats.stores += 1;
+    }
+}
+
+mod test {
+    #[test]
+    fn test_depth_preferred_replacement() {
+        use super::{Bound, Entry, ReplacementPolicy, TranspositionTable};
+
+        let mut table =
+            TranspositionTable::<u8>::with_capacity(1, ReplacementPolicy::DepthPreferred);
+        table.store(Entry::new(1, 5, 1.0, Bound::Exact, None));
+
+        // A shallower entry for another position does not evict the deeper one
+        table.store(Entry::new(2, 2, 0.0, Bound::Exact, None));
+        assert!(table.probe(1).is_some());
+        assert_eq!(table.stats().rejected, 1);
+
+        // Same position always updates
+        table.store(Entry::new(1, 1, 0.5, Bound::Lower, Some(3)));
+        assert_eq!(table.probe(1).unwrap().bound, Bound::Lower);
+
+        // Entries from earlier searches are replaced
+        table.new_search();
+        table.store(Entry::new(2, 0, 0.0, Bound::Upper, None));
+        assert!(table.probe(1).is_none());
+        assert_eq!(table.stats().overwrites, 1);
+
+        let mut table =
+            TranspositionTable::<u8>::with_capacity(1, ReplacementPolicy::AlwaysReplace);
+        table.store(Entry::new(1, 5, 1.0, Bound::Exact, None));
+        table.store(Entry::new(2, 0, 0.0, Bound::Exact, None));
+        assert!(table.probe(2).is_some());
+    }
+}
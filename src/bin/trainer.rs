@@ -1,37 +1,288 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use games_rs::{
-    Game, agents::RandomAgent, agents::monte_carlo_graph::MonteCarloGraph,
-    agents::train::TrainableComponent, connect_four::ConnectFour,
+    Game,
+    agents::analysis::Rate,
+    agents::connect_four_solver::ConnectFourSolver,
+    agents::graph_export::GraphExport,
+    agents::graph_file::{GraphFileError, GraphMapping, GraphNode, Mapping, read_header},
+    agents::ismcts::InformationSetMcts,
+    agents::mcts::{MonteCarloTreeSearch, SearchBudget},
+    agents::monte_carlo_graph::{
+        Canonical, EvictionPolicy, Exact, GraphPosition, MonteCarloGraph, NodeMapping, UpdateMode,
+    },
+    agents::scorer::naive_scorer::NaiveScorer,
+    agents::{Agent, MinimaxAgent, MonteCarloGraphSearch, RandomAgent},
+    connect_four::ConnectFour,
+    rummy::{GinRummyMatch, Rummy},
+    ultimate_ttt::UltimateTTT,
 };
 use indicatif::MultiProgress;
+use std::{path::Path, sync::Arc};
 
 #[derive(clap::ValueEnum, Clone, Debug)]
 enum AgentType {
-    MCGS,
+    Mcgs,
 }
 
+/// Agents that can play the games used as training samples.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OpponentType {
+    Random,
+    Minimax,
+    Mcts,
+    /// Search over the graph as trained by the previous rounds
+    Mcgs,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum EvictionType {
+    FewestVisits,
+    Deepest,
+    Lru,
+}
+
+impl From<EvictionType> for EvictionPolicy {
+    fn from(value: EvictionType) -> Self {
+        match value {
+            EvictionType::FewestVisits => EvictionPolicy::FewestVisits,
+            EvictionType::Deepest => EvictionPolicy::Deepest,
+            EvictionType::Lru => EvictionPolicy::LeastRecentlyUpdated,
+        }
+    }
+}
+
+/// Games graphs can be trained on.
 #[derive(clap::ValueEnum, Clone, Debug)]
 enum GameType {
     ConnectFour,
     UltimateTTT,
+    /// A single hand of Gin Rummy
+    Rummy,
+    /// Hands of Gin Rummy played until a player reaches the target score
+    GinRummyMatch,
+}
+
+/// A game the trainer supports, with the options that only some games have.
+trait TrainedGame: Game + GraphNode + GraphPosition + Send + Sync + 'static {
+    /// Node mapping of graphs trained with `--merge-symmetries`
+    type Symmetric: NodeMapping<Self> + GraphMapping + Clone + Send + Sync + 'static;
+
+    /// How new graphs credit results. Crediting every ancestor loses results on cycles,
+    /// so games that repeat positions credit the path instead.
+    const UPDATE_MODE: UpdateMode = UpdateMode::AllAncestors;
+
+    /// Returns an empty graph merging symmetric positions, or `None` if the game has no
+    /// symmetries.
+    fn symmetric_graph() -> Option<MonteCarloGraph<Self, Self::Symmetric>>;
+
+    /// Returns a minimax player searching `depth` moves, or `None` if the game has no
+    /// score function.
+    fn minimax(depth: usize) -> Option<Box<dyn Agent<Self>>>;
+}
+
+impl TrainedGame for ConnectFour {
+    type Symmetric = Canonical;
+
+    fn symmetric_graph() -> Option<MonteCarloGraph<Self, Canonical>> {
+        Some(MonteCarloGraph::new().with_symmetries())
+    }
+
+    fn minimax(depth: usize) -> Option<Box<dyn Agent<Self>>> {
+        Some(Box::new(MinimaxAgent::new(depth, NaiveScorer::new())))
+    }
+}
+
+impl TrainedGame for UltimateTTT {
+    type Symmetric = Canonical;
+
+    fn symmetric_graph() -> Option<MonteCarloGraph<Self, Canonical>> {
+        Some(MonteCarloGraph::new().with_symmetries())
+    }
+
+    fn minimax(depth: usize) -> Option<Box<dyn Agent<Self>>> {
+        Some(Box::new(MinimaxAgent::new(depth, NaiveScorer::new())))
+    }
+}
+
+impl TrainedGame for Rummy {
+    type Symmetric = Exact;
+    const UPDATE_MODE: UpdateMode = UpdateMode::PathOnly;
+
+    fn symmetric_graph() -> Option<MonteCarloGraph<Self>> {
+        None
+    }
+
+    fn minimax(_: usize) -> Option<Box<dyn Agent<Self>>> {
+        None
+    }
+}
+
+impl TrainedGame for GinRummyMatch {
+    type Symmetric = Exact;
+    const UPDATE_MODE: UpdateMode = UpdateMode::PathOnly;
+
+    fn symmetric_graph() -> Option<MonteCarloGraph<Self>> {
+        None
+    }
+
+    fn minimax(_: usize) -> Option<Box<dyn Agent<Self>>> {
+        None
+    }
 }
 
 #[derive(Parser, Debug)]
-struct Args {
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate self-play samples and train agents on them
+    Train(TrainArgs),
+    /// Merge trained graph files into one
+    Merge(MergeArgs),
+    /// Export part of a trained graph for viewing
+    Export(ExportArgs),
+    /// Print what a trained graph has learned about a position
+    Analyze(AnalyzeArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct TrainArgs {
+    /// Number of samples per round
     #[clap(short, long, default_value_t = 1000)]
     num_samples: usize,
+    /// Number of rounds of sample generation and training
+    #[clap(long, default_value_t = 1)]
+    rounds: usize,
+    /// Game to train on
     #[clap(long, required = true)]
     game: GameType,
     #[clap(long, required = false)]
     agents: Vec<AgentType>,
+    /// Agent playing first in the sample games
+    #[clap(long, value_enum, default_value_t = OpponentType::Random)]
+    player1: OpponentType,
+    /// Agent playing second in the sample games
+    #[clap(long, value_enum, default_value_t = OpponentType::Random)]
+    player2: OpponentType,
+    /// Search depth of minimax players, which Connect Four and Ultimate Tic-Tac-Toe have
+    #[clap(long, default_value_t = 3)]
+    minimax_depth: usize,
+    /// Iterations per move of MCTS players
+    #[clap(long, default_value_t = 200)]
+    mcts_iterations: usize,
     #[clap(long, default_value_t = true)]
     verbose: bool,
+    /// Store symmetric positions as a single graph node (Connect Four and Ultimate
+    /// Tic-Tac-Toe)
+    #[clap(long, default_value_t = false)]
+    merge_symmetries: bool,
+    /// Maximum number of positions kept in a graph
+    #[clap(long)]
+    max_nodes: Option<usize>,
+    /// Which positions to evict once a graph reaches --max-nodes
+    #[clap(long, value_enum, default_value_t = EvictionType::FewestVisits)]
+    eviction: EvictionType,
+    /// File to write the trained graph and its checkpoints to
+    #[clap(short, long)]
+    output: Option<String>,
+    /// Continue training from the graph in --output, counting its samples towards the
+    /// --rounds times --num-samples to train on.
+    /// Refused unless --merge-symmetries matches the run that wrote it.
+    #[clap(long, default_value_t = false, requires = "output")]
+    resume: bool,
+    /// Write a checkpoint to --output after every this many samples
+    #[clap(long, requires = "output")]
+    checkpoint_every: Option<usize>,
 }
 
-pub fn main() {
-    let args = Args::parse();
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    #[clap(long, required = true)]
+    game: GameType,
+    /// File to write the merged graph to
+    #[clap(short, long, required = true)]
+    output: String,
+    /// Graph files to merge
+    #[clap(required = true, num_args = 1..)]
+    inputs: Vec<String>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Dot,
+    Graphml,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportArgs {
+    #[clap(long, required = true)]
+    game: GameType,
+    /// Graph file to export from
+    input: String,
+    /// File to write the export to
+    #[clap(short, long, required = true)]
+    output: String,
+    #[clap(long, value_enum, default_value_t = ExportFormat::Dot)]
+    format: ExportFormat,
+    /// Moves leading from the initial position to the position to export from. Rummy
+    /// graphs start before the deal, which is not a move, so only their root is reached
+    #[clap(long, value_delimiter = ',')]
+    moves: Vec<String>,
+    /// Maximum number of moves from the starting position
+    #[clap(long)]
+    max_depth: Option<usize>,
+    /// Minimum number of visits of exported positions
+    #[clap(long, default_value_t = 0)]
+    min_visits: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct AnalyzeArgs {
+    #[clap(long, required = true)]
+    game: GameType,
+    /// Graph file to analyze
+    input: String,
+    /// Moves leading from the initial position to the position to analyze. Rummy
+    /// graphs start before the deal, which is not a move, so only their root is reached
+    #[clap(long, value_delimiter = ',')]
+    moves: Vec<String>,
+    /// Number of moves to list
+    #[clap(long, default_value_t = 5)]
+    top: usize,
+    /// Maximum length of the principal variation
+    #[clap(long, default_value_t = 20)]
+    pv_length: usize,
+    /// Number of positions disagreeing with the reference to list
+    #[clap(long, default_value_t = 0)]
+    disagreements: usize,
+    /// Minimum number of visits of positions checked against the reference
+    #[clap(long, default_value_t = 20)]
+    min_visits: usize,
+    /// Smallest difference in expected score reported as a disagreement
+    #[clap(long, default_value_t = 0.3)]
+    threshold: f64,
+    /// Minimum number of tokens on the board before positions are solved (Connect Four)
+    #[clap(long, default_value_t = 12)]
+    solve_from: usize,
+}
+
+/// What graph statistics are checked against in an analysis.
+enum Reference<'a, G: Game> {
+    /// Expected scores of positions
+    Values(&'a dyn Fn(&G) -> Option<f64>),
+    /// Moves chosen by an agent
+    Moves(&'a dyn Agent<G>),
+}
+
+fn train<G: TrainedGame>(args: &TrainArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let players = [args.player1, args.player2];
+    if players.contains(&OpponentType::Minimax) && G::minimax(args.minimax_depth).is_none() {
+        return Err(format!("{} has no score function for minimax players", G::name).into());
+    }
 
-    println!("{:?}", args);
     let mpb = if args.verbose {
         let pb = MultiProgress::new();
         Some(pb)
@@ -39,30 +290,408 @@ pub fn main() {
         None
     };
 
-    let a1_agent = Box::new(RandomAgent::<ConnectFour>::new());
-    let a2_agent = Box::new(RandomAgent::<ConnectFour>::new());
+    for agent_type in &args.agents {
+        match agent_type {
+            AgentType::Mcgs => {
+                if args.merge_symmetries {
+                    let fresh = G::symmetric_graph()
+                        .ok_or_else(|| format!("{} has no symmetries to merge", G::name))?
+                        .with_update_mode(G::UPDATE_MODE);
+                    train_graph(starting_graph(fresh, args)?, args, mpb.as_ref())?;
+                } else {
+                    let fresh = MonteCarloGraph::<G>::new().with_update_mode(G::UPDATE_MODE);
+                    train_graph(starting_graph(fresh, args)?, args, mpb.as_ref())?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the graph to train: the graph in `--output` when resuming, or `fresh`.
+///
+/// Graph files record whether symmetries were merged, so resuming with a different
+/// `--merge-symmetries` than the run that wrote the file is refused.
+fn starting_graph<G, M>(
+    fresh: MonteCarloGraph<G, M>,
+    args: &TrainArgs,
+) -> Result<MonteCarloGraph<G, M>, Box<dyn std::error::Error>>
+where
+    G: Game + GraphNode,
+    M: GraphMapping,
+{
+    let mut graph = match &args.output {
+        Some(output) if args.resume && Path::new(output).exists() => {
+            let graph = MonteCarloGraph::<G, M>::from_file(output).map_err(|err| match err {
+                GraphFileError::WrongMapping { found, .. } => format!(
+                    "Cannot resume from {}: it was trained {} --merge-symmetries",
+                    output,
+                    if found == Mapping::Canonical {
+                        "with"
+                    } else {
+                        "without"
+                    }
+                ),
+                err => err.to_string(),
+            })?;
+            println!("Resuming from {} samples in {}", graph.samples(), output);
+            graph
+        }
+        _ => fresh,
+    };
+    if let Some(max_nodes) = args.max_nodes {
+        graph = graph.with_node_budget(max_nodes, args.eviction.into());
+    }
+    Ok(graph)
+}
+
+/// Creates a player for sample games.
+///
+/// MCGS players search `graph`, which is shared by all games of a batch. Minimax
+/// players are only asked for once [`train`] checked that the game has them.
+fn opponent<G, M>(
+    kind: OpponentType,
+    args: &TrainArgs,
+    graph: Option<&Arc<MonteCarloGraphSearch<G, M>>>,
+) -> Box<dyn Agent<G>>
+where
+    G: TrainedGame,
+    M: NodeMapping<G> + Clone + Send + Sync + 'static,
+{
+    match kind {
+        OpponentType::Random => Box::new(RandomAgent::<G>::new()),
+        OpponentType::Minimax => G::minimax(args.minimax_depth).unwrap(),
+        OpponentType::Mcts => Box::new(MonteCarloTreeSearch::<G>::new(SearchBudget::Iterations(
+            args.mcts_iterations,
+        ))),
+        OpponentType::Mcgs => Box::new(graph.unwrap().clone()),
+    }
+}
+
+/// Trains `graph` on `--rounds` rounds of `--num-samples` sample games each.
+///
+/// MCGS players search a copy of the graph taken at the start of each round, so every
+/// round plays against what the previous rounds learned. Within a round, samples are
+/// generated and trained on in chunks of `--checkpoint-every`, with the graph written
+/// to `--output` after every chunk, so an interrupted run loses at most one chunk of
+/// work.
+fn train_graph<G, M>(
+    mut graph: MonteCarloGraph<G, M>,
+    args: &TrainArgs,
+    mpb: Option<&MultiProgress>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    G: TrainedGame,
+    M: NodeMapping<G> + GraphMapping + Clone + Send + Sync + 'static,
+{
+    let per_round = args.num_samples.max(1) as u64;
+    let target = per_round * args.rounds as u64;
+    let chunk = args.checkpoint_every.unwrap_or(args.num_samples).max(1) as u64;
+    let uses_graph = [args.player1, args.player2].contains(&OpponentType::Mcgs);
+
+    while graph.samples() < target {
+        let round = graph.samples() / per_round;
+        let round_end = (round + 1) * per_round;
+        println!("Round {} of {}", round + 1, args.rounds);
+        let search = uses_graph.then(|| Arc::new(MonteCarloGraphSearch::from_graph(graph.clone())));
+
+        while graph.samples() < round_end {
+            let size = chunk.min(round_end - graph.samples()) as usize;
+            let batch = games_rs::agents::train::play_batch_parallel::<G, _, _>(
+                || opponent(args.player1, args, search.as_ref()),
+                || opponent(args.player2, args, search.as_ref()),
+                size,
+                mpb,
+            );
+            graph.train_batch_parallel(&batch, mpb);
+
+            if let Some(output) = &args.output {
+                graph.to_file(output)?;
+                println!(
+                    "Checkpoint: {} of {} samples written to {}",
+                    graph.samples(),
+                    target,
+                    output
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads every input graph, merges them in order and writes the result.
+///
+/// The inputs are loaded with the node mapping recorded in the first of them.
+fn merge<G: TrainedGame>(args: &MergeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match read_header(&args.inputs[0])?.mapping {
+        Mapping::Exact => merge_graphs::<G, Exact>(args),
+        Mapping::Canonical => merge_graphs::<G, G::Symmetric>(args),
+    }
+}
+
+/// Merges the input graphs, which were trained with node mapping `M`.
+fn merge_graphs<G, M>(args: &MergeArgs) -> Result<(), Box<dyn std::error::Error>>
+where
+    G: Game + GraphNode + GraphPosition,
+    M: NodeMapping<G> + GraphMapping,
+{
+    let mut merged = MonteCarloGraph::<G, M>::from_file(&args.inputs[0])?;
+    for input in &args.inputs[1..] {
+        let graph = MonteCarloGraph::<G, M>::from_file(input)?;
+        merged.merge(&graph)?;
+        println!("Merged {}", input);
+    }
+
+    merged.to_file(&args.output)?;
+    println!(
+        "Wrote {} nodes from {} graphs to {}",
+        merged.nodes().len(),
+        args.inputs.len(),
+        args.output
+    );
+    Ok(())
+}
+
+/// Plays `moves` from `root`, the position the graph is rooted at.
+fn position<G: Game>(root: G, moves: &[String]) -> Result<G, Box<dyn std::error::Error>> {
+    let mut state = root;
+    for mv in moves {
+        let parsed = mv
+            .parse::<G::MoveType>()
+            .map_err(|_| format!("Invalid move {}", mv))?;
+        state.play(parsed, state.get_current_player())?;
+    }
+    Ok(state)
+}
+
+/// Writes the part of a graph selected by the arguments as DOT or GraphML.
+///
+/// The graph is loaded with the node mapping recorded in its header.
+fn export<G: TrainedGame>(args: &ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match read_header(&args.input)?.mapping {
+        Mapping::Exact => export_graph(&MonteCarloGraph::<G>::from_file(&args.input)?, args),
+        Mapping::Canonical => export_graph(
+            &MonteCarloGraph::<G, G::Symmetric>::from_file(&args.input)?,
+            args,
+        ),
+    }
+}
+
+/// Writes the part of `graph` selected by the arguments.
+fn export_graph<G, M>(
+    graph: &MonteCarloGraph<G, M>,
+    args: &ExportArgs,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    G: Game,
+    M: NodeMapping<G>,
+{
+    let start = M::node(&position(graph.root(), &args.moves)?);
+    if !graph.contains_node(&start) {
+        return Err(format!("Position after {:?} is not in the graph", args.moves).into());
+    }
+
+    let mut selection = GraphExport::new(graph)
+        .from_position(start)
+        .with_min_visits(args.min_visits);
+    if let Some(max_depth) = args.max_depth {
+        selection = selection.with_max_depth(max_depth);
+    }
+
+    let output = match args.format {
+        ExportFormat::Dot => selection.to_dot(),
+        ExportFormat::Graphml => selection.to_graphml(),
+    };
+    std::fs::write(&args.output, output)?;
+
+    let (nodes, edges) = selection.select();
+    println!(
+        "Wrote {} nodes and {} edges to {}",
+        nodes.len(),
+        edges.len(),
+        args.output
+    );
+    Ok(())
+}
 
-    let _ = games_rs::agents::train::play_batch::<ConnectFour>(
-        a1_agent.as_ref(),
-        a2_agent.as_ref(),
-        args.num_samples,
-        mpb.as_ref(),
+/// Prints a report on the position selected by the arguments.
+///
+/// The graph is loaded with the node mapping recorded in its header.
+fn analyze<G: TrainedGame>(
+    args: &AnalyzeArgs,
+    reference: Reference<G>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match read_header(&args.input)?.mapping {
+        Mapping::Exact => analyze_graph(
+            &MonteCarloGraph::<G>::from_file(&args.input)?,
+            args,
+            reference,
+        ),
+        Mapping::Canonical => analyze_graph(
+            &MonteCarloGraph::<G, G::Symmetric>::from_file(&args.input)?,
+            args,
+            reference,
+        ),
+    }
+}
+
+/// Prints a report on the position of `graph` selected by the arguments.
+fn analyze_graph<G, M>(
+    graph: &MonteCarloGraph<G, M>,
+    args: &AnalyzeArgs,
+    reference: Reference<G>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    G: Game,
+    M: NodeMapping<G>,
+{
+    let state = position(graph.root(), &args.moves)?;
+
+    println!("Position after [{}]:", args.moves.join(","));
+    println!("{}", state);
+    println!("Visits: {}", graph.visits(&M::node(&state)));
+    if let Some(value) = graph.position_value(&state) {
+        println!("Value for the player to move: {:.3}", value);
+    }
+
+    let line = graph.principal_variation(&state, args.pv_length);
+    println!(
+        "Principal variation: {}",
+        line.iter()
+            .map(|mv| format!("{:?}", mv))
+            .collect::<Vec<_>>()
+            .join(" ")
     );
 
-    let batch = games_rs::agents::train::play_batch_parallel::<ConnectFour, _, _>(
-        || Box::new(RandomAgent::<ConnectFour>::new()),
-        || Box::new(RandomAgent::<ConnectFour>::new()),
-        args.num_samples,
-        mpb.as_ref(),
+    let rate = |rate: Rate| {
+        format!(
+            "{:5.1}% [{:5.1}, {:5.1}]",
+            100.0 * rate.estimate,
+            100.0 * rate.lower,
+            100.0 * rate.upper
+        )
+    };
+    println!(
+        "{:<8}{:>8}  {:<22}{:<22}{:<22}",
+        "Move", "Visits", "Win", "Draw", "Loss"
     );
+    for stats in graph.top_moves(&state, args.top) {
+        println!(
+            "{:<8}{:>8}  {:<22}{:<22}{:<22}",
+            format!("{:?}", stats.mv),
+            stats.stats.simulations(),
+            rate(stats.win),
+            rate(stats.draw),
+            rate(stats.loss)
+        );
+    }
 
-    for agent_type in &args.agents {
-        // println!("Training agent: {:?}", agent_type);
-        match agent_type {
-            AgentType::MCGS => {
-                let mut mcgs_agent = MonteCarloGraph::<ConnectFour>::new();
+    if args.disagreements == 0 {
+        return Ok(());
+    }
+    match reference {
+        Reference::Values(value) => {
+            let found = graph.disagreements(args.min_visits, args.threshold, value);
+            println!(
+                "{} positions disagree with the reference value",
+                found.len()
+            );
+            for disagreement in found.iter().take(args.disagreements) {
+                println!(
+                    "{}Visits: {}, graph: {:.3}, reference: {:.3}\n",
+                    disagreement.position,
+                    disagreement.visits,
+                    disagreement.graph_value,
+                    disagreement.reference_value
+                );
+            }
+        }
+        Reference::Moves(agent) => {
+            let found = graph.move_disagreements(args.min_visits, args.threshold, |state| {
+                Some(agent.get_move(state))
+            });
+            println!("{} positions disagree with the reference move", found.len());
+            for disagreement in found.iter().take(args.disagreements) {
+                println!(
+                    "{}Visits: {}, graph: {:?}, reference: {:?}, regret: {:.3}\n",
+                    disagreement.position,
+                    disagreement.visits,
+                    disagreement.graph_move,
+                    disagreement.reference_move,
+                    disagreement.regret
+                );
+            }
+        }
+    }
+    Ok(())
+}
 
-                mcgs_agent.train_batch(&batch, mpb.as_ref());
+pub fn main() {
+    match Cli::parse().command {
+        Command::Train(args) => {
+            println!("{:?}", args);
+            let result = match args.game {
+                GameType::ConnectFour => train::<ConnectFour>(&args),
+                GameType::UltimateTTT => train::<UltimateTTT>(&args),
+                GameType::Rummy => train::<Rummy>(&args),
+                GameType::GinRummyMatch => train::<GinRummyMatch>(&args),
+            };
+            if let Err(err) = result {
+                eprintln!("Training failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::Merge(args) => {
+            let result = match args.game {
+                GameType::ConnectFour => merge::<ConnectFour>(&args),
+                GameType::UltimateTTT => merge::<UltimateTTT>(&args),
+                GameType::Rummy => merge::<Rummy>(&args),
+                GameType::GinRummyMatch => merge::<GinRummyMatch>(&args),
+            };
+            if let Err(err) = result {
+                eprintln!("Merge failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::Export(args) => {
+            let result = match args.game {
+                GameType::ConnectFour => export::<ConnectFour>(&args),
+                GameType::UltimateTTT => export::<UltimateTTT>(&args),
+                GameType::Rummy => export::<Rummy>(&args),
+                GameType::GinRummyMatch => export::<GinRummyMatch>(&args),
+            };
+            if let Err(err) = result {
+                eprintln!("Export failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        Command::Analyze(args) => {
+            let result = match args.game {
+                GameType::ConnectFour => {
+                    let solver = ConnectFourSolver::new();
+                    let solve = |state: &ConnectFour| {
+                        (state.move_count() >= args.solve_from)
+                            .then(|| solver.solve(state).expected_score())
+                    };
+                    analyze::<ConnectFour>(&args, Reference::Values(&solve))
+                }
+                GameType::UltimateTTT => {
+                    let agent = MinimaxAgent::new(2, NaiveScorer::<UltimateTTT>::new());
+                    analyze::<UltimateTTT>(&args, Reference::Moves(&agent))
+                }
+                GameType::Rummy => {
+                    let agent = InformationSetMcts::new(SearchBudget::Iterations(200));
+                    analyze::<Rummy>(&args, Reference::Moves(&agent))
+                }
+                GameType::GinRummyMatch => {
+                    let agent =
+                        MonteCarloTreeSearch::<GinRummyMatch>::new(SearchBudget::Iterations(200));
+                    analyze::<GinRummyMatch>(&args, Reference::Moves(&agent))
+                }
+            };
+            if let Err(err) = result {
+                eprintln!("Analysis failed: {}", err);
+                std::process::exit(1);
             }
         }
     }
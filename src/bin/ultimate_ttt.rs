@@ -1,7 +1,11 @@
 use clap::Parser;
 use games_rs::{
-    GameStatus, PlayThrough,
-    agents::{Agent, MinimaxAgent, PlayerAgent, RandomAgent, scorer::naive_scorer::NaiveScorer},
+    GameStatus,
+    agents::{
+        Agent, MinimaxAgent, PlayerAgent, RandomAgent,
+        mcts::{MonteCarloTreeSearch, SearchBudget},
+        scorer::naive_scorer::NaiveScorer,
+    },
     ultimate_ttt::UltimateTTT,
 };
 
@@ -10,16 +14,18 @@ type G = UltimateTTT;
 #[derive(clap::ValueEnum, Clone, Debug)]
 enum AvailableAgents {
     Minimax,
+    Mcts,
     Player,
     Random,
 }
 
-impl ToString for AvailableAgents {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for AvailableAgents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AvailableAgents::Minimax => "Minimax".to_string(),
-            AvailableAgents::Player => "Player".to_string(),
-            AvailableAgents::Random => "Random".to_string(),
+            AvailableAgents::Minimax => write!(f, "Minimax"),
+            AvailableAgents::Mcts => write!(f, "MCTS"),
+            AvailableAgents::Player => write!(f, "Player"),
+            AvailableAgents::Random => write!(f, "Random"),
         }
     }
 }
@@ -38,6 +44,9 @@ fn main() {
             let scorer = NaiveScorer::<G>::new();
             MinimaxAgent::<G, _>::new(4, scorer)
         }),
+        AvailableAgents::Mcts => Box::new(MonteCarloTreeSearch::<G>::new(SearchBudget::Time(
+            std::time::Duration::from_secs(1),
+        ))),
         AvailableAgents::Player => Box::new(PlayerAgent::<G>::new(1)),
         AvailableAgents::Random => Box::new(RandomAgent::<G>::new()),
     };
@@ -47,6 +56,9 @@ fn main() {
             let scorer = NaiveScorer::<G>::new();
             MinimaxAgent::<G, _>::new(4, scorer)
         }),
+        AvailableAgents::Mcts => Box::new(MonteCarloTreeSearch::<G>::new(SearchBudget::Time(
+            std::time::Duration::from_secs(1),
+        ))),
         AvailableAgents::Player => Box::new(PlayerAgent::<G>::new(2)),
         AvailableAgents::Random => Box::new(RandomAgent::<G>::new()),
     };
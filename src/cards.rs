@@ -214,6 +214,23 @@ impl Card {
     pub fn rank(&self) -> &Rank {
         &self.rank
     }
+
+    /// Returns a dense index for the card, in `0..52` for standard cards and 52 for jokers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use games_rs::cards::{Card, Suit, Rank};
+    ///
+    /// assert_eq!(Card::new(Suit::Hearts, Rank::Two).index(), 0);
+    /// assert_eq!(Card::new(Suit::Spades, Rank::Ace).index(), 51);
+    /// ```
+    pub fn index(&self) -> usize {
+        match (self.suit, self.rank) {
+            (Suit::Joker, _) | (_, Rank::Joker) => 52,
+            (suit, rank) => suit as usize * 13 + rank as usize,
+        }
+    }
 }
 
 impl Default for Card {
@@ -315,14 +332,28 @@ impl Deck {
 
     /// Randomly shuffles the deck using a cryptographically secure RNG.
     pub fn shuffle(&mut self) {
+        self.shuffle_with(&mut rand::rng());
+    }
+
+    /// Shuffles the deck with `rng`, so a seeded RNG always gives the same order.
+    pub fn shuffle_with<R: rand::Rng + ?Sized>(&mut self, rng: &mut R) {
         use rand::seq::SliceRandom;
 
-        let mut rng = rand::rng();
         let mut cards_vec: ArrayVec<[Card; 52]> = self.cards.drain(..).collect();
-        cards_vec.shuffle(&mut rng);
+        cards_vec.shuffle(rng);
         self.cards = ArrayVec::from(cards_vec);
     }
 
+    /// Returns the top card of the deck (the next card to be drawn) without removing it.
+    pub fn top(&self) -> Option<&Card> {
+        self.cards.last()
+    }
+
+    /// Iterates over the cards from the bottom of the deck to the top.
+    pub fn iter(&self) -> impl Iterator<Item = &Card> {
+        self.cards.iter()
+    }
+
     /// Draws a card from the top of the deck, returning `None` if the deck is empty.
     pub fn draw(&mut self) -> Option<Card> {
         self.cards.pop()
@@ -1,2 +1,3 @@
 pub mod defaults;
 pub mod filesystem;
+pub mod zobrist;
@@ -0,0 +1,29 @@
+//! Deterministic key tables for Zobrist hashing.
+//!
+//! Tables are generated at compile time from a fixed seed so that position keys are
+//! stable across runs and can be stored alongside trained data.
+
+/// Builds a table of `N` pseudo-random 64-bit keys using the SplitMix64 generator.
+///
+/// # Examples
+/// ```
+/// use games_rs::common::zobrist::keys;
+///
+/// const KEYS: [u64; 4] = keys(42);
+/// assert_ne!(KEYS[0], KEYS[1]);
+/// assert_eq!(KEYS, keys::<4>(42));
+/// ```
+pub const fn keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut table = [0u64; N];
+    let mut state = seed;
+    let mut i = 0;
+    while i < N {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
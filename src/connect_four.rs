@@ -8,9 +8,12 @@ use core::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Game, GameStatus};
+use crate::{Game, GameStatus, Symmetric, common::zobrist};
 use derive_aliases::derive;
 
+/// Zobrist keys indexed by `(token - 1) * 42 + row * 7 + col`.
+static CELL_KEYS: [u64; 84] = zobrist::keys(0xC0FF_EE00_0000_0004);
+
 /// Represents a token in the Connect Four game.
 ///
 /// Tokens can be empty, red (player 1), or yellow (player 2).
@@ -44,29 +47,127 @@ impl Into<u8> for Token {
     }
 }
 
+/// Bits of a single column: six playable rows plus an always-empty sentinel row.
+const COLUMN_MASK: u64 = 0b111_1111;
+
+/// Bits of the bottom row of every column.
+pub(crate) const BOTTOM_MASK: u64 = 0x0408_1020_4081;
+
+/// Bits of every playable cell.
+pub(crate) const BOARD_MASK: u64 = BOTTOM_MASK * 0b11_1111;
+
+/// Preferred move order for searches: central columns take part in the most lines.
+pub(crate) const MOVE_ORDER: [usize; 7] = [3, 2, 4, 1, 5, 0, 6];
+
 /// The Connect Four game board.
 ///
 /// A 6-row by 7-column grid where tokens drop to the lowest available position
 /// in each column. The board is indexed with row 0 at the bottom.
+///
+/// Internally each player's tokens are stored as a bitboard with bit `col * 7 + row`.
+/// The seventh bit of every column is a sentinel that is never set, which keeps lines
+/// from wrapping between columns when bitboards are shifted.
 #[derive(..StdTraits, Serialize, Deserialize)]
 pub struct ConnectFour {
-    grid: [[Token; 7]; 6],
+    /// Bitboards of red and yellow tokens.
+    tokens: [u64; 2],
+    /// Number of tokens in each column.
+    heights: [u8; 7],
+    /// Number of tokens on the board.
+    moves: u8,
+    /// Zobrist key of the tokens, updated incrementally.
+    hash: u64,
 }
 
 impl ConnectFour {
     /// Creates a new Connect Four game with an empty board.
     pub fn new() -> Self {
         ConnectFour {
-            grid: [[Token::Empty; 7]; 6],
+            tokens: [0; 2],
+            heights: [0; 7],
+            moves: 0,
+            hash: 0,
+        }
+    }
+
+    /// Returns the Zobrist key for `token` occupying the given cell.
+    fn cell_key(row: usize, col: usize, token: Token) -> u64 {
+        match token {
+            Token::Empty => 0,
+            token => CELL_KEYS[(Into::<u8>::into(token) as usize - 1) * 42 + row * 7 + col],
+        }
+    }
+
+    /// Computes the Zobrist key of the grid from scratch.
+    fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for row in 0..6 {
+            for col in 0..7 {
+                hash ^= Self::cell_key(row, col, self.get_token(row, col));
+            }
         }
+        hash
     }
 
-    /// Returns a reference to the current grid state.
+    /// Returns the bit for the given cell.
+    #[inline]
+    fn cell_bit(row: usize, col: usize) -> u64 {
+        1 << (col * 7 + row)
+    }
+
+    /// Returns `true` if the bitboard contains four tokens in a line.
+    #[inline]
+    pub(crate) fn has_four(bitboard: u64) -> bool {
+        // Vertical, horizontal, and the two diagonals
+        [1, 7, 6, 8].iter().any(|&shift| {
+            let pairs = bitboard & (bitboard >> shift);
+            pairs & (pairs >> (2 * shift)) != 0
+        })
+    }
+
+    /// Returns the token occupying the given cell.
+    pub fn get_token(&self, row: usize, col: usize) -> Token {
+        let bit = Self::cell_bit(row, col);
+        if self.tokens[0] & bit != 0 {
+            Token::Red
+        } else if self.tokens[1] & bit != 0 {
+            Token::Yellow
+        } else {
+            Token::Empty
+        }
+    }
+
+    /// Returns a copy of the current grid state.
     ///
     /// # Returns
     /// A 2D array representing the grid, with rows and columns.
-    pub fn get_grid(&self) -> &[[Token; 7]; 6] {
-        &self.grid
+    pub fn get_grid(&self) -> [[Token; 7]; 6] {
+        let mut grid = [[Token::Empty; 7]; 6];
+        for (row, cells) in grid.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                *cell = self.get_token(row, col);
+            }
+        }
+        grid
+    }
+
+    /// Returns the bitboard of a player's tokens.
+    pub fn get_bitboard(&self, token: Token) -> u64 {
+        match token {
+            Token::Red => self.tokens[0],
+            Token::Yellow => self.tokens[1],
+            Token::Empty => !(self.tokens[0] | self.tokens[1]) & BOARD_MASK,
+        }
+    }
+
+    /// Returns the number of tokens played so far.
+    pub fn move_count(&self) -> usize {
+        self.moves as usize
+    }
+
+    /// Returns the number of tokens in a column.
+    pub fn column_height(&self, column: usize) -> usize {
+        self.heights[column] as usize
     }
 
     /// Checks if a move to the specified column is valid.
@@ -80,10 +181,37 @@ impl ConnectFour {
     /// # Returns
     /// `true` if the move is valid, `false` otherwise.
     pub fn is_valid_move(&self, column: usize) -> bool {
-        if column >= 7 {
-            return false;
-        }
-        self.grid[5][column] == Token::Empty
+        column < 7 && self.heights[column] < 6
+    }
+
+    /// Returns `true` if dropping a token for the current player in `column` wins the game.
+    ///
+    /// The column must be a valid move.
+    pub fn is_winning_move(&self, column: usize) -> bool {
+        let current = self.tokens[(self.moves % 2) as usize];
+        let bit = Self::cell_bit(self.heights[column] as usize, column);
+        Self::has_four(current | bit)
+    }
+
+    /// Returns the available moves ordered for search.
+    ///
+    /// Immediately winning moves come first, followed by the remaining columns from the
+    /// centre outwards. Searches that try good moves first prune far more of the tree.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::connect_four::ConnectFour;
+    ///
+    /// assert_eq!(ConnectFour::new().get_ordered_moves(), vec![3, 2, 4, 1, 5, 0, 6]);
+    /// ```
+    pub fn get_ordered_moves(&self) -> Vec<usize> {
+        let mut moves = MOVE_ORDER
+            .iter()
+            .copied()
+            .filter(|&col| self.is_valid_move(col))
+            .collect::<Vec<_>>();
+        moves.sort_by_key(|&col| !self.is_winning_move(col));
+        moves
     }
 }
 
@@ -91,25 +219,14 @@ impl Game for ConnectFour {
     const name: &'static str = "Connect Four";
     type MoveType = usize;
     type PlayerType = Token;
+    /// The column the token was dropped into.
+    type MoveRecord = usize;
 
     /// Returns the current player (1 for Red, 2 for Yellow).
     ///
-    /// Determines the current player by counting tokens. Player 1 (Red) goes first.
+    /// Determines the current player from the move count. Player 1 (Red) goes first.
     fn get_current_player(&self) -> Token {
-        let mut red_count = 0;
-        let mut yellow_count = 0;
-
-        for row in 0..6 {
-            for col in 0..7 {
-                match self.grid[row][col] {
-                    Token::Red => red_count += 1,
-                    Token::Yellow => yellow_count += 1,
-                    Token::Empty => {}
-                }
-            }
-        }
-
-        if red_count <= yellow_count {
+        if self.moves % 2 == 0 {
             Token::Red // Player Red's turn
         } else {
             Token::Yellow // Player Yellow's turn
@@ -118,15 +235,9 @@ impl Game for ConnectFour {
 
     /// Returns all columns where a token can be dropped.
     ///
-    /// A column is available if its top row (row 5) is empty.
+    /// A column is available if it holds fewer than six tokens.
     fn get_available_moves(&self) -> Vec<Self::MoveType> {
-        let mut moves = Vec::new();
-        for col in 0..7 {
-            if self.grid[5][col] == Token::Empty {
-                moves.push(col);
-            }
-        }
-        moves
+        (0..7).filter(|&col| self.heights[col] < 6).collect()
     }
 
     /// Drops a token into the specified column.
@@ -141,19 +252,33 @@ impl Game for ConnectFour {
     /// Returns an error if:
     /// - The column index is out of bounds (>= 7)
     /// - The column is full (top row is not empty)
-    fn play(&mut self, mv: Self::MoveType, token: Token) -> Result<(), String> {
-        if mv >= 7 || self.grid[5][mv] != Token::Empty {
+    /// - The token is empty
+    fn make_move(&mut self, mv: Self::MoveType, token: Token) -> Result<usize, String> {
+        if !self.is_valid_move(mv) || token == Token::Empty {
             return Err("Invalid move".to_string());
         }
 
-        for row in 0..6 {
-            if self.grid[row][mv] == Token::Empty {
-                self.grid[row][mv] = token;
-                break;
-            }
+        let row = self.heights[mv] as usize;
+        self.tokens[Into::<u8>::into(token) as usize - 1] |= Self::cell_bit(row, mv);
+        self.heights[mv] += 1;
+        self.moves += 1;
+        self.hash ^= Self::cell_key(row, mv, token);
+
+        Ok(mv)
+    }
+
+    /// Removes the topmost token from the recorded column.
+    fn undo(&mut self, column: usize) {
+        if self.heights[column] == 0 {
+            return;
         }
 
-        Ok(())
+        let row = self.heights[column] as usize - 1;
+        let token = self.get_token(row, column);
+        self.hash ^= Self::cell_key(row, column, token);
+        self.tokens[Into::<u8>::into(token) as usize - 1] &= !Self::cell_bit(row, column);
+        self.heights[column] -= 1;
+        self.moves -= 1;
     }
 
     /// Returns the current status of the game.
@@ -161,68 +286,70 @@ impl Game for ConnectFour {
     /// Checks for four connected tokens in any direction (horizontal, vertical, or diagonal).
     /// Returns `BoardStatus::Draw` if the board is full with no winner.
     fn get_status(&self) -> GameStatus {
-        let mut status = GameStatus::InProgress;
-
-        // Check columns for 4 in a row
-        for col in 0..7 {
-            for row in 0..3 {
-                if self.grid[row][col] != Token::Empty
-                    && self.grid[row][col] == self.grid[row + 1][col]
-                    && self.grid[row][col] == self.grid[row + 2][col]
-                    && self.grid[row][col] == self.grid[row + 3][col]
-                {
-                    return GameStatus::Win(self.grid[row][col].into());
-                }
-            }
+        if Self::has_four(self.tokens[0]) {
+            GameStatus::Win(Token::Red.into())
+        } else if Self::has_four(self.tokens[1]) {
+            GameStatus::Win(Token::Yellow.into())
+        } else if self.moves == 42 {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
         }
+    }
 
-        // Check rows for 4 in a row
-        for row in 0..6 {
-            for col in 0..4 {
-                if self.grid[row][col] != Token::Empty
-                    && self.grid[row][col] == self.grid[row][col + 1]
-                    && self.grid[row][col] == self.grid[row][col + 2]
-                    && self.grid[row][col] == self.grid[row][col + 3]
-                {
-                    return GameStatus::Win(self.grid[row][col].into());
-                }
-            }
-        }
+    fn hash_key(&self) -> u64 {
+        self.hash
+    }
+}
 
-        // Check diagonals for 4 in a row
-        for row in 0..3 {
-            for col in 0..4 {
-                if self.grid[row][col] != Token::Empty
-                    && self.grid[row][col] == self.grid[row + 1][col + 1]
-                    && self.grid[row][col] == self.grid[row + 2][col + 2]
-                    && self.grid[row][col] == self.grid[row + 3][col + 3]
-                {
-                    return GameStatus::Win(self.grid[row][col].into());
-                }
-            }
-        }
-        for row in 0..3 {
-            for col in 3..7 {
-                if self.grid[row][col] != Token::Empty
-                    && self.grid[row][col] == self.grid[row + 1][col - 1]
-                    && self.grid[row][col] == self.grid[row + 2][col - 2]
-                    && self.grid[row][col] == self.grid[row + 3][col - 3]
-                {
-                    return GameStatus::Win(self.grid[row][col].into());
-                }
+/// Symmetries of the Connect Four board.
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub enum Symmetry {
+    /// Leaves the board unchanged
+    Identity,
+    /// Reflects the board left to right
+    Mirror,
+}
+
+impl Symmetric for ConnectFour {
+    type Transform = Symmetry;
+
+    fn symmetries() -> &'static [Symmetry] {
+        &[Symmetry::Identity, Symmetry::Mirror]
+    }
+
+    fn inverse(transform: Symmetry) -> Symmetry {
+        transform
+    }
+
+    fn transform(&self, transform: Symmetry) -> Self {
+        match transform {
+            Symmetry::Identity => *self,
+            Symmetry::Mirror => {
+                let mirror = |bitboard: u64| {
+                    (0..7).fold(0, |mirrored, col| {
+                        mirrored | ((bitboard >> (col * 7)) & COLUMN_MASK) << ((6 - col) * 7)
+                    })
+                };
+                let mut heights = self.heights;
+                heights.reverse();
+                let mut game = ConnectFour {
+                    tokens: [mirror(self.tokens[0]), mirror(self.tokens[1])],
+                    heights,
+                    moves: self.moves,
+                    hash: 0,
+                };
+                game.hash = game.compute_hash();
+                game
             }
         }
+    }
 
-        // Check for draw
-        if self
-            .grid
-            .iter()
-            .all(|row| row.iter().all(|&cell| cell != Token::Empty))
-        {
-            status = GameStatus::Draw;
+    fn transform_move(mv: usize, transform: Symmetry) -> usize {
+        match transform {
+            Symmetry::Identity => mv,
+            Symmetry::Mirror => 6 - mv,
         }
-
-        status
     }
 }
 
@@ -232,28 +359,47 @@ impl Default for ConnectFour {
     }
 }
 
-impl fmt::Debug for ConnectFour {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for row in (0..6).rev() {
-            for col in 0..7 {
-                let symbol = match self.grid[row][col] {
-                    Token::Empty => '.',
-                    Token::Red => 'R',
-                    Token::Yellow => 'Y',
+/// Layout of [`ConnectFour`] in graph files written before the format had a header.
+///
+/// Kept frozen so those files can still be migrated; see
+/// [`graph_file`](crate::agents::graph_file).
+#[derive(..StdTraits, Debug, Deserialize)]
+pub struct LegacyConnectFour {
+    grid: [[Token; 7]; 6],
+}
+
+impl From<LegacyConnectFour> for ConnectFour {
+    fn from(value: LegacyConnectFour) -> Self {
+        let mut game = ConnectFour::new();
+        for (row, tokens) in value.grid.iter().enumerate() {
+            for (col, &token) in tokens.iter().enumerate() {
+                let player = match token {
+                    Token::Red => 0,
+                    Token::Yellow => 1,
+                    Token::Empty => continue,
                 };
-                write!(f, "{} ", symbol)?;
+                game.tokens[player] |= Self::cell_bit(row, col);
+                game.heights[col] += 1;
+                game.moves += 1;
             }
-            writeln!(f)?;
         }
-        Ok(())
+        game.hash = game.compute_hash();
+        game
+    }
+}
+
+impl fmt::Debug for ConnectFour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
     }
 }
 
 impl fmt::Display for ConnectFour {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let grid = self.get_grid();
         for row in (0..6).rev() {
             for col in 0..7 {
-                let symbol = match self.grid[row][col] {
+                let symbol = match grid[row][col] {
                     Token::Empty => '.',
                     Token::Red => 'R',
                     Token::Yellow => 'Y',
@@ -265,3 +411,106 @@ impl fmt::Display for ConnectFour {
         Ok(())
     }
 }
+
+mod test {
+    #[test]
+    fn test_hash_key_has_no_collisions() {
+        use super::ConnectFour;
+        use crate::{Game, GameStatus};
+        use std::collections::HashMap;
+
+        fn visit(game: &mut ConnectFour, depth: usize, seen: &mut HashMap<u64, ConnectFour>) {
+            assert_eq!(game.hash_key(), game.compute_hash());
+            if let Some(existing) = seen.insert(game.hash_key(), *game) {
+                assert_eq!(existing, *game, "hash collision");
+            }
+            if depth == 0 || game.get_status() != GameStatus::InProgress {
+                return;
+            }
+            let player = game.get_current_player();
+            for mv in game.get_available_moves() {
+                let record = game.make_move(mv, player).unwrap();
+                visit(game, depth - 1, seen);
+                game.undo(record);
+            }
+        }
+
+        let mut seen = HashMap::new();
+        let mut game = ConnectFour::new();
+        visit(&mut game, 6, &mut seen);
+
+        assert_eq!(game.hash_key(), 0);
+        assert!(seen.len() > 10_000);
+    }
+
+    #[test]
+    fn test_mirrored_positions_share_canonical_form() {
+        use super::{ConnectFour, Symmetry, Token};
+        use crate::{Game, Symmetric};
+
+        let mut game = ConnectFour::new();
+        let mut mirrored = ConnectFour::new();
+        for (mv, token) in [(0, Token::Red), (3, Token::Yellow), (5, Token::Red)] {
+            game.play(mv, token).unwrap();
+            mirrored
+                .play(ConnectFour::transform_move(mv, Symmetry::Mirror), token)
+                .unwrap();
+        }
+
+        assert_ne!(game, mirrored);
+        assert_eq!(game.transform(Symmetry::Mirror), mirrored);
+        assert_eq!(game.canonicalize().0, mirrored.canonicalize().0);
+
+        let (canonical, transform) = game.canonicalize();
+        assert_eq!(canonical.transform(ConnectFour::inverse(transform)), game);
+    }
+
+    #[test]
+    fn test_status_matches_grid_scan() {
+        use super::{ConnectFour, Token};
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+
+        // Reference implementation scanning every line of the grid
+        fn scan(grid: &[[Token; 7]; 6]) -> Option<Token> {
+            let directions = [(0i32, 1i32), (1, 0), (1, 1), (1, -1)];
+            for row in 0..6i32 {
+                for col in 0..7i32 {
+                    let token = grid[row as usize][col as usize];
+                    if token == Token::Empty {
+                        continue;
+                    }
+                    for (dr, dc) in directions {
+                        if (1..4).all(|k| {
+                            let (r, c) = (row + dr * k, col + dc * k);
+                            (0..6).contains(&r)
+                                && (0..7).contains(&c)
+                                && grid[r as usize][c as usize] == token
+                        }) {
+                            return Some(token);
+                        }
+                    }
+                }
+            }
+            None
+        }
+
+        let mut rng = rand::rng();
+        for _ in 0..500 {
+            let mut game = ConnectFour::new();
+            while game.get_status() == GameStatus::InProgress {
+                let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+                let winning = game.is_winning_move(mv);
+                game.play(mv, game.get_current_player()).unwrap();
+
+                let expected = match scan(&game.get_grid()) {
+                    Some(token) => GameStatus::Win(token.into()),
+                    None if game.move_count() == 42 => GameStatus::Draw,
+                    None => GameStatus::InProgress,
+                };
+                assert_eq!(game.get_status(), expected);
+                assert_eq!(winning, matches!(expected, GameStatus::Win(_)));
+            }
+        }
+    }
+}
@@ -28,7 +28,7 @@ mod derive_alias {
 
 use derive_aliases::derive;
 
-#[derive(..StdTraits)]
+#[derive(..StdTraits, Debug)]
 pub enum GameStatus {
     InProgress,
     Win(u8),
@@ -74,31 +74,154 @@ pub trait Game:
         + Send
         + Sync;
 
+    /// Token returned by [`Game::make_move`] holding what is needed to revert that move.
+    type MoveRecord: Copy + Clone + Debug + Send + Sync;
+
     fn get_current_player(&self) -> Self::PlayerType;
 
     fn get_available_moves(&self) -> Vec<Self::MoveType>;
 
-    fn play(&mut self, mv: Self::MoveType, player: Self::PlayerType) -> Result<(), String>;
+    /// Plays a move, discarding the undo record.
+    fn play(&mut self, mv: Self::MoveType, player: Self::PlayerType) -> Result<(), String> {
+        self.make_move(mv, player).map(|_| ())
+    }
+
+    /// Plays a move and returns a record that can be passed to [`Game::undo`].
+    ///
+    /// On error the game is left unchanged.
+    fn make_move(
+        &mut self,
+        mv: Self::MoveType,
+        player: Self::PlayerType,
+    ) -> Result<Self::MoveRecord, String>;
+
+    /// Reverts a move previously applied with [`Game::make_move`].
+    ///
+    /// Records must be undone in the reverse order in which they were made, which lets
+    /// searches walk the game tree in place instead of cloning the board for every child.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::Game;
+    /// use games_rs::connect_four::{ConnectFour, Token};
+    ///
+    /// let mut game = ConnectFour::new();
+    /// let record = game.make_move(3, Token::Red).unwrap();
+    /// assert_eq!(game.get_current_player(), Token::Yellow);
+    ///
+    /// game.undo(record);
+    /// assert_eq!(game, ConnectFour::new());
+    /// ```
+    fn undo(&mut self, record: Self::MoveRecord);
 
     fn get_status(&self) -> GameStatus;
 
+    /// Returns a 64-bit Zobrist key identifying the current position.
+    ///
+    /// Equal positions always share a key. Keys are maintained incrementally by
+    /// [`Game::make_move`] and [`Game::undo`], so this is a constant-time lookup.
+    fn hash_key(&self) -> u64;
+
     fn move_message(&self) -> &str {
         ""
     }
 }
 
-/// A recorded game sample containing the sequence of moves and final result.
+/// A game whose board has symmetries that map positions onto equivalent positions.
+///
+/// Equivalent positions have the same game-theoretic value, so searches and learned
+/// statistics can be shared between them by working on the canonical representative.
+pub trait Symmetric: Game {
+    /// An element of the game's symmetry group.
+    type Transform: Copy + Clone + Eq + Debug + Send + Sync + 'static;
+
+    /// Returns every symmetry of the board, starting with the identity.
+    fn symmetries() -> &'static [Self::Transform];
+
+    /// Returns the transform that undoes `transform`.
+    fn inverse(transform: Self::Transform) -> Self::Transform;
+
+    /// Applies a symmetry to the position.
+    fn transform(&self, transform: Self::Transform) -> Self;
+
+    /// Maps a move through a symmetry.
+    ///
+    /// Playing `mv` and then transforming gives the same position as transforming and
+    /// then playing `transform_move(mv, transform)`.
+    fn transform_move(mv: Self::MoveType, transform: Self::Transform) -> Self::MoveType;
+
+    /// Returns the canonical representative of the position's symmetry class together
+    /// with the transform that maps this position onto it.
+    ///
+    /// The canonical representative is the smallest transformed position under `Ord`.
+    fn canonicalize(&self) -> (Self, Self::Transform) {
+        Self::symmetries()
+            .iter()
+            .map(|&transform| (self.transform(transform), transform))
+            .min_by(|a, b| a.0.cmp(&b.0))
+            .unwrap()
+    }
+}
+
+/// A game in which players see only part of the state, such as the cards in their own
+/// hand.
+///
+/// Agents given the full game can read hidden information straight out of it; agents
+/// implementing [`agents::ObservingAgent`] are given an observation instead, through
+/// [`play_observed_game`].
+pub trait ImperfectInfoGame: Game {
+    /// Everything one player may see of the game.
+    type Observation: Clone + Debug + Send + Sync;
+
+    /// Returns what `player` may see of the current state.
+    fn observe(&self, player: Self::PlayerType) -> Self::Observation;
+
+    /// Returns the moves available to the observing player, empty if it is not their
+    /// turn.
+    fn observed_moves(observation: &Self::Observation) -> Vec<Self::MoveType>;
+}
+
+/// A recorded game sample containing the starting position, the sequence of moves and
+/// the final result.
 pub struct PlayThrough<G: Game> {
+    start: G,
     result: GameStatus,
     moves: Vec<(<G as Game>::PlayerType, <G as Game>::MoveType)>,
 }
 
 impl<G: Game> PlayThrough<G> {
+    /// Creates a sample of a game started from `G::default()`.
     pub fn new(
         result: GameStatus,
         moves: Vec<(<G as Game>::PlayerType, <G as Game>::MoveType)>,
     ) -> Self {
-        PlayThrough { result, moves }
+        PlayThrough {
+            start: G::default(),
+            result,
+            moves,
+        }
+    }
+
+    /// Sets the position the game started from, for games such as Rummy whose default
+    /// position is a random deal.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::{GameStatus, PlayThrough};
+    /// use games_rs::rummy::Rummy;
+    ///
+    /// let deal = Rummy::default();
+    /// let sample = PlayThrough::new(GameStatus::InProgress, Vec::new()).with_start(deal);
+    /// assert_eq!(*sample.get_start(), deal);
+    /// ```
+    pub fn with_start(mut self, start: G) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Returns the position the game started from.
+    pub fn get_start(&self) -> &G {
+        &self.start
     }
 
     /// Returns the result of the game.
@@ -134,17 +257,15 @@ where
             Vec<(<G as Game>::PlayerType, <G as Game>::MoveType)>,
         ),
     ) -> Self {
-        PlayThrough {
-            result: value.0,
-            moves: value.1,
-        }
+        PlayThrough::new(value.0, value.1)
     }
 }
 
 /// Plays a single game between two agents and returns the playthrough.
 pub fn play_game<G: Game>(a1: &dyn agents::Agent<G>, a2: &dyn agents::Agent<G>) -> PlayThrough<G> {
     let mut game = G::default();
-    let mut playthrough: PlayThrough<G> = PlayThrough::new(GameStatus::InProgress, Vec::new());
+    let mut playthrough: PlayThrough<G> =
+        PlayThrough::new(GameStatus::InProgress, Vec::new()).with_start(game);
 
     loop {
         let current_player = game.get_current_player();
@@ -171,3 +292,47 @@ pub fn play_game<G: Game>(a1: &dyn agents::Agent<G>, a2: &dyn agents::Agent<G>)
         }
     }
 }
+
+/// Plays a single game between two agents that only see their own observations.
+///
+/// # Examples
+/// ```
+/// use games_rs::{GameStatus, play_observed_game};
+/// use games_rs::agents::RandomAgent;
+/// use games_rs::rummy::Rummy;
+///
+/// let agent = RandomAgent::<Rummy>::new();
+/// let playthrough = play_observed_game(&agent, &agent);
+/// assert_ne!(*playthrough.get_result(), GameStatus::InProgress);
+/// ```
+pub fn play_observed_game<G: ImperfectInfoGame>(
+    a1: &dyn agents::ObservingAgent<G>,
+    a2: &dyn agents::ObservingAgent<G>,
+) -> PlayThrough<G> {
+    let mut game = G::default();
+    let mut playthrough: PlayThrough<G> =
+        PlayThrough::new(GameStatus::InProgress, Vec::new()).with_start(game);
+
+    loop {
+        let current_player = game.get_current_player();
+        let observation = game.observe(current_player);
+
+        if G::observed_moves(&observation).is_empty() {
+            playthrough.set_result(game.get_status());
+            return playthrough;
+        }
+
+        let mv = if current_player == G::PlayerType::from(1) {
+            a1.get_observed_move(&observation)
+        } else {
+            a2.get_observed_move(&observation)
+        };
+        game.play(mv, current_player).unwrap();
+        playthrough.add_move(current_player, mv);
+
+        if game.get_status() != GameStatus::InProgress {
+            playthrough.set_result(game.get_status());
+            return playthrough;
+        }
+    }
+}
@@ -1,19 +1,95 @@
 use std::{fmt, str::FromStr};
 
 use crate::{
-    Game,
+    Game, ImperfectInfoGame,
     cards::{Card, Deck},
+    common::zobrist,
 };
 use derive_aliases::derive;
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 use serde::{Deserialize, Serialize};
 use tinyvec::ArrayVec;
 
+/// Zobrist keys for card locations, indexed by `location * 52 + card.index()`.
+static LOCATION_KEYS: [u64; 208] = zobrist::keys(0xC0FF_EE00_0000_0208);
+
+/// Zobrist keys for the card on top of the discard pile.
+static DISCARD_TOP_KEYS: [u64; 52] = zobrist::keys(0xC0FF_EE00_0000_0052);
+
+/// Zobrist key toggled while it is player 2's turn.
+static PLAYER2_KEY: [u64; 1] = zobrist::keys(0xC0FF_EE00_0000_0001);
+
+/// Zobrist key toggled once the hand has been ended by a knock.
+static HAND_OVER_KEY: [u64; 1] = zobrist::keys(0xC0FF_EE00_0000_0002);
+
+/// Multipliers mixing match scores, boxes and the dealer into a match key.
+static MATCH_KEYS: [u64; 5] = zobrist::keys(0xC0FF_EE00_0000_0005);
+
+/// Most deadwood a player may hold when knocking.
+pub const KNOCK_LIMIT: u8 = 10;
+
+/// Bonus for going gin, on top of the defender's deadwood.
+pub const GIN_BONUS: u8 = 25;
+
+/// Bonus for going gin with all 11 cards, on top of the defender's deadwood.
+pub const BIG_GIN_BONUS: u8 = 31;
+
+/// Bonus for the defender when their deadwood is no more than the knocker's.
+pub const UNDERCUT_BONUS: u8 = 25;
+
+/// Points ending a match.
+pub const MATCH_TARGET: u16 = 100;
+
+/// Bonus for winning a match.
+pub const GAME_BONUS: u16 = 100;
+
+/// Line or box bonus for every hand won in a match.
+pub const BOX_BONUS: u16 = 25;
+
+/// Where a card currently lies, used to select its Zobrist key.
+#[derive(..Copy)]
+enum Location {
+    Deck,
+    Discard,
+    Hand(Player),
+}
+
+impl Location {
+    fn key(self, card: &Card) -> u64 {
+        let location = match self {
+            Location::Deck => 0,
+            Location::Discard => 1,
+            Location::Hand(Player::Player1) => 2,
+            Location::Hand(Player::Player2) => 3,
+        };
+        LOCATION_KEYS[location * 52 + card.index()]
+    }
+}
+
 #[derive(..StdTraits, Debug, Serialize, Deserialize)]
 pub enum Player {
     Player1,
     Player2,
 }
 
+impl Player {
+    /// Returns the index of the player's hand.
+    fn seat(self) -> usize {
+        match self {
+            Player::Player1 => 0,
+            Player::Player2 => 1,
+        }
+    }
+
+    /// Returns the other player.
+    pub fn opponent(self) -> Player {
+        match self {
+            Player::Player1 => Player::Player2,
+            Player::Player2 => Player::Player1,
+        }
+    }
+}
+
 impl From<u8> for Player {
     fn from(value: u8) -> Self {
         match value {
@@ -38,7 +114,289 @@ pub enum Action {
     DrawFromDeck,
     DrawFromDiscard,
     Discard(Card),
+    /// Discards the card and ends the hand, going gin if no deadwood is left.
     Knock(Card),
+    /// Ends the hand with all 11 cards melded, without discarding.
+    BigGin,
+}
+
+/// How a hand was ended.
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub enum Declaration {
+    /// Knocked with at most [`KNOCK_LIMIT`] deadwood
+    Knock,
+    /// Knocked with no deadwood
+    Gin,
+    /// Melded all 11 cards without discarding
+    BigGin,
+}
+
+/// Outcome of a hand ended by a knock.
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub struct HandResult {
+    pub knocker: Player,
+    pub declaration: Declaration,
+    pub knocker_deadwood: u8,
+    /// Deadwood of the defender after laying off onto the knocker's melds
+    pub defender_deadwood: u8,
+    pub winner: Player,
+    /// Points won by the winner, including bonuses
+    pub points: u8,
+}
+
+impl HandResult {
+    /// Returns `true` if the defender won by undercutting the knocker.
+    pub fn is_undercut(&self) -> bool {
+        self.winner != self.knocker
+    }
+}
+
+/// Returns the deadwood value of a card: aces count 1, face cards 10.
+///
+/// # Examples
+/// ```
+/// use games_rs::cards::{Card, Rank, Suit};
+/// use games_rs::rummy::deadwood_value;
+///
+/// assert_eq!(deadwood_value(&Card::new(Suit::Hearts, Rank::Ace)), 1);
+/// assert_eq!(deadwood_value(&Card::new(Suit::Hearts, Rank::Seven)), 7);
+/// assert_eq!(deadwood_value(&Card::new(Suit::Hearts, Rank::King)), 10);
+/// ```
+pub fn deadwood_value(card: &Card) -> u8 {
+    let rank: u8 = (*card.rank()).into();
+    match rank {
+        14 => 1,
+        rank => rank.min(10),
+    }
+}
+
+/// Returns the position of a card in a run, with aces low.
+fn run_rank(card: &Card) -> u8 {
+    let rank: u8 = (*card.rank()).into();
+    if rank == 14 { 1 } else { rank }
+}
+
+/// Returns `true` if `card` can be added to `meld`.
+fn fits(meld: &[Card], card: &Card) -> bool {
+    if meld[0].rank() == meld[1].rank() {
+        return card.rank() == meld[0].rank();
+    }
+    let low = meld.iter().map(run_rank).min().unwrap();
+    let high = meld.iter().map(run_rank).max().unwrap();
+    card.suit() == meld[0].suit() && (run_rank(card) + 1 == low || run_rank(card) == high + 1)
+}
+
+/// Lays off cards of `deadwood` onto `melds`, returning the cards left over.
+///
+/// Adding a card to a meld never stops another card from fitting, so cards are laid off
+/// while any fits; only the choice of meld for a card that fits several is searched,
+/// keeping the arrangement that leaves the least deadwood.
+fn lay_off(melds: &[Vec<Card>], deadwood: Vec<Card>) -> Vec<Card> {
+    let Some((i, card)) = deadwood
+        .iter()
+        .enumerate()
+        .find(|(_, card)| melds.iter().any(|meld| fits(meld, card)))
+    else {
+        return deadwood;
+    };
+
+    let mut rest = deadwood.clone();
+    rest.remove(i);
+    melds
+        .iter()
+        .enumerate()
+        .filter(|(_, meld)| fits(meld, card))
+        .map(|(m, _)| {
+            let mut melds = melds.to_vec();
+            melds[m].push(*card);
+            lay_off(&melds, rest.clone())
+        })
+        .min_by_key(|left| left.iter().map(deadwood_value).sum::<u8>())
+        .unwrap()
+}
+
+/// Returns every set and run contained in `hand`, as masks over its indices.
+fn candidate_melds(hand: &[Card]) -> Vec<u16> {
+    let mut melds = Vec::new();
+    let mask_of = |cards: &[usize]| cards.iter().fold(0u16, |mask, i| mask | 1 << i);
+
+    let mut by_rank: Vec<Vec<usize>> = vec![Vec::new(); 15];
+    let mut by_suit: Vec<Vec<usize>> = vec![Vec::new(); 5];
+    for (i, card) in hand.iter().enumerate() {
+        by_rank[run_rank(card) as usize].push(i);
+        by_suit[*card.suit() as usize].push(i);
+    }
+
+    for cards in by_rank.iter().filter(|cards| cards.len() >= 3) {
+        melds.push(mask_of(cards));
+        if cards.len() == 4 {
+            for left_out in cards {
+                melds.push(mask_of(cards) & !(1 << left_out));
+            }
+        }
+    }
+
+    for cards in &mut by_suit {
+        cards.sort_by_key(|i| run_rank(&hand[*i]));
+        // Every window of three or more consecutive ranks is a run
+        for start in 0..cards.len() {
+            let mut end = start + 1;
+            while end < cards.len()
+                && run_rank(&hand[cards[end]]) == run_rank(&hand[cards[end - 1]]) + 1
+            {
+                end += 1;
+                if end - start >= 3 {
+                    melds.push(mask_of(&cards[start..end]));
+                }
+            }
+        }
+    }
+    melds
+}
+
+/// Computes the least deadwood of every subset of `hand`, indexed by bit mask.
+///
+/// Returns the deadwood values together with the meld chosen for each subset (0 if its
+/// lowest card is left as deadwood).
+fn least_deadwood(hand: &[Card]) -> (Vec<u8>, Vec<u16>) {
+    let n = hand.len();
+    let candidates = candidate_melds(hand);
+
+    // best[mask] is the least deadwood of the cards in mask, reached by melding
+    // choice[mask] (0 if the lowest card in mask is left as deadwood)
+    let mut best = vec![0u8; 1 << n];
+    let mut choice = vec![0u16; 1 << n];
+    for mask in 1..(1usize << n) {
+        let lowest = mask.trailing_zeros() as usize;
+        best[mask] = deadwood_value(&hand[lowest]) + best[mask & !(1 << lowest)];
+        for &meld in &candidates {
+            let meld_mask = meld as usize;
+            if meld_mask & (1 << lowest) != 0
+                && meld_mask & !mask == 0
+                && best[mask & !meld_mask] < best[mask]
+            {
+                best[mask] = best[mask & !meld_mask];
+                choice[mask] = meld;
+            }
+        }
+    }
+    (best, choice)
+}
+
+/// Returns the least deadwood a defender holding `hand` is left with after laying off
+/// onto the knocker's `melds`.
+///
+/// The defender picks their own melds knowing what they can lay off, so a card may be
+/// worth more laid off than kept in a meld. Every subset of the cards that could be
+/// laid off at all is tried; when it can be laid off entirely, the rest of the hand is
+/// arranged on its own.
+fn defender_deadwood(melds: &[Vec<Card>], hand: &[Card]) -> u8 {
+    let (best, _) = least_deadwood(hand);
+    let all = (1usize << hand.len()) - 1;
+
+    // Cards that fit the knocker's melds once extended by every other such card
+    let mut extended = melds.to_vec();
+    let mut layable = 0usize;
+    while let Some(i) = (0..hand.len())
+        .find(|&i| layable & 1 << i == 0 && extended.iter().any(|meld| fits(meld, &hand[i])))
+    {
+        layable |= 1 << i;
+        for meld in &mut extended {
+            if fits(meld, &hand[i]) {
+                meld.push(hand[i]);
+            }
+        }
+    }
+
+    let mut least = best[all];
+    let mut subset = layable;
+    while subset != 0 {
+        let cards = (0..hand.len())
+            .filter(|i| subset & 1 << i != 0)
+            .map(|i| hand[i])
+            .collect();
+        if best[all & !subset] < least && lay_off(melds, cards).is_empty() {
+            least = best[all & !subset];
+        }
+        subset = (subset - 1) & layable;
+    }
+    least
+}
+
+/// Finds the least-deadwood arrangement of `hand`, see [`Rummy::get_min_pt_melds`].
+fn arrange(hand: &[Card]) -> MeldArrangement {
+    let n = hand.len();
+    let (best, choice) = least_deadwood(hand);
+
+    let mut arrangement = MeldArrangement {
+        melds: Vec::new(),
+        deadwood: Vec::new(),
+        points: best[(1 << n) - 1],
+    };
+    let mut mask = (1usize << n) - 1;
+    while mask != 0 {
+        let lowest = mask.trailing_zeros() as usize;
+        match choice[mask] as usize {
+            0 => {
+                arrangement.deadwood.push(hand[lowest]);
+                mask &= !(1 << lowest);
+            }
+            meld => {
+                arrangement.melds.push(
+                    (0..n)
+                        .filter(|i| meld & (1 << i) != 0)
+                        .map(|i| hand[i])
+                        .collect(),
+                );
+                mask &= !meld;
+            }
+        }
+    }
+    arrangement
+}
+
+/// Returns the actions open to a player holding `hand` on their turn.
+fn hand_actions(hand: &[Card], can_take_discard: bool) -> Vec<Action> {
+    let mut moves = Vec::new();
+
+    // Drawing options
+    if hand.len() < 11 {
+        moves.push(Action::DrawFromDeck);
+        if can_take_discard {
+            moves.push(Action::DrawFromDiscard);
+        }
+
+        return moves;
+    }
+
+    // Options to discard
+    moves = hand.iter().map(|c| Action::Discard(*c)).collect();
+
+    // Options for knocking, from the deadwood left after each discard
+    let (best, _) = least_deadwood(hand);
+    let all = (1 << hand.len()) - 1;
+    for (i, card) in hand.iter().enumerate() {
+        if best[all & !(1 << i)] <= KNOCK_LIMIT {
+            moves.push(Action::Knock(*card));
+        }
+    }
+    if best[all] == 0 {
+        moves.push(Action::BigGin);
+    }
+
+    moves
+}
+
+/// Parses a card written as `rank_suit`, e.g. `Q_hearts`.
+fn parse_card(s: &str) -> Result<Card, String> {
+    let parts: Vec<&str> = s.split('_').collect();
+    if parts.len() == 2 {
+        let rank = parts[0].parse().map_err(|_| "Invalid rank".to_string())?;
+        let suit = parts[1].parse().map_err(|_| "Invalid suit".to_string())?;
+        Ok(Card::new(suit, rank))
+    } else {
+        Err("Invalid card format".to_string())
+    }
 }
 
 impl FromStr for Action {
@@ -48,28 +406,128 @@ impl FromStr for Action {
         match s {
             "draw_deck" => Ok(Action::DrawFromDeck),
             "draw_discard" => Ok(Action::DrawFromDiscard),
+            "big_gin" => Ok(Action::BigGin),
             _ if s.starts_with("discard ") => {
-                let card_str = s.trim_start_matches("discard ");
-                // Assuming card_str is in the format "rank_suit"
-                let parts: Vec<&str> = card_str.split('_').collect();
-                if parts.len() == 2 {
-                    let rank = parts[0].parse().map_err(|_| "Invalid rank".to_string())?;
-                    let suit = parts[1].parse().map_err(|_| "Invalid suit".to_string())?;
-                    Ok(Action::Discard(Card::new(suit, rank)))
-                } else {
-                    Err("Invalid discard action format".to_string())
-                }
+                parse_card(s.trim_start_matches("discard ")).map(Action::Discard)
             }
             _ if s.starts_with("knock ") => {
-                let card_str = s.trim_start_matches("knock ");
-                // Similar parsing logic as above
-                unimplemented!()
+                parse_card(s.trim_start_matches("knock ")).map(Action::Knock)
             }
             _ => Err("Unknown action".to_string()),
         }
     }
 }
 
+/// What one player may see of a [`Rummy`] hand.
+///
+/// The deck order and the opponent's cards stay hidden, except for the cards the
+/// opponent took from the discard pile and still holds.
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub struct RummyObservation {
+    /// The observing player
+    pub player: Player,
+    pub current_player: Player,
+    pub hand: Hand,
+    /// Cards of `hand` the opponent knows about from discard pickups
+    pub revealed: Hand,
+    /// The discard pile, face up, from the bottom to the top
+    pub discard: Deck,
+    /// Cards known to be in the opponent's hand
+    pub opponent_known: Hand,
+    pub opponent_hand_size: u8,
+    pub deck_size: u8,
+    pub result: Option<HandResult>,
+}
+
+impl RummyObservation {
+    /// Returns the actions open to the observing player, empty unless it is their turn
+    /// in a hand still in play.
+    pub fn available_moves(&self) -> Vec<Action> {
+        let drawn = self.deck_size <= 2 && self.hand.len() < 11;
+        if self.player != self.current_player || self.result.is_some() || drawn {
+            return Vec::new();
+        }
+        hand_actions(&self.hand, !self.discard.is_empty())
+    }
+
+    /// Samples a full hand consistent with the observation.
+    ///
+    /// The unseen cards, the deck and the opponent's unknown cards, are shuffled and
+    /// dealt out in their observed numbers, so every sample looks the same to the
+    /// observing player.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::ImperfectInfoGame;
+    /// use games_rs::rummy::{Player, Rummy};
+    ///
+    /// let game = Rummy::dealt_by(Player::Player2);
+    /// let observation = game.observe(Player::Player1);
+    /// let sample = observation.determinize(&mut rand::rng());
+    /// assert_eq!(sample.observe(Player::Player1), observation);
+    /// ```
+    pub fn determinize<R: Rng + ?Sized>(&self, rng: &mut R) -> Rummy {
+        let mut unseen = Deck::new()
+            .iter()
+            .filter(|card| {
+                !self.hand.contains(card)
+                    && !self.opponent_known.contains(card)
+                    && !self.discard.iter().any(|c| c == *card)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        unseen.shuffle(rng);
+
+        let hidden = self.opponent_hand_size as usize - self.opponent_known.len();
+        let mut opponent_hand = self.opponent_known;
+        opponent_hand.extend(unseen.drain(..hidden));
+        let mut deck = Deck::new_empty();
+        for card in unseen {
+            deck.push_top(card);
+        }
+
+        let mask = |cards: &Hand| cards.iter().fold(0, |mask, card| mask | 1 << card.index());
+        let mut hands = [Hand::new(), Hand::new()];
+        let mut known = [0, 0];
+        hands[self.player.seat()] = self.hand;
+        known[self.player.seat()] = mask(&self.revealed);
+        hands[self.player.opponent().seat()] = opponent_hand;
+        known[self.player.opponent().seat()] = mask(&self.opponent_known);
+
+        let mut rummy = Rummy {
+            deck,
+            discard: self.discard,
+            hands,
+            current_player: self.current_player,
+            result: self.result,
+            known,
+            hash: 0,
+        };
+        rummy.hash = rummy.compute_hash();
+        rummy
+    }
+}
+
+/// A hand split into melds and the deadwood left over.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MeldArrangement {
+    pub melds: Vec<Vec<Card>>,
+    pub deadwood: Vec<Card>,
+    /// Total deadwood value
+    pub points: u8,
+}
+
+/// Undo record for an action played on a [`Rummy`] hand.
+#[derive(..Copy, Debug)]
+pub struct MoveRecord {
+    action: Action,
+    player: Player,
+    /// Position the discarded card occupied in the player's hand.
+    hand_index: u8,
+    /// Whether the discarded card was known to the opponent from a discard pickup.
+    was_known: bool,
+}
+
 #[derive(..StdTraits, Debug, Serialize, Deserialize)]
 pub struct Rummy {
     // Fields for the Gin Rummy game
@@ -77,18 +535,37 @@ pub struct Rummy {
     discard: Deck,
     hands: [Hand; 2],
     current_player: Player,
+    /// Set once a player has knocked, ending the hand.
+    result: Option<HandResult>,
+    /// Cards each player took from the discard pile and still holds, by card index.
+    ///
+    /// These are the only cards of a hand the opponent can know about.
+    known: [u64; 2],
+    /// Zobrist key over card locations, the discard top and the player to move.
+    ///
+    /// The order of cards within the deck, the discard pile and each hand is not hashed.
+    hash: u64,
 }
 
 impl Game for Rummy {
     const name: &'static str = "Rummy";
     type MoveType = Action;
     type PlayerType = Player;
+    type MoveRecord = MoveRecord;
 
+    /// Returns the winner of a knocked hand.
+    ///
+    /// A hand is drawn once no more than two cards are left in the deck at the start of
+    /// a turn.
     fn get_status(&self) -> crate::GameStatus {
-        if self.deck.is_empty() && self.discard.is_empty() {
-            crate::GameStatus::Draw
-        } else {
-            crate::GameStatus::InProgress
+        match self.result {
+            Some(result) => crate::GameStatus::Win(result.winner.into()),
+            None if self.deck.len() <= 2
+                && self.get_hand(self.current_player).unwrap().len() < 11 =>
+            {
+                crate::GameStatus::Draw
+            }
+            None => crate::GameStatus::InProgress,
         }
     }
 
@@ -96,38 +573,165 @@ impl Game for Rummy {
         self.current_player
     }
 
-    fn play(&mut self, action: Self::MoveType, player: Player) -> Result<(), String> {
-        self.play_action(player, action)
+    fn make_move(&mut self, action: Self::MoveType, player: Player) -> Result<MoveRecord, String> {
+        let hand_index = match action {
+            Action::Discard(card) | Action::Knock(card) => self
+                .get_hand(player)
+                .unwrap()
+                .iter()
+                .position(|c| *c == card)
+                .unwrap_or(0) as u8,
+            _ => 0,
+        };
+        let was_known = match action {
+            Action::Discard(card) | Action::Knock(card) => {
+                self.known[player.seat()] & 1 << card.index() != 0
+            }
+            _ => false,
+        };
+
+        self.play_action(player, action)?;
+
+        Ok(MoveRecord {
+            action,
+            player,
+            hand_index,
+            was_known,
+        })
+    }
+
+    fn undo(&mut self, record: MoveRecord) {
+        self.undo_action(record)
     }
 
     fn get_available_moves(&self) -> Vec<Self::MoveType> {
         self.get_available_moves()
     }
+
+    fn hash_key(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl ImperfectInfoGame for Rummy {
+    type Observation = RummyObservation;
+
+    fn observe(&self, player: Player) -> RummyObservation {
+        self.observation(player)
+    }
+
+    fn observed_moves(observation: &RummyObservation) -> Vec<Action> {
+        observation.available_moves()
+    }
 }
 
 impl Rummy {
     pub fn new() -> Self {
-        let mut deck = Deck::new();
-        deck.shuffle();
+        let mut rummy = Self::undealt();
+        rummy.deck.shuffle();
+        rummy
+    }
 
-        Rummy {
-            deck,
+    /// Returns a hand before the deal, with the deck in order.
+    ///
+    /// This is the root of graphs trained on Rummy: every deal is a move away from it.
+    pub fn undealt() -> Self {
+        let mut rummy = Rummy {
+            deck: Deck::new(),
             discard: Deck::new_empty(),
             hands: [Hand::new(), Hand::new()],
             current_player: Player::Player1,
+            result: None,
+            known: [0, 0],
+            hash: 0,
+        };
+        rummy.hash = rummy.compute_hash();
+        rummy
+    }
+
+    /// Returns the Zobrist key contribution of the discard pile's top card.
+    fn discard_top_key(&self) -> u64 {
+        self.discard
+            .top()
+            .map_or(0, |card| DISCARD_TOP_KEYS[card.index()])
+    }
+
+    /// Updates the hash for `card` moving between locations.
+    ///
+    /// `top_key` is the discard top contribution from before the move.
+    fn rehash(&mut self, card: &Card, from: Location, to: Location, top_key: u64) {
+        self.hash ^= from.key(card) ^ to.key(card) ^ top_key ^ self.discard_top_key();
+    }
+
+    /// Computes the Zobrist key of the hand from scratch.
+    fn compute_hash(&self) -> u64 {
+        let mut hash = self.discard_top_key();
+        hash ^= self
+            .deck
+            .iter()
+            .fold(0, |h, card| h ^ Location::Deck.key(card));
+        hash ^= self
+            .discard
+            .iter()
+            .fold(0, |h, card| h ^ Location::Discard.key(card));
+        for player in [Player::Player1, Player::Player2] {
+            hash ^= self
+                .get_hand(player)
+                .unwrap()
+                .iter()
+                .fold(0, |h, card| h ^ Location::Hand(player).key(card));
+        }
+        if self.current_player == Player::Player2 {
+            hash ^= PLAYER2_KEY[0];
         }
+        if self.result.is_some() {
+            hash ^= HAND_OVER_KEY[0];
+        }
+        hash
+    }
+
+    /// Shuffles a new deck and deals a hand, with the player after `dealer` to move.
+    pub fn dealt_by(dealer: Player) -> Self {
+        Self::dealt_with(dealer, &mut rand::rng())
+    }
+
+    /// Deals a hand like [`Rummy::dealt_by`], shuffling with `rng`.
+    pub fn dealt_with<R: Rng + ?Sized>(dealer: Player, rng: &mut R) -> Self {
+        let mut rummy = Self::undealt();
+        rummy.deal_with(rng);
+        rummy.current_player = dealer.opponent();
+        rummy.hash = rummy.compute_hash();
+        rummy
     }
 
     pub fn deal(&mut self) {
-        self.deck.shuffle();
+        self.deal_with(&mut rand::rng());
+    }
+
+    /// Shuffles the deck with `rng` and deals ten cards to each player.
+    pub fn deal_with<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.deck.shuffle_with(rng);
+        let top_key = self.discard_top_key();
 
         // Logic to deal cards to players
         for _ in 0..10 {
             if let Some(card) = self.deck.draw() {
                 self.hands[0].push(card);
+                self.rehash(
+                    &card,
+                    Location::Deck,
+                    Location::Hand(Player::Player1),
+                    top_key,
+                );
             }
             if let Some(card) = self.deck.draw() {
                 self.hands[1].push(card);
+                self.rehash(
+                    &card,
+                    Location::Deck,
+                    Location::Hand(Player::Player2),
+                    top_key,
+                );
             }
         }
     }
@@ -143,6 +747,9 @@ impl Rummy {
         if player != self.current_player {
             return Err("Not this player's turn".to_string());
         }
+        if self.result.is_some() {
+            return Err("The hand is over".to_string());
+        }
 
         // Validation step for drawing
         if action == Action::DrawFromDeck || action == Action::DrawFromDiscard {
@@ -150,30 +757,90 @@ impl Rummy {
                 return Err("Cannot draw more cards, hand is full".to_string());
             }
         } else {
-            if self.get_hand(player).unwrap().len() < 10 {
+            if self.get_hand(player).unwrap().len() < 11 {
                 return Err("Must draw a card before discarding".to_string());
             }
         }
 
-        //
-
         match action {
             Action::DrawFromDeck => self.draw_card(player, false),
             Action::DrawFromDiscard => self.draw_card(player, true),
             Action::Discard(card) => self.discard_card(player, card),
             Action::Knock(card) => {
-                self.discard_card(player, card);
-                // Logic for knocking
-                unimplemented!()
+                let deadwood = self
+                    .deadwood_after_discard(player, card)
+                    .ok_or("Card not in hand".to_string())?;
+                if deadwood > KNOCK_LIMIT {
+                    return Err(format!("Cannot knock with {} deadwood", deadwood));
+                }
+                self.discard_card(player, card)?;
+                self.knock(player)
+            }
+            Action::BigGin => self.knock(player),
+        }
+    }
+
+    /// Reverts an action previously played through [`Game::make_move`].
+    pub fn undo_action(&mut self, record: MoveRecord) {
+        if self.result.take().is_some() {
+            self.hash ^= HAND_OVER_KEY[0];
+        }
+        let top_key = self.discard_top_key();
+        let hand = match record.player {
+            Player::Player1 => &mut self.hands[0],
+            Player::Player2 => &mut self.hands[1],
+        };
+
+        match record.action {
+            Action::DrawFromDeck => {
+                if let Some(card) = hand.pop() {
+                    self.deck.push_top(card);
+                    self.rehash(
+                        &card,
+                        Location::Hand(record.player),
+                        Location::Deck,
+                        top_key,
+                    );
+                }
+            }
+            Action::DrawFromDiscard => {
+                if let Some(card) = hand.pop() {
+                    self.known[record.player.seat()] &= !(1 << card.index());
+                    self.discard.push_top(card);
+                    self.rehash(
+                        &card,
+                        Location::Hand(record.player),
+                        Location::Discard,
+                        top_key,
+                    );
+                }
+            }
+            Action::Discard(_) | Action::Knock(_) => {
+                if let Some(card) = self.discard.draw() {
+                    hand.insert(record.hand_index as usize, card);
+                    if record.was_known {
+                        self.known[record.player.seat()] |= 1 << card.index();
+                    }
+                    self.rehash(
+                        &card,
+                        Location::Discard,
+                        Location::Hand(record.player),
+                        top_key,
+                    );
+                }
+                self.current_player = record.player;
+                self.hash ^= PLAYER2_KEY[0];
             }
+            Action::BigGin => {}
         }
     }
 
     pub fn draw_card(&mut self, player: Player, from_discard: bool) -> Result<(), String> {
-        let card = if from_discard {
-            self.discard.draw()
+        let top_key = self.discard_top_key();
+        let (card, source) = if from_discard {
+            (self.discard.draw(), Location::Discard)
         } else {
-            self.deck.draw()
+            (self.deck.draw(), Location::Deck)
         };
 
         match card {
@@ -182,6 +849,10 @@ impl Rummy {
                     Player::Player1 => self.hands[0].push(c),
                     Player::Player2 => self.hands[1].push(c),
                 };
+                if from_discard {
+                    self.known[player.seat()] |= 1 << c.index();
+                }
+                self.rehash(&c, source, Location::Hand(player), top_key);
                 Ok(())
             }
             None => Err("No cards left to draw".to_string()),
@@ -189,6 +860,7 @@ impl Rummy {
     }
 
     pub fn discard_card(&mut self, player: Player, card: Card) -> Result<(), String> {
+        let top_key = self.discard_top_key();
         let hand = match player {
             Player::Player1 => &mut self.hands[0],
             Player::Player2 => &mut self.hands[1],
@@ -197,175 +869,948 @@ impl Rummy {
         if let Some(pos) = hand.iter().position(|x| *x == card) {
             hand.remove(pos);
             self.discard.push_top(card);
+            self.known[player.seat()] &= !(1 << card.index());
             self.current_player = match self.current_player {
                 Player::Player1 => Player::Player2,
                 Player::Player2 => Player::Player1,
             };
+            self.rehash(&card, Location::Hand(player), Location::Discard, top_key);
+            self.hash ^= PLAYER2_KEY[0];
             Ok(())
         } else {
             Err("Card not in hand".to_string())
         }
     }
 
+    /// Ends the hand for `player`, who has just discarded or holds 11 melded cards.
+    ///
+    /// The knocker's cards are arranged into melds. Unless the knocker went gin, the
+    /// defender lays off what they can onto those melds, arranging the rest of their
+    /// cards into melds of their own in whichever way leaves the least deadwood. The knocker wins the difference in deadwood, or the
+    /// gin bonus plus the defender's deadwood; a defender with no more deadwood than
+    /// the knocker wins the difference plus the undercut bonus.
+    ///
+    /// # Errors
+    /// Fails if the knocker holds more than [`KNOCK_LIMIT`] deadwood, or holds 11 cards
+    /// that are not all melded.
     pub fn knock(&mut self, player: Player) -> Result<(), String> {
-        // Generate melds
+        let hand = self.get_hand(player).unwrap().to_vec();
+        let arrangement = self.get_min_pt_melds(&hand);
+        let knocker_deadwood = arrangement.points;
+        let declaration = match (hand.len(), knocker_deadwood) {
+            (11, 0) => Declaration::BigGin,
+            (11, _) => return Err("Big gin needs all 11 cards melded".to_string()),
+            (_, 0) => Declaration::Gin,
+            (_, deadwood) if deadwood <= KNOCK_LIMIT => Declaration::Knock,
+            (_, deadwood) => return Err(format!("Cannot knock with {} deadwood", deadwood)),
+        };
 
-        // Allow opposing player to add to melds if score != 0
+        let defender = player.opponent();
+        let defender_hand = self.get_hand(defender).unwrap();
+        let defender_deadwood = if declaration == Declaration::Knock {
+            defender_deadwood(&arrangement.melds, defender_hand)
+        } else {
+            self.get_min_pt_melds(defender_hand).points
+        };
 
-        // Leave remaining cards in hand and calculate points
-        let other_player = match player {
-            Player::Player1 => Player::Player2,
-            Player::Player2 => Player::Player1,
+        let (winner, points) = match declaration {
+            Declaration::Gin => (player, GIN_BONUS + defender_deadwood),
+            Declaration::BigGin => (player, BIG_GIN_BONUS + defender_deadwood),
+            Declaration::Knock if knocker_deadwood < defender_deadwood => {
+                (player, defender_deadwood - knocker_deadwood)
+            }
+            Declaration::Knock => (
+                defender,
+                UNDERCUT_BONUS + knocker_deadwood - defender_deadwood,
+            ),
         };
+        self.result = Some(HandResult {
+            knocker: player,
+            declaration,
+            knocker_deadwood,
+            defender_deadwood,
+            winner,
+            points,
+        });
+        self.hash ^= HAND_OVER_KEY[0];
+        Ok(())
+    }
 
-        self.caluclate_points(player);
-        self.caluclate_points(other_player);
+    /// Returns the outcome of the hand, once a player has knocked.
+    pub fn result(&self) -> Option<HandResult> {
+        self.result
+    }
 
-        unimplemented!()
+    /// Returns the deadwood of the player's hand.
+    pub fn caluclate_points(&self, player: Player) -> u8 {
+        self.get_min_pt_melds(self.get_hand(player).unwrap()).points
     }
 
-    pub fn caluclate_points(&self, player: Player) {}
+    /// Returns the deadwood the player would hold after discarding `card`, or `None` if
+    /// the card is not in their hand.
+    fn deadwood_after_discard(&self, player: Player, card: Card) -> Option<u8> {
+        let mut hand = self.get_hand(player).unwrap().to_vec();
+        let pos = hand.iter().position(|c| *c == card)?;
+        hand.remove(pos);
+        Some(self.get_min_pt_melds(&hand).points)
+    }
 
     pub fn get_available_moves(&self) -> Vec<Action> {
-        let mut moves = Vec::new();
+        if self.get_status() != crate::GameStatus::InProgress {
+            return Vec::new();
+        }
+        hand_actions(
+            self.get_hand(self.current_player).unwrap(),
+            !self.discard.is_empty(),
+        )
+    }
 
-        // Drawing options
-        if self.get_hand(self.current_player).unwrap().len() < 11 {
-            moves.push(Action::DrawFromDeck);
-            if !self.discard.is_empty() {
-                moves.push(Action::DrawFromDiscard);
-            }
+    /// Returns what `player` may see of the hand.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::ImperfectInfoGame;
+    /// use games_rs::rummy::{Player, Rummy};
+    ///
+    /// let game = Rummy::dealt_by(Player::Player2);
+    /// let observation = game.observe(Player::Player1);
+    /// assert_eq!(observation.hand.len(), 10);
+    /// assert_eq!(observation.opponent_hand_size, 10);
+    /// assert_eq!(observation.deck_size, 32);
+    /// assert!(observation.opponent_known.is_empty());
+    /// ```
+    pub fn observation(&self, player: Player) -> RummyObservation {
+        let opponent = player.opponent();
+        let opponent_hand = self.get_hand(opponent).unwrap();
+        let known = |player: Player| {
+            let mut cards: Hand = self
+                .get_hand(player)
+                .unwrap()
+                .iter()
+                .filter(|card| self.known[player.seat()] & 1 << card.index() != 0)
+                .copied()
+                .collect();
+            cards.sort();
+            cards
+        };
 
-            return moves;
+        RummyObservation {
+            player,
+            current_player: self.current_player,
+            hand: *self.get_hand(player).unwrap(),
+            revealed: known(player),
+            discard: self.discard,
+            opponent_known: known(opponent),
+            opponent_hand_size: opponent_hand.len() as u8,
+            deck_size: self.deck.len() as u8,
+            result: self.result,
         }
+    }
 
-        // Options to discard
-        moves = self
-            .get_hand(self.current_player)
-            .unwrap()
-            .iter()
-            .map(|c| Action::Discard(*c))
-            .collect();
+    /// Finds the arrangement of `hand` into melds that leaves the least deadwood.
+    ///
+    /// Sets are three or four cards of a rank and runs three or more consecutive cards
+    /// of a suit, with aces low. Every set and every run contained in the hand is a
+    /// candidate, including the three-card sets inside a four-card set and the shorter
+    /// runs inside a longer one, so a card shared between a set and a run goes wherever
+    /// it saves the most. The best choice of candidates is found exactly by dynamic
+    /// programming over the subsets of the hand.
+    ///
+    /// # Examples
+    /// ```
+    /// use games_rs::cards::{Card, Rank, Suit};
+    /// use games_rs::rummy::Rummy;
+    ///
+    /// // 7♥ completes both the sevens and the hearts run; the run saves more
+    /// let hand = [
+    ///     Card::new(Suit::Hearts, Rank::Five),
+    ///     Card::new(Suit::Hearts, Rank::Six),
+    ///     Card::new(Suit::Hearts, Rank::Seven),
+    ///     Card::new(Suit::Spades, Rank::Seven),
+    ///     Card::new(Suit::Clubs, Rank::Seven),
+    ///     Card::new(Suit::Diamonds, Rank::Seven),
+    ///     Card::new(Suit::Spades, Rank::King),
+    /// ];
+    /// let arrangement = Rummy::new().get_min_pt_melds(&hand);
+    /// assert_eq!(arrangement.melds.len(), 2);
+    /// assert_eq!(arrangement.deadwood, vec![Card::new(Suit::Spades, Rank::King)]);
+    /// assert_eq!(arrangement.points, 10);
+    /// ```
+    pub fn get_min_pt_melds(&self, hand: &[Card]) -> MeldArrangement {
+        arrange(hand)
+    }
 
-        // Options for knocking
+    // Additional methods for game logic would go here
+}
 
-        moves
+/// A freshly dealt hand with player 1 to move.
+impl Default for Rummy {
+    fn default() -> Self {
+        Self::dealt_by(Player::Player2)
     }
+}
 
-    pub fn get_min_pt_melds(&self, hand: &Vec<Card>) -> (u8, Vec<Vec<Card>>) {
-        // Construct all possible melds
-        // TODO: Isolate by rank for sets
-        // TODO: Isolate by suit for runs
-        // TODO: Identify melds with more than 3 cards
-        // TODO: Use combinatorial approach to find best meld combination
+/// Layout of [`Rummy`] in graph files written before the format had a header.
+///
+/// Kept frozen so those files can still be migrated; see
+/// [`graph_file`](crate::agents::graph_file).
+#[derive(..StdTraits, Debug, Deserialize)]
+pub struct LegacyRummy {
+    deck: Deck,
+    discard: Deck,
+    hands: [Hand; 2],
+    current_player: Player,
+}
 
-        let rank_ordered = {
-            let mut sets = hand.clone();
-            sets.sort_by_key(|c| *c.rank());
-            sets
-        };
-        let suit_ordered = {
-            let mut runs = hand.clone();
-            runs.sort_by_key(|c| *c.suit());
-            runs
+impl From<LegacyRummy> for Rummy {
+    /// Hands from before knocking was tracked are taken to be in play, with no cards
+    /// known to the opponent.
+    fn from(value: LegacyRummy) -> Self {
+        let mut rummy = Rummy {
+            deck: value.deck,
+            discard: value.discard,
+            hands: value.hands,
+            current_player: value.current_player,
+            result: None,
+            known: [0, 0],
+            hash: 0,
         };
+        rummy.hash = rummy.compute_hash();
+        rummy
+    }
+}
 
-        let sets = {
-            let mut melds = Vec::new();
-            let mut i = 0;
-            while i < rank_ordered.len() {
-                let mut current_set = vec![rank_ordered[i]];
-                let mut j = i + 1;
-                while j < rank_ordered.len() && rank_ordered[j].rank() == rank_ordered[i].rank() {
-                    current_set.push(rank_ordered[j]);
-                    j += 1;
-                }
-                if current_set.len() >= 3 {
-                    melds.push(current_set);
-                }
-                i = j;
-            }
-            melds
-        };
+impl fmt::Display for Rummy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Current Player: {:?}", self.current_player)?;
+        writeln!(f, "Deck has {} cards", self.deck.len())?;
+        writeln!(f, "Discard pile has {} cards", self.discard.len())?;
+        writeln!(f, "Player 1 Hand: {:?}", self.hands[0])?;
+        writeln!(f, "Player 2 Hand: {:?}", self.hands[1])?;
+        if let Some(result) = self.result {
+            writeln!(
+                f,
+                "{:?} by {:?}: {:?} wins {} points",
+                result.declaration, result.knocker, result.winner, result.points
+            )?;
+        }
+        Ok(())
+    }
+}
 
-        let runs = {
-            let mut melds = Vec::new();
-            let mut i = 0;
-            while i < suit_ordered.len() {
-                let mut current_run = vec![suit_ordered[i]];
-                let mut j = i + 1;
-                while j < suit_ordered.len()
-                    && suit_ordered[j].suit() == suit_ordered[i].suit()
-                    && *suit_ordered[j].rank() as u8 == *suit_ordered[j - 1].rank() as u8 + 1
-                {
-                    current_run.push(suit_ordered[j]);
-                    j += 1;
-                }
-                if current_run.len() >= 3 {
-                    melds.push(current_run);
-                }
-                i = j;
-            }
-            melds
-        };
+/// Undo record for an action played in a [`GinRummyMatch`].
+#[derive(..Copy, Debug)]
+pub struct MatchMoveRecord {
+    record: MoveRecord,
+    /// The match before the hand was scored, if the action ended it
+    finished: Option<GinRummyMatch>,
+}
 
-        // Find non-overlapping melds
-        let melds = {
-            let mut melds = Vec::new();
-            melds.extend(sets.clone().into_iter().filter(|set| {
-                !runs
-                    .iter()
-                    .any(|run| set.iter().any(|card| run.contains(card)))
-            }));
-            melds.extend(runs.clone().into_iter().filter(|run| {
-                !sets
-                    .iter()
-                    .any(|set| run.iter().any(|card| set.contains(card)))
-            }));
-            melds
-        };
+/// A Gin Rummy match: hands are played until a player reaches the target score.
+///
+/// Each finished hand is scored and a new one dealt, the deal alternating between the
+/// players. Only the first deal is random: later ones are shuffled with a seed taken from
+/// the match so far, so the same moves from the same start always lead to the same match. Drawn hands score nothing. Once a player reaches the
+/// target the match is over; the final scores add the [`GAME_BONUS`] for the winner, a
+/// [`BOX_BONUS`] for every hand won, and double the winner's score if the loser scored
+/// nothing (a shutout, or schneider).
+///
+/// # Examples
+/// ```
+/// use games_rs::Game;
+/// use games_rs::rummy::GinRummyMatch;
+///
+/// let game = GinRummyMatch::new();
+/// assert_eq!(game.scores(), [0, 0]);
+/// assert_eq!(game.hand().get_hand(game.get_current_player()).unwrap().len(), 10);
+/// ```
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub struct GinRummyMatch {
+    hand: Rummy,
+    scores: [u16; 2],
+    boxes: [u8; 2],
+    dealer: Player,
+    target: u16,
+}
 
-        // Find overlapping melds
-        let overlapping_melds = {
-            let mut melds = Vec::new();
-            for set in &sets {
-                for run in &runs {
-                    if set.iter().any(|card| run.contains(card)) {
-                        melds.push(vec![set.clone(), run.clone()].concat());
-                    }
-                }
-            }
-            melds
+impl GinRummyMatch {
+    /// Starts a match to [`MATCH_TARGET`] points with player 2 dealing.
+    pub fn new() -> Self {
+        GinRummyMatch {
+            hand: Rummy::dealt_by(Player::Player2),
+            scores: [0, 0],
+            boxes: [0, 0],
+            dealer: Player::Player2,
+            target: MATCH_TARGET,
+        }
+    }
+
+    /// Returns a match before the first deal, the root of graphs trained on matches.
+    pub fn undealt() -> Self {
+        GinRummyMatch {
+            hand: Rummy::undealt(),
+            ..Self::new()
+        }
+    }
+
+    /// Plays to `target` points instead.
+    pub fn with_target(mut self, target: u16) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Returns the hand in play, or the last hand once the match is over.
+    pub fn hand(&self) -> &Rummy {
+        &self.hand
+    }
+
+    /// Returns the points each player has won in hands so far.
+    pub fn scores(&self) -> [u16; 2] {
+        self.scores
+    }
+
+    /// Returns the number of hands each player has won.
+    pub fn boxes(&self) -> [u8; 2] {
+        self.boxes
+    }
+
+    pub fn dealer(&self) -> Player {
+        self.dealer
+    }
+
+    /// Returns the player who reached the target, once the match is over.
+    pub fn winner(&self) -> Option<Player> {
+        if self.scores[0] >= self.target {
+            Some(Player::Player1)
+        } else if self.scores[1] >= self.target {
+            Some(Player::Player2)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the scores including the game, box and shutout bonuses, once the match is
+    /// over.
+    pub fn final_scores(&self) -> Option<[u16; 2]> {
+        let winner = self.winner()?;
+        let (w, l) = match winner {
+            Player::Player1 => (0, 1),
+            Player::Player2 => (1, 0),
         };
+        let mut totals = [0; 2];
+        for i in 0..2 {
+            totals[i] = self.scores[i] + BOX_BONUS * self.boxes[i] as u16;
+        }
+        totals[w] += GAME_BONUS;
+        if self.scores[l] == 0 {
+            totals[w] *= 2;
+        }
+        Some(totals)
+    }
+
+    /// Scores the finished hand and deals the next one unless the match is over.
+    fn finish_hand(&mut self) {
+        if let Some(result) = self.hand.result() {
+            let i = match result.winner {
+                Player::Player1 => 0,
+                Player::Player2 => 1,
+            };
+            self.scores[i] += result.points as u16;
+            self.boxes[i] += 1;
+        }
+        if self.winner().is_none() {
+            self.dealer = self.dealer.opponent();
+            // Seeded from the match so far, so replaying its moves deals the same hands
+            let mut rng = StdRng::seed_from_u64(self.hash_key());
+            self.hand = Rummy::dealt_with(self.dealer, &mut rng);
+        }
+    }
+}
+
+impl Game for GinRummyMatch {
+    const name: &'static str = "Gin Rummy";
+    type MoveType = Action;
+    type PlayerType = Player;
+    type MoveRecord = MatchMoveRecord;
+
+    fn get_status(&self) -> crate::GameStatus {
+        match self.winner() {
+            Some(winner) => crate::GameStatus::Win(winner.into()),
+            None => crate::GameStatus::InProgress,
+        }
+    }
 
-        // Find combination of overlapping melds that minimizes point total
+    fn get_current_player(&self) -> Player {
+        self.hand.get_current_player()
+    }
 
-        // Logic to calculate minimal points in hand
-        (0, melds)
+    fn make_move(&mut self, action: Action, player: Player) -> Result<MatchMoveRecord, String> {
+        if self.winner().is_some() {
+            return Err("The match is over".to_string());
+        }
+        let record = self.hand.make_move(action, player)?;
+        if self.hand.get_status() == crate::GameStatus::InProgress {
+            return Ok(MatchMoveRecord {
+                record,
+                finished: None,
+            });
+        }
 
-        // Return (minimum points, resulting melds)
+        let finished = *self;
+        self.finish_hand();
+        Ok(MatchMoveRecord {
+            record,
+            finished: Some(finished),
+        })
     }
 
-    // Additional methods for game logic would go here
+    fn undo(&mut self, record: MatchMoveRecord) {
+        if let Some(finished) = record.finished {
+            *self = finished;
+        }
+        self.hand.undo(record.record);
+    }
+
+    fn get_available_moves(&self) -> Vec<Action> {
+        if self.winner().is_some() {
+            return Vec::new();
+        }
+        self.hand.get_available_moves()
+    }
+
+    /// Mixes the scores, boxes and dealer into the key of the hand in play.
+    fn hash_key(&self) -> u64 {
+        let dealer: u8 = self.dealer.into();
+        [
+            self.scores[0] as u64,
+            self.scores[1] as u64,
+            self.boxes[0] as u64,
+            self.boxes[1] as u64,
+            dealer as u64,
+        ]
+        .iter()
+        .zip(MATCH_KEYS)
+        .fold(self.hand.hash_key(), |hash, (value, key)| {
+            hash ^ value.wrapping_mul(key)
+        })
+    }
 }
 
-impl Default for Rummy {
+/// What one player may see of a [`GinRummyMatch`]: the scores and the hand in play.
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub struct MatchObservation {
+    pub hand: RummyObservation,
+    pub scores: [u16; 2],
+    pub boxes: [u8; 2],
+    pub dealer: Player,
+    pub target: u16,
+}
+
+impl ImperfectInfoGame for GinRummyMatch {
+    type Observation = MatchObservation;
+
+    fn observe(&self, player: Player) -> MatchObservation {
+        MatchObservation {
+            hand: self.hand.observation(player),
+            scores: self.scores,
+            boxes: self.boxes,
+            dealer: self.dealer,
+            target: self.target,
+        }
+    }
+
+    fn observed_moves(observation: &MatchObservation) -> Vec<Action> {
+        if observation
+            .scores
+            .iter()
+            .any(|score| *score >= observation.target)
+        {
+            return Vec::new();
+        }
+        observation.hand.available_moves()
+    }
+}
+
+impl Default for GinRummyMatch {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl fmt::Display for Rummy {
+impl fmt::Display for GinRummyMatch {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Current Player: {:?}", self.current_player)?;
-        writeln!(f, "Deck has {} cards", self.deck.len())?;
-        writeln!(f, "Discard pile has {} cards", self.discard.len())?;
-        writeln!(f, "Player 1 Hand: {:?}", self.hands[0])?;
-        writeln!(f, "Player 2 Hand: {:?}", self.hands[1])?;
-        Ok(())
+        writeln!(
+            f,
+            "Score: {} to {} ({} to {} hands), {:?} dealt",
+            self.scores[0], self.scores[1], self.boxes[0], self.boxes[1], self.dealer
+        )?;
+        write!(f, "{}", self.hand)
     }
 }
 
 pub type Hand = ArrayVec<[Card; 11]>; // Max 11 cards in hand during play
 
 // pub type Hand = Array<Card, 11>; // Max 11 cards in hand during play
+
+mod test {
+    /// Parses cards written as rank and suit initial, e.g. `TH QS`.
+    fn cards(cards: &str) -> super::Hand {
+        use crate::cards::{Card, Suit};
+
+        cards
+            .split(' ')
+            .map(|card| {
+                let (rank, suit) = card.split_at(card.len() - 1);
+                let suit = match suit {
+                    "H" => Suit::Hearts,
+                    "D" => Suit::Diamonds,
+                    "C" => Suit::Clubs,
+                    _ => Suit::Spades,
+                };
+                Card::new(suit, rank.parse().unwrap())
+            })
+            .collect()
+    }
+
+    fn card(card: &str) -> crate::cards::Card {
+        cards(card)[0]
+    }
+
+    /// A hand with player 1 to move holding `knocker` and player 2 holding `defender`.
+    fn hand(knocker: &str, defender: &str) -> super::Rummy {
+        use crate::cards::Deck;
+
+        let mut game = super::Rummy::new();
+        game.deck = Deck::new_empty();
+        for card in Deck::new().iter() {
+            if !cards(knocker).contains(card) && !cards(defender).contains(card) {
+                game.deck.push_top(*card);
+            }
+        }
+        game.hands = [cards(knocker), cards(defender)];
+        game.hash = game.compute_hash();
+        game
+    }
+
+    #[test]
+    fn test_undo_restores_positions() {
+        use super::Rummy;
+        use crate::Game;
+        use rand::seq::IndexedRandom;
+
+        let mut rng = rand::rng();
+        let mut game = Rummy::new();
+        game.deal();
+        let mut history = vec![game];
+        let mut records = Vec::new();
+
+        for _ in 0..60 {
+            // Hands end early once a player knocks
+            let Some(&mv) = game.get_available_moves().choose(&mut rng) else {
+                break;
+            };
+            records.push(game.make_move(mv, game.get_current_player()).unwrap());
+            history.push(game);
+        }
+
+        history.pop();
+        while let Some(record) = records.pop() {
+            game.undo(record);
+            assert_eq!(game, history.pop().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_hash_key_matches_recomputation() {
+        use super::Rummy;
+        use crate::{Game, cards::Card};
+        use rand::seq::IndexedRandom;
+        use std::collections::HashMap;
+
+        let mut rng = rand::rng();
+        let mut seen: HashMap<u64, Rummy> = HashMap::new();
+        for _ in 0..20 {
+            let mut game = Rummy::new();
+            game.deal();
+            assert_eq!(game.hash_key(), game.compute_hash());
+
+            for _ in 0..40 {
+                let Some(&mv) = game.get_available_moves().choose(&mut rng) else {
+                    break;
+                };
+                game.play(mv, game.get_current_player()).unwrap();
+                assert_eq!(game.hash_key(), game.compute_hash());
+
+                // Keys describe which cards lie where, not their order within a pile
+                if let Some(existing) = seen.insert(game.hash_key(), game) {
+                    let sorted = |cards: &mut dyn Iterator<Item = &Card>| {
+                        let mut cards = cards.copied().collect::<Vec<_>>();
+                        cards.sort();
+                        cards
+                    };
+                    for i in 0..2 {
+                        assert_eq!(
+                            sorted(&mut existing.hands[i].iter()),
+                            sorted(&mut game.hands[i].iter()),
+                            "hash collision"
+                        );
+                    }
+                    assert_eq!(
+                        sorted(&mut existing.discard.iter()),
+                        sorted(&mut game.discard.iter()),
+                        "hash collision"
+                    );
+                    assert_eq!(existing.discard.top(), game.discard.top());
+                    assert_eq!(existing.current_player, game.current_player);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hand_resolution() {
+        use super::test::{card, hand};
+        use super::{Action, Declaration, Player};
+        use crate::{Game, GameStatus};
+
+        // The defender lays off 5H, 4H and 9H and undercuts the knocker's 2 deadwood
+        let mut game = hand(
+            "6H 7H 8H 5S 5D 5C 9C 9D 9S 2C KH",
+            "5H 4H 9H KS KD KC QS QD QC AH",
+        );
+        let moves = game.get_available_moves();
+        assert!(moves.contains(&Action::Knock(card("KH"))));
+        assert!(!moves.contains(&Action::Knock(card("6H"))));
+        assert!(
+            game.play(Action::Knock(card("6H")), Player::Player1)
+                .is_err()
+        );
+
+        let before = game;
+        let record = game
+            .make_move(Action::Knock(card("KH")), Player::Player1)
+            .unwrap();
+        let result = game.result().unwrap();
+        assert_eq!(result.declaration, Declaration::Knock);
+        assert_eq!((result.knocker_deadwood, result.defender_deadwood), (2, 1));
+        assert!(result.is_undercut());
+        assert_eq!(result.points, 26);
+        assert_eq!(game.get_status(), GameStatus::Win(2));
+        assert!(game.get_available_moves().is_empty());
+        assert_eq!(game.hash_key(), game.compute_hash());
+        game.undo(record);
+        assert_eq!(game, before);
+
+        // Without the layoffs the knocker wins the difference
+        let mut game = hand(
+            "6H 7H 8H 5S 5D 5C 9C 9D 9S 2C KH",
+            "3H 4D 2S KS KD KC QS QD QC AH",
+        );
+        game.play(Action::Knock(card("KH")), Player::Player1)
+            .unwrap();
+        let result = game.result().unwrap();
+        assert_eq!((result.winner, result.points), (Player::Player1, 8));
+
+        // Melding 2D 3D 4D instead of the fours frees 4S to go onto 5S 6S 7S, leaving
+        // the defender only 4C
+        let mut game = hand(
+            "5S 6S 7S 9C 9D 9H JC JD JH 4H KH",
+            "2D 3D 4D 4S 4C 8C 8D 8S 8H 9S",
+        );
+        game.play(Action::Knock(card("KH")), Player::Player1)
+            .unwrap();
+        let result = game.result().unwrap();
+        assert_eq!((result.knocker_deadwood, result.defender_deadwood), (4, 4));
+        assert_eq!((result.winner, result.points), (Player::Player2, 25));
+
+        // Gin denies layoffs
+        let mut game = hand(
+            "6H 7H 8H 9H 5S 5D 5C JC JD JS KH",
+            "5H TH KS KD KC QS QD QC AH 2S",
+        );
+        game.play(Action::Knock(card("KH")), Player::Player1)
+            .unwrap();
+        let result = game.result().unwrap();
+        assert_eq!(result.declaration, Declaration::Gin);
+        assert_eq!((result.winner, result.points), (Player::Player1, 25 + 18));
+
+        let mut game = hand(
+            "6H 7H 8H 9H 5S 5D 5C JC JD JS JH",
+            "5H TH KS KD KC QS QD QC AH 2S",
+        );
+        assert!(game.get_available_moves().contains(&Action::BigGin));
+        game.play(Action::BigGin, Player::Player1).unwrap();
+        let result = game.result().unwrap();
+        assert_eq!(result.declaration, Declaration::BigGin);
+        assert_eq!((result.winner, result.points), (Player::Player1, 31 + 18));
+
+        // A hand is drawn once the deck is down to two cards
+        let mut game = hand(
+            "6H 7H 8H 9H 5S 5D 5C JC JD JS",
+            "5H TH KS KD KC QS QD QC AH 2S",
+        );
+        while game.deck.len() > 2 {
+            game.deck.draw();
+        }
+        assert_eq!(game.get_status(), GameStatus::Draw);
+        assert!(game.get_available_moves().is_empty());
+    }
+
+    #[test]
+    fn test_min_pt_melds_matches_brute_force() {
+        use super::{Rummy, deadwood_value, run_rank};
+        use crate::cards::{Card, Deck, Rank, Suit};
+        use rand::seq::IndexedRandom;
+
+        let is_meld = |cards: &[Card]| {
+            if cards.len() < 3 {
+                return false;
+            }
+            if cards.iter().all(|card| card.rank() == cards[0].rank()) {
+                return true;
+            }
+            let mut ranks = cards.iter().map(run_rank).collect::<Vec<_>>();
+            ranks.sort();
+            cards.iter().all(|card| card.suit() == cards[0].suit())
+                && ranks.windows(2).all(|pair| pair[1] == pair[0] + 1)
+        };
+
+        // Least deadwood over every subset of the hand that can be split into melds
+        let brute_force = |hand: &[Card]| {
+            let n = hand.len();
+            let cards = |mask: usize| {
+                (0..n)
+                    .filter(|i| mask & (1 << i) != 0)
+                    .map(|i| hand[i])
+                    .collect::<Vec<_>>()
+            };
+            let mut splits = vec![false; 1 << n];
+            splits[0] = true;
+            for mask in 1..(1usize << n) {
+                let lowest = 1 << mask.trailing_zeros();
+                let mut part = mask;
+                while part != 0 {
+                    if part & lowest != 0 && splits[mask & !part] && is_meld(&cards(part)) {
+                        splits[mask] = true;
+                        break;
+                    }
+                    part = (part - 1) & mask;
+                }
+            }
+            (0..(1usize << n))
+                .filter(|mask| splits[*mask])
+                .map(|mask| {
+                    cards(!mask & ((1 << n) - 1))
+                        .iter()
+                        .map(deadwood_value)
+                        .sum::<u8>()
+                })
+                .min()
+                .unwrap()
+        };
+
+        let rummy = Rummy::new();
+        let points = |hand: &[Card]| rummy.get_min_pt_melds(hand).points;
+        let card = |rank: Rank, suit: Suit| Card::new(suit, rank);
+        // A four-card set
+        let nines = [Suit::Hearts, Suit::Spades, Suit::Diamonds, Suit::Clubs]
+            .map(|suit| card(Rank::Nine, suit));
+        assert_eq!(points(&nines), 0);
+        // A run giving up its top card to a set, leaving a shorter run with aces low
+        let mut hand = [Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five]
+            .map(|rank| card(rank, Suit::Hearts))
+            .to_vec();
+        hand.extend([
+            card(Rank::Five, Suit::Spades),
+            card(Rank::Five, Suit::Diamonds),
+        ]);
+        assert_eq!(points(&hand), 0);
+        // Queen, king and ace do not form a run
+        let high = [Rank::Queen, Rank::King, Rank::Ace].map(|rank| card(rank, Suit::Clubs));
+        assert_eq!(points(&high), 21);
+
+        // Hands from a deck of low cards are dense in overlapping sets and runs
+        let full = Deck::new().iter().copied().collect::<Vec<_>>();
+        let low = full
+            .iter()
+            .copied()
+            .filter(|card| run_rank(card) <= 7)
+            .collect::<Vec<_>>();
+
+        let mut rng = rand::rng();
+        for (deck, size) in [(&full, 10), (&full, 11), (&low, 10), (&low, 11)] {
+            for _ in 0..300 {
+                let hand = deck
+                    .choose_multiple(&mut rng, size)
+                    .copied()
+                    .collect::<Vec<_>>();
+                let arrangement = rummy.get_min_pt_melds(&hand);
+                assert_eq!(arrangement.points, brute_force(&hand), "{:?}", hand);
+
+                let mut used = arrangement.deadwood.clone();
+                for meld in &arrangement.melds {
+                    assert!(is_meld(meld), "{:?}", meld);
+                    used.extend(meld);
+                }
+                used.sort();
+                let mut sorted = hand.clone();
+                sorted.sort();
+                assert_eq!(used, sorted);
+                assert_eq!(
+                    arrangement.deadwood.iter().map(deadwood_value).sum::<u8>(),
+                    arrangement.points
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_match_scoring_and_undo() {
+        use super::test::{card, hand};
+        use super::{Action, GinRummyMatch, Player};
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+
+        // Player 1 knocks with 2 deadwood against 10 and wins 8 points
+        let knock = hand(
+            "6H 7H 8H 5S 5D 5C 9C 9D 9S 2C KH",
+            "3H 4D 2S KS KD KC QS QD QC AH",
+        );
+        let mut game = GinRummyMatch::new();
+        game.hand = knock;
+        game.scores = [90, 0];
+        let before = game;
+        let record = game
+            .make_move(Action::Knock(card("KH")), Player::Player1)
+            .unwrap();
+        assert_eq!((game.scores(), game.boxes()), ([98, 0], [1, 0]));
+        assert_eq!(game.dealer(), Player::Player1);
+        assert_eq!(game.get_current_player(), Player::Player2);
+        assert_eq!(game.get_status(), GameStatus::InProgress);
+        game.undo(record);
+        assert_eq!(game, before);
+        assert_eq!(game.hash_key(), before.hash_key());
+
+        // Reaching the target ends the match; the shutout doubles the winner's total
+        game.scores = [95, 0];
+        game.boxes = [3, 0];
+        game.play(Action::Knock(card("KH")), Player::Player1)
+            .unwrap();
+        assert_eq!(game.get_status(), GameStatus::Win(1));
+        assert!(game.get_available_moves().is_empty());
+        assert_eq!(game.final_scores(), Some([(103 + 4 * 25 + 100) * 2, 0]));
+
+        // Whole matches are undone move by move, across redeals
+        let mut rng = rand::rng();
+        let mut game = GinRummyMatch::new().with_target(30);
+        let mut history = vec![game];
+        let mut played = Vec::new();
+        let mut records = Vec::new();
+        while game.get_status() == GameStatus::InProgress && records.len() < 5000 {
+            let moves = game.get_available_moves();
+            let knocks = moves
+                .iter()
+                .filter(|mv| matches!(mv, Action::Knock(_) | Action::BigGin))
+                .copied()
+                .collect::<Vec<_>>();
+            let mv = *knocks
+                .first()
+                .unwrap_or_else(|| moves.choose(&mut rng).unwrap());
+            records.push(game.make_move(mv, game.get_current_player()).unwrap());
+            played.push(mv);
+            history.push(game);
+        }
+        assert!(game.boxes().iter().sum::<u8>() > 0);
+
+        // Redeals are seeded from the match, so replaying its moves deals the same hands
+        let mut replay = history[0];
+        for mv in &played {
+            replay.play(*mv, replay.get_current_player()).unwrap();
+        }
+        assert_eq!(replay, game);
+
+        history.pop();
+        while let Some(record) = records.pop() {
+            game.undo(record);
+            assert_eq!(game, history.pop().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_graphs_credit_undercuts_to_the_defender() {
+        use super::test::{card, hand};
+        use super::{Action, Player, Rummy};
+        use crate::agents::monte_carlo_graph::MonteCarloGraph;
+        use crate::agents::train::TrainableComponent;
+        use crate::{Game, GameStatus, PlayThrough};
+
+        // The knock is undercut, so the player who made the last move lost
+        let deal = hand(
+            "5S 6S 7S 9C 9D 9H JC JD JH 4H KH",
+            "2D 3D 4D 4S 4C 8C 8D 8S 8H 9S",
+        );
+        let knock = Action::Knock(card("KH"));
+        let mut end = deal;
+        end.play(knock, Player::Player1).unwrap();
+        assert_eq!(end.get_status(), GameStatus::Win(Player::Player2.into()));
+
+        let sample =
+            PlayThrough::new(end.get_status(), vec![(Player::Player1, knock)]).with_start(deal);
+        let mut graph = MonteCarloGraph::<Rummy>::new();
+        graph.train(&sample, false);
+        assert_eq!(graph.edge_weight(deal, end), Some(&(0, 1, 0).into()));
+        assert!(graph.validate());
+    }
+
+    #[test]
+    fn test_observations_hide_hidden_cards() {
+        use super::{Action, Player, Rummy};
+        use crate::{Game, GameStatus, ImperfectInfoGame};
+        use rand::seq::{IndexedRandom, SliceRandom};
+
+        let mut rng = rand::rng();
+        let mut game = Rummy::dealt_by(Player::Player2);
+
+        // Moving the opponent's cards into the deck and reshuffling is invisible
+        let mut other = game;
+        let mut unseen = other.deck.iter().copied().collect::<Vec<_>>();
+        unseen.extend(other.hands[1].iter());
+        unseen.shuffle(&mut rng);
+        other.hands[1] = unseen[..10].iter().copied().collect();
+        other.deck.clear();
+        for card in &unseen[10..] {
+            other.deck.push_top(*card);
+        }
+        assert_eq!(
+            game.observe(Player::Player1),
+            other.observe(Player::Player1)
+        );
+        assert_ne!(
+            game.observe(Player::Player2),
+            other.observe(Player::Player2)
+        );
+
+        // Player 2 picks up player 1's discard, which player 1 then knows about
+        game.play(Action::DrawFromDeck, Player::Player1).unwrap();
+        let discard = game.hands[0][0];
+        game.play(Action::Discard(discard), Player::Player1)
+            .unwrap();
+        game.play(Action::DrawFromDiscard, Player::Player2).unwrap();
+        let observation = game.observe(Player::Player1);
+        assert_eq!(observation.opponent_known.to_vec(), vec![discard]);
+        assert_eq!(observation.opponent_hand_size, 11);
+        assert!(game.observe(Player::Player2).opponent_known.is_empty());
+        game.play(Action::Discard(discard), Player::Player2)
+            .unwrap();
+        assert!(game.observe(Player::Player1).opponent_known.is_empty());
+
+        // Moves computed from an observation are the moves of the game
+        while game.get_status() == GameStatus::InProgress {
+            let player = game.get_current_player();
+            let moves = game.get_available_moves();
+            assert_eq!(Rummy::observed_moves(&game.observe(player)), moves);
+            assert!(Rummy::observed_moves(&game.observe(player.opponent())).is_empty());
+            game.play(*moves.choose(&mut rng).unwrap(), player).unwrap();
+        }
+    }
+}
@@ -11,10 +11,16 @@ use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Game, GameStatus};
+use crate::{Game, GameStatus, Symmetric, common::zobrist};
 
 use derive_aliases::derive;
 
+/// Zobrist keys for marks, indexed by `(player - 1) * 81 + microboard * 9 + cell`.
+static CELL_KEYS: [u64; 162] = zobrist::keys(0xC0FF_EE00_0000_0081);
+
+/// Zobrist keys for the `next_microboard` constraint, indexed by `row * 3 + col`.
+static NEXT_KEYS: [u64; 9] = zobrist::keys(0xC0FF_EE00_0000_0009);
+
 #[derive(..StdTraits, Serialize, Deserialize, Debug)]
 pub enum Player {
     X,
@@ -125,6 +131,60 @@ impl From<&Move> for (u8, u8, u8, u8) {
     }
 }
 
+/// Cell masks of the eight three-in-a-row lines on a 3×3 board.
+const LINES: [u16; 8] = [
+    0b000_000_111,
+    0b000_111_000,
+    0b111_000_000,
+    0b001_001_001,
+    0b010_010_010,
+    0b100_100_100,
+    0b100_010_001,
+    0b001_010_100,
+];
+
+/// Mask of all nine cells of a 3×3 board.
+const FULL: u16 = 0b111_111_111;
+
+/// Lookup table from a 9-bit mask to whether it contains a three-in-a-row line.
+static WINNING: [bool; 512] = {
+    let mut table = [false; 512];
+    let mut mask = 0;
+    while mask < 512 {
+        let mut line = 0;
+        while line < LINES.len() {
+            if mask as u16 & LINES[line] == LINES[line] {
+                table[mask] = true;
+            }
+            line += 1;
+        }
+        mask += 1;
+    }
+    table
+};
+
+/// Returns the bit for a cell of a 3×3 board.
+#[inline]
+fn bit(row: u8, col: u8) -> u16 {
+    1 << (row * 3 + col)
+}
+
+/// Returns the marks of a 9-bit mask as `(row, col)` pairs in row-major order.
+fn cells(mask: u16) -> impl Iterator<Item = (u8, u8)> {
+    (0..9u8)
+        .filter(move |i| mask & (1 << i) != 0)
+        .map(|i| (i / 3, i % 3))
+}
+
+/// Undo record for a move played on an [`UltimateTTT`] board.
+///
+/// Stores the move together with the microboard constraint that was in force before it.
+#[derive(..Copy, Debug)]
+pub struct MoveRecord {
+    mv: Move,
+    next_microboard: Option<(u8, u8)>,
+}
+
 /// The main Ultimate Tic-Tac-Toe game board.
 ///
 /// This structure represents a 3×3 grid of microboards. The game follows these rules:
@@ -132,32 +192,128 @@ impl From<&Move> for (u8, u8, u8, u8) {
 /// - The cell's position determines which microboard the next player must play in
 /// - If a microboard is already won or full, the player can choose any available microboard
 /// - A player wins by getting three microboards in a row (horizontally, vertically, or diagonally)
+///
+/// Each player's marks are packed into one 9-bit mask per microboard. The outcome of
+/// every microboard is cached as macro-board masks, so status checks and move
+/// generation never rescan the cells.
 #[derive(..StdTraits, Serialize, Deserialize)]
 pub struct UltimateTTT {
-    boards: [[MicroBoard; 3]; 3],
+    /// Marks of X and O in each microboard, indexed by `row * 3 + col`.
+    marks: [[u16; 9]; 2],
+    /// Microboards won by X and O.
+    won: [u16; 2],
+    /// Microboards filled without a winner.
+    drawn: u16,
+    /// Number of marks on the board.
+    moves: u8,
     /// The microboard where the next move must be played, or None if any board is allowed.
     next_microboard: Option<(u8, u8)>,
+    /// Zobrist key of the marks and `next_microboard`, updated incrementally.
+    hash: u64,
 }
 
 impl UltimateTTT {
     /// Creates a new Ultimate Tic-Tac-Toe game with all boards empty.
     pub fn new() -> Self {
         UltimateTTT {
-            boards: [
-                [MicroBoard::new(), MicroBoard::new(), MicroBoard::new()],
-                [MicroBoard::new(), MicroBoard::new(), MicroBoard::new()],
-                [MicroBoard::new(), MicroBoard::new(), MicroBoard::new()],
-            ],
+            marks: [[0; 9]; 2],
+            won: [0; 2],
+            drawn: 0,
+            moves: 0,
             next_microboard: None,
+            hash: 0,
+        }
+    }
+
+    /// Returns the Zobrist key for `player` occupying the given cell.
+    fn cell_key(mv: &Move, player: Player) -> u64 {
+        match player {
+            Player::Empty => 0,
+            player => {
+                let microboard = (mv.microboard_row * 3 + mv.microboard_col) as usize;
+                let cell = (mv.cell_row * 3 + mv.cell_col) as usize;
+                CELL_KEYS[(Into::<u8>::into(player) as usize - 1) * 81 + microboard * 9 + cell]
+            }
+        }
+    }
+
+    /// Returns the Zobrist key for the given `next_microboard` constraint.
+    fn next_key(next_microboard: Option<(u8, u8)>) -> u64 {
+        match next_microboard {
+            Some((row, col)) => NEXT_KEYS[(row * 3 + col) as usize],
+            None => 0,
         }
     }
 
-    pub fn get_microboards(&self) -> &[[MicroBoard; 3]; 3] {
-        &self.boards
+    /// Computes the Zobrist key of the position from scratch.
+    fn compute_hash(&self) -> u64 {
+        let mut hash = Self::next_key(self.next_microboard);
+        for (player, marks) in [Player::X, Player::O].iter().zip(self.marks.iter()) {
+            for (microboard, &mask) in marks.iter().enumerate() {
+                for (cell_row, cell_col) in cells(mask) {
+                    let (row, col) = (microboard as u8 / 3, microboard as u8 % 3);
+                    let mv = Move::from((row, col, cell_row, cell_col));
+                    hash ^= Self::cell_key(&mv, *player);
+                }
+            }
+        }
+        hash
+    }
+
+    /// Updates the cached outcome of a microboard from its marks.
+    fn refresh_microboard(&mut self, microboard: usize) {
+        let bit = 1 << microboard;
+        self.won[0] &= !bit;
+        self.won[1] &= !bit;
+        self.drawn &= !bit;
+
+        if WINNING[self.marks[0][microboard] as usize] {
+            self.won[0] |= bit;
+        } else if WINNING[self.marks[1][microboard] as usize] {
+            self.won[1] |= bit;
+        } else if self.marks[0][microboard] | self.marks[1][microboard] == FULL {
+            self.drawn |= bit;
+        }
     }
 
-    pub fn get_microboard(&self, row: u8, col: u8) -> &MicroBoard {
-        &self.boards[row as usize][col as usize]
+    /// Returns the mask of microboards that are still being played.
+    #[inline]
+    fn open_microboards(&self) -> u16 {
+        !(self.won[0] | self.won[1] | self.drawn) & FULL
+    }
+
+    /// Returns the mask of microboards the current player may play in.
+    #[inline]
+    fn playable_microboards(&self) -> u16 {
+        let open = self.open_microboards();
+        match self.next_microboard {
+            Some((row, col)) if open & bit(row, col) != 0 => bit(row, col),
+            _ => open,
+        }
+    }
+
+    /// Returns a view of every microboard.
+    pub fn get_microboards(&self) -> [[MicroBoard; 3]; 3] {
+        let mut boards = [[MicroBoard::new(); 3]; 3];
+        for (row, boards_row) in boards.iter_mut().enumerate() {
+            for (col, board) in boards_row.iter_mut().enumerate() {
+                *board = self.get_microboard(row as u8, col as u8);
+            }
+        }
+        boards
+    }
+
+    /// Returns a view of the microboard at the given position.
+    pub fn get_microboard(&self, row: u8, col: u8) -> MicroBoard {
+        let microboard = (row * 3 + col) as usize;
+        MicroBoard {
+            marks: [self.marks[0][microboard], self.marks[1][microboard]],
+        }
+    }
+
+    /// Returns the number of marks played so far.
+    pub fn move_count(&self) -> usize {
+        self.moves as usize
     }
 }
 
@@ -165,29 +321,13 @@ impl Game for UltimateTTT {
     const name: &'static str = "Ultimate Tic-Tac-Toe";
     type MoveType = Move;
     type PlayerType = Player;
+    type MoveRecord = MoveRecord;
 
     /// Returns the current player (1 for X, 2 for O).
     ///
-    /// Determines the current player by counting moves. Player 1 (X) goes first.
+    /// Determines the current player from the move count. Player 1 (X) goes first.
     fn get_current_player(&self) -> Player {
-        let mut x_count = 0;
-        let mut o_count = 0;
-
-        for i in 0..3 {
-            for j in 0..3 {
-                for row in 0..3 {
-                    for col in 0..3 {
-                        match self.boards[i][j].grid[row][col] {
-                            Player::X => x_count += 1,
-                            Player::O => o_count += 1,
-                            Player::Empty => {}
-                        }
-                    }
-                }
-            }
-        }
-
-        if x_count <= o_count {
+        if self.moves % 2 == 0 {
             Player::X // Player X's turn
         } else {
             Player::O // Player O's turn
@@ -200,27 +340,12 @@ impl Game for UltimateTTT {
     /// only moves in that microboard are returned. Otherwise, moves from all playable microboards
     /// are returned.
     fn get_available_moves(&self) -> Vec<Self::MoveType> {
-        let mut available_microboards = Vec::new();
         let mut available_moves = Vec::new();
 
-        if let Some((row, col)) = self.next_microboard
-            && self.boards[row as usize][col as usize].get_status() == GameStatus::InProgress
-        {
-            available_microboards.push((row, col));
-        } else {
-            for i in 0..3 {
-                for j in 0..3 {
-                    if self.boards[i][j].get_status() == GameStatus::InProgress {
-                        available_microboards.push((i as u8, j as u8));
-                    }
-                }
-            }
-        }
-
-        for (microboard_row, microboard_col) in available_microboards {
-            let microboard = &self.boards[microboard_row as usize][microboard_col as usize];
-            let moves = microboard.get_available_moves();
-            for (cell_row, cell_col) in moves {
+        for (microboard_row, microboard_col) in cells(self.playable_microboards()) {
+            let microboard = (microboard_row * 3 + microboard_col) as usize;
+            let empty = !(self.marks[0][microboard] | self.marks[1][microboard]) & FULL;
+            for (cell_row, cell_col) in cells(empty) {
                 available_moves.push(Move::from((
                     microboard_row,
                     microboard_col,
@@ -247,71 +372,185 @@ impl Game for UltimateTTT {
     ///
     /// # Side Effects
     /// Updates `next_microboard` to direct the next player to the appropriate board.
-    fn play(&mut self, mv: Self::MoveType, player: Self::PlayerType) -> Result<(), String> {
+    fn make_move(
+        &mut self,
+        mv: Self::MoveType,
+        player: Self::PlayerType,
+    ) -> Result<MoveRecord, String> {
         if self.get_status() != GameStatus::InProgress {
             return Err("Game is already over".to_string());
         }
 
-        if !self.get_available_moves().contains(&mv) {
+        if mv.microboard_row > 2
+            || mv.microboard_col > 2
+            || mv.cell_row > 2
+            || mv.cell_col > 2
+            || self.playable_microboards() & bit(mv.microboard_row, mv.microboard_col) == 0
+            || player == Player::Empty
+        {
             return Err("Invalid move".to_string());
         }
 
         // Play the move on the specified microboard
-        let (microboard_row, microboard_col, cell_row, cell_col) = (
-            mv.microboard_row,
-            mv.microboard_col,
-            mv.cell_row,
-            mv.cell_col,
-        );
-        let microboard = &mut self.boards[microboard_row as usize][microboard_col as usize];
-        microboard.play(cell_row, cell_col, player)?;
+        let microboard = (mv.microboard_row * 3 + mv.microboard_col) as usize;
+        let cell = bit(mv.cell_row, mv.cell_col);
+        if (self.marks[0][microboard] | self.marks[1][microboard]) & cell != 0 {
+            return Err("Cell already occupied".to_string());
+        }
+        self.marks[Into::<u8>::into(player) as usize - 1][microboard] |= cell;
+        self.refresh_microboard(microboard);
+        self.moves += 1;
 
         // Set the previous move
-        self.next_microboard = Some((cell_row, cell_col));
+        let record = MoveRecord {
+            mv,
+            next_microboard: self.next_microboard,
+        };
+        self.hash ^= Self::cell_key(&mv, player)
+            ^ Self::next_key(self.next_microboard)
+            ^ Self::next_key(Some((mv.cell_row, mv.cell_col)));
+        self.next_microboard = Some((mv.cell_row, mv.cell_col));
+
+        Ok(record)
+    }
 
-        Ok(())
+    /// Clears the recorded cell and restores the previous microboard constraint.
+    fn undo(&mut self, record: MoveRecord) {
+        let mv = record.mv;
+        let microboard = (mv.microboard_row * 3 + mv.microboard_col) as usize;
+        let cell = bit(mv.cell_row, mv.cell_col);
+        let player = self
+            .get_microboard(mv.microboard_row, mv.microboard_col)
+            .get_cell(mv.cell_row, mv.cell_col);
+        if player == Player::Empty {
+            return;
+        }
+
+        self.hash ^= Self::cell_key(&mv, player)
+            ^ Self::next_key(self.next_microboard)
+            ^ Self::next_key(record.next_microboard);
+        self.marks[Into::<u8>::into(player) as usize - 1][microboard] &= !cell;
+        self.refresh_microboard(microboard);
+        self.moves -= 1;
+        self.next_microboard = record.next_microboard;
     }
 
     /// Returns the current status of the game.
     ///
     /// Checks for wins by examining if three microboards in a row have been won by the same player.
+    /// A line of three drawn microboards also ends the game in a draw.
     /// Returns `BoardStatus::Draw` if no moves are available and no player has won.
     fn get_status(&self) -> GameStatus {
-        // Check rows and columns
-        for i in 0..3 {
-            if self.boards[i][0].get_status() != GameStatus::InProgress
-                && self.boards[i][0].get_status() == self.boards[i][1].get_status()
-                && self.boards[i][1].get_status() == self.boards[i][2].get_status()
-            {
-                return self.boards[i][0].get_status();
-            }
-            if self.boards[0][i].get_status() != GameStatus::InProgress
-                && self.boards[0][i].get_status() == self.boards[1][i].get_status()
-                && self.boards[1][i].get_status() == self.boards[2][i].get_status()
-            {
-                return self.boards[0][i].get_status();
-            }
-        }
-        // Check diagonals
-        if self.boards[0][0].get_status() != GameStatus::InProgress
-            && self.boards[0][0].get_status() == self.boards[1][1].get_status()
-            && self.boards[1][1].get_status() == self.boards[2][2].get_status()
-        {
-            return self.boards[0][0].get_status();
+        if WINNING[self.won[0] as usize] {
+            return GameStatus::Win(Player::X.into());
         }
-        if self.boards[0][2].get_status() != GameStatus::InProgress
-            && self.boards[0][2].get_status() == self.boards[1][1].get_status()
-            && self.boards[1][1].get_status() == self.boards[2][0].get_status()
-        {
-            return self.boards[0][2].get_status();
+        if WINNING[self.won[1] as usize] {
+            return GameStatus::Win(Player::O.into());
         }
-
-        if self.get_available_moves().is_empty() {
+        if WINNING[self.drawn as usize] || self.open_microboards() == 0 {
             return GameStatus::Draw;
         }
 
         GameStatus::InProgress
     }
+
+    fn hash_key(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// The eight symmetries of the square board (the dihedral group of order 8).
+///
+/// Each symmetry acts on the full 9×9 grid, which maps microboards onto microboards and
+/// cells onto the matching cells, and on the `next_microboard` constraint.
+#[derive(..StdTraits, Debug, Serialize, Deserialize)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Reflects left to right
+    FlipHorizontal,
+    /// Reflects top to bottom
+    FlipVertical,
+    /// Reflects across the main diagonal
+    Transpose,
+    /// Reflects across the anti-diagonal
+    AntiTranspose,
+}
+
+impl Symmetry {
+    /// Maps a `(row, col)` coordinate on a `size`×`size` grid through the symmetry.
+    fn apply(self, row: u8, col: u8, size: u8) -> (u8, u8) {
+        let last = size - 1;
+        match self {
+            Symmetry::Identity => (row, col),
+            Symmetry::Rotate90 => (col, last - row),
+            Symmetry::Rotate180 => (last - row, last - col),
+            Symmetry::Rotate270 => (last - col, row),
+            Symmetry::FlipHorizontal => (row, last - col),
+            Symmetry::FlipVertical => (last - row, col),
+            Symmetry::Transpose => (col, row),
+            Symmetry::AntiTranspose => (last - col, last - row),
+        }
+    }
+}
+
+impl Symmetric for UltimateTTT {
+    type Transform = Symmetry;
+
+    fn symmetries() -> &'static [Symmetry] {
+        &[
+            Symmetry::Identity,
+            Symmetry::Rotate90,
+            Symmetry::Rotate180,
+            Symmetry::Rotate270,
+            Symmetry::FlipHorizontal,
+            Symmetry::FlipVertical,
+            Symmetry::Transpose,
+            Symmetry::AntiTranspose,
+        ]
+    }
+
+    fn inverse(transform: Symmetry) -> Symmetry {
+        match transform {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            transform => transform,
+        }
+    }
+
+    fn transform(&self, transform: Symmetry) -> Self {
+        let mut game = UltimateTTT::new();
+        for player in 0..2 {
+            for microboard in 0..9u8 {
+                for (cell_row, cell_col) in cells(self.marks[player][microboard as usize]) {
+                    let mv = Move::from((microboard / 3, microboard % 3, cell_row, cell_col));
+                    let mv = Self::transform_move(mv, transform);
+                    game.marks[player][(mv.microboard_row * 3 + mv.microboard_col) as usize] |=
+                        bit(mv.cell_row, mv.cell_col);
+                }
+            }
+        }
+        for microboard in 0..9 {
+            game.refresh_microboard(microboard);
+        }
+        game.moves = self.moves;
+        game.next_microboard = self
+            .next_microboard
+            .map(|(row, col)| transform.apply(row, col, 3));
+        game.hash = game.compute_hash();
+        game
+    }
+
+    fn transform_move(mv: Move, transform: Symmetry) -> Move {
+        let (row, col) = transform.apply(
+            mv.microboard_row * 3 + mv.cell_row,
+            mv.microboard_col * 3 + mv.cell_col,
+            9,
+        );
+        Move::from((row / 3, col / 3, row % 3, col % 3))
+    }
 }
 
 impl Default for UltimateTTT {
@@ -320,37 +559,57 @@ impl Default for UltimateTTT {
     }
 }
 
-impl Debug for UltimateTTT {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for i in 0..3 {
-            for row in 0..3 {
-                for j in 0..3 {
-                    for col in 0..3 {
-                        let cell = self.boards[i][j].grid[row][col];
-                        let symbol = match cell {
-                            Player::X => 'X',
-                            Player::O => 'O',
-                            Player::Empty => '-',
-                        };
-                        write!(f, "{} ", symbol)?;
-                    }
-                    write!(f, "  ")?;
-                }
-                writeln!(f)?;
+/// Layout of [`UltimateTTT`] in graph files written before the format had a header.
+///
+/// Kept frozen so those files can still be migrated; see
+/// [`graph_file`](crate::agents::graph_file).
+#[derive(..StdTraits, Debug, Deserialize)]
+pub struct LegacyUltimateTTT {
+    boards: [[LegacyMicroBoard; 3]; 3],
+    next_microboard: Option<(u8, u8)>,
+}
+
+/// Layout of a [`MicroBoard`] within a [`LegacyUltimateTTT`].
+#[derive(..StdTraits, Debug, Deserialize)]
+pub struct LegacyMicroBoard {
+    grid: [[Player; 3]; 3],
+}
+
+impl From<LegacyUltimateTTT> for UltimateTTT {
+    fn from(value: LegacyUltimateTTT) -> Self {
+        let mut game = UltimateTTT::new();
+        for (microboard, board) in value.boards.iter().flatten().enumerate() {
+            for (cell, &player) in board.grid.iter().flatten().enumerate() {
+                let player = match player {
+                    Player::X => 0,
+                    Player::O => 1,
+                    Player::Empty => continue,
+                };
+                game.marks[player][microboard] |= 1 << cell;
+                game.moves += 1;
             }
-            writeln!(f)?;
+            game.refresh_microboard(microboard);
         }
-        Ok(())
+        game.next_microboard = value.next_microboard;
+        game.hash = game.compute_hash();
+        game
+    }
+}
+
+impl Debug for UltimateTTT {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt::Display::fmt(self, f)
     }
 }
 
 impl fmt::Display for UltimateTTT {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let boards = self.get_microboards();
         for i in 0..3 {
             for row in 0..3 {
                 for j in 0..3 {
                     for col in 0..3 {
-                        let cell = self.boards[i][j].grid[row][col];
+                        let cell = boards[i][j].get_cell(row, col);
                         let symbol = match cell {
                             Player::X => 'X',
                             Player::O => 'O',
@@ -371,25 +630,37 @@ impl fmt::Display for UltimateTTT {
 /// A single 3×3 Tic-Tac-Toe board within the Ultimate Tic-Tac-Toe game.
 ///
 /// Each cell can be empty (0), occupied by player 1 (X), or occupied by player 2 (O).
+/// Microboards returned by [`UltimateTTT`] are copies; playing on them does not affect the game.
 #[derive(..StdTraits, Serialize, Deserialize)]
 pub struct MicroBoard {
-    grid: [[Player; 3]; 3],
+    /// Marks of X and O as 9-bit masks with bit `row * 3 + col`.
+    marks: [u16; 2],
 }
 
 impl MicroBoard {
     /// Creates a new empty microboard.
     pub fn new() -> Self {
-        MicroBoard {
-            grid: [[Player::Empty; 3]; 3],
-        }
+        MicroBoard { marks: [0; 2] }
     }
 
-    pub fn get_grid(&self) -> &[[Player; 3]; 3] {
-        &self.grid
+    pub fn get_grid(&self) -> [[Player; 3]; 3] {
+        let mut grid = [[Player::Empty; 3]; 3];
+        for (row, cells) in grid.iter_mut().enumerate() {
+            for (col, cell) in cells.iter_mut().enumerate() {
+                *cell = self.get_cell(row as u8, col as u8);
+            }
+        }
+        grid
     }
 
     pub fn get_cell(&self, row: u8, col: u8) -> Player {
-        self.grid[row as usize][col as usize]
+        if self.marks[0] & bit(row, col) != 0 {
+            Player::X
+        } else if self.marks[1] & bit(row, col) != 0 {
+            Player::O
+        } else {
+            Player::Empty
+        }
     }
 
     /// Returns the current status of this microboard.
@@ -397,53 +668,20 @@ impl MicroBoard {
     /// Checks for wins (three in a row) and returns the winning player.
     /// Returns `BoardStatus::Draw` if the board is full with no winner.
     pub fn get_status(&self) -> GameStatus {
-        // Check rows and columns for win
-        for i in 0..3 {
-            if self.grid[i][0] != Player::Empty
-                && self.grid[i][0] == self.grid[i][1]
-                && self.grid[i][1] == self.grid[i][2]
-            {
-                return GameStatus::Win(self.grid[i][0].into());
-            }
-            if self.grid[0][i] != Player::Empty
-                && self.grid[0][i] == self.grid[1][i]
-                && self.grid[1][i] == self.grid[2][i]
-            {
-                return GameStatus::Win(self.grid[0][i].into());
-            }
-        }
-        // Check diagonals
-        if self.grid[0][0] != Player::Empty
-            && self.grid[0][0] == self.grid[1][1]
-            && self.grid[1][1] == self.grid[2][2]
-        {
-            return GameStatus::Win(self.grid[0][0].into());
-        }
-        if self.grid[0][2] != Player::Empty
-            && self.grid[0][2] == self.grid[1][1]
-            && self.grid[1][1] == self.grid[2][0]
-        {
-            return GameStatus::Win(self.grid[0][2].into());
-        }
-
-        if self.get_available_moves().is_empty() {
-            return GameStatus::Draw;
+        if WINNING[self.marks[0] as usize] {
+            GameStatus::Win(Player::X.into())
+        } else if WINNING[self.marks[1] as usize] {
+            GameStatus::Win(Player::O.into())
+        } else if self.marks[0] | self.marks[1] == FULL {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
         }
-
-        GameStatus::InProgress
     }
 
     /// Returns all empty cells in this microboard as (row, col) tuples.
     pub fn get_available_moves(&self) -> Vec<(u8, u8)> {
-        let mut moves = Vec::new();
-        for i in 0..3 {
-            for j in 0..3 {
-                if self.grid[i][j] == Player::Empty {
-                    moves.push((i as u8, j as u8));
-                }
-            }
-        }
-        moves
+        cells(!(self.marks[0] | self.marks[1]) & FULL).collect()
     }
 
     /// Places a player's mark in the specified cell.
@@ -456,10 +694,165 @@ impl MicroBoard {
     /// # Errors
     /// Returns an error if the cell is already occupied.
     pub fn play(&mut self, row: u8, col: u8, player: Player) -> Result<(), String> {
-        if self.grid[row as usize][col as usize] != Player::Empty {
+        if self.get_cell(row, col) != Player::Empty {
             return Err("Cell already occupied".to_string());
         }
-        self.grid[row as usize][col as usize] = player;
+        match player {
+            Player::X => self.marks[0] |= bit(row, col),
+            Player::O => self.marks[1] |= bit(row, col),
+            Player::Empty => {}
+        }
         Ok(())
     }
 }
+
+mod test {
+    #[test]
+    fn test_undo_restores_positions() {
+        use super::UltimateTTT;
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            let mut game = UltimateTTT::new();
+            let mut history = vec![game];
+            let mut records = Vec::new();
+
+            while game.get_status() == GameStatus::InProgress {
+                let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+                records.push(game.make_move(mv, game.get_current_player()).unwrap());
+                history.push(game);
+            }
+
+            history.pop();
+            while let Some(record) = records.pop() {
+                game.undo(record);
+                assert_eq!(game, history.pop().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_key_has_no_collisions() {
+        use super::UltimateTTT;
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+        use std::collections::HashMap;
+
+        let mut rng = rand::rng();
+        let mut seen: HashMap<u64, UltimateTTT> = HashMap::new();
+        for _ in 0..500 {
+            let mut game = UltimateTTT::new();
+            while game.get_status() == GameStatus::InProgress {
+                let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+                game.play(mv, game.get_current_player()).unwrap();
+
+                assert_eq!(game.hash_key(), game.compute_hash());
+                if let Some(existing) = seen.insert(game.hash_key(), game) {
+                    assert_eq!(existing, game, "hash collision");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetries_commute_with_moves() {
+        use super::UltimateTTT;
+        use crate::{Game, GameStatus, Symmetric};
+        use rand::seq::IndexedRandom;
+
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            let mut game = UltimateTTT::new();
+            let mut transformed = UltimateTTT::symmetries()
+                .iter()
+                .map(|&t| (t, game.transform(t)))
+                .collect::<Vec<_>>();
+
+            while game.get_status() == GameStatus::InProgress {
+                let player = game.get_current_player();
+                let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+                game.play(mv, player).unwrap();
+
+                for (t, other) in transformed.iter_mut() {
+                    other
+                        .play(UltimateTTT::transform_move(mv, *t), player)
+                        .unwrap();
+                    assert_eq!(*other, game.transform(*t));
+                    assert_eq!(other.transform(UltimateTTT::inverse(*t)), game);
+                    assert_eq!(other.canonicalize().0, game.canonicalize().0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cached_status_matches_grid_scan() {
+        use super::{Player, UltimateTTT};
+        use crate::{Game, GameStatus};
+        use rand::seq::IndexedRandom;
+
+        // Reference implementation over a 3×3 grid of statuses or marks
+        fn line_status(cells: [[GameStatus; 3]; 3]) -> Option<GameStatus> {
+            let lines = [
+                [(0, 0), (0, 1), (0, 2)],
+                [(1, 0), (1, 1), (1, 2)],
+                [(2, 0), (2, 1), (2, 2)],
+                [(0, 0), (1, 0), (2, 0)],
+                [(0, 1), (1, 1), (2, 1)],
+                [(0, 2), (1, 2), (2, 2)],
+                [(0, 0), (1, 1), (2, 2)],
+                [(0, 2), (1, 1), (2, 0)],
+            ];
+            lines.iter().find_map(|line| {
+                let first = cells[line[0].0][line[0].1];
+                (first != GameStatus::InProgress && line.iter().all(|&(r, c)| cells[r][c] == first))
+                    .then_some(first)
+            })
+        }
+
+        let mut rng = rand::rng();
+        for _ in 0..200 {
+            let mut game = UltimateTTT::new();
+            while game.get_status() == GameStatus::InProgress {
+                let mv = *game.get_available_moves().choose(&mut rng).unwrap();
+                game.play(mv, game.get_current_player()).unwrap();
+
+                let boards = game.get_microboards();
+                let mut statuses = [[GameStatus::InProgress; 3]; 3];
+                for i in 0..3 {
+                    for j in 0..3 {
+                        let marks = boards[i][j].get_grid().map(|row| {
+                            row.map(|cell| match cell {
+                                Player::Empty => GameStatus::InProgress,
+                                player => GameStatus::Win(player.into()),
+                            })
+                        });
+                        statuses[i][j] = line_status(marks).unwrap_or(
+                            if boards[i][j].get_available_moves().is_empty() {
+                                GameStatus::Draw
+                            } else {
+                                GameStatus::InProgress
+                            },
+                        );
+                        assert_eq!(boards[i][j].get_status(), statuses[i][j]);
+                    }
+                }
+
+                let expected = line_status(statuses).unwrap_or(
+                    if statuses
+                        .iter()
+                        .flatten()
+                        .all(|s| *s != GameStatus::InProgress)
+                    {
+                        GameStatus::Draw
+                    } else {
+                        GameStatus::InProgress
+                    },
+                );
+                assert_eq!(game.get_status(), expected);
+            }
+        }
+    }
+}